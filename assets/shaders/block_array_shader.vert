@@ -0,0 +1,44 @@
+#version 450
+
+layout(location=0) in vec3 a_position;
+layout(location=1) in vec3 a_color;
+layout(location=2) in vec2 a_uv;
+layout(location=3) in uint a_layer;
+layout(location=4) in vec3 a_normal;
+layout(location=5) in float a_emission;
+layout(location=6) in vec2 a_light;
+
+layout(set=0, binding=0) uniform Uniforms {
+    mat4 u_view_proj;
+};
+
+struct ChunkInstance {
+    mat4 world;
+    vec4 fade;
+};
+
+layout(set=1, binding=0) readonly buffer Instances {
+    ChunkInstance u_instances[];
+};
+
+layout(location=0) out vec3 v_color;
+layout(location=1) out vec2 v_uv;
+layout(location=2) flat out uint v_layer;
+layout(location=3) out vec3 v_normal;
+layout(location=4) out vec3 v_world_pos;
+layout(location=5) flat out float v_fade;
+layout(location=6) flat out float v_emission;
+layout(location=7) out vec2 v_light;
+
+void main() {
+    v_color = a_color;
+    v_uv = a_uv;
+    v_emission = a_emission;
+    v_light = a_light;
+    v_fade = u_instances[gl_InstanceIndex].fade.x;
+    v_normal = mat3(u_instances[gl_InstanceIndex].world) * a_normal;
+    v_layer = a_layer;
+    vec4 world_pos = u_instances[gl_InstanceIndex].world * vec4(a_position, 1.0);
+    v_world_pos = world_pos.xyz;
+    gl_Position = u_view_proj * world_pos;
+}
\ No newline at end of file
@@ -2,12 +2,104 @@
 
 layout(location=0) in vec3 v_color;
 layout(location=1) in vec2 v_uv;
+layout(location=2) in vec3 v_normal;
+layout(location=3) in vec3 v_world_pos;
+layout(location=4) flat in float v_fade;
+layout(location=5) flat in float v_emission;
+layout(location=6) in vec2 v_light;
+
+layout(set=0, binding=1) uniform Light {
+    vec4 u_sun_dir;
+    vec4 u_sun_color;
+    vec4 u_ambient_color;
+};
+
+layout(set=0, binding=2) uniform Fog {
+    vec4 u_fog_color;
+    vec4 u_fog_params; // start, end, density, mode (0: none, 1: linear, 2: exp, 3: exp2)
+};
 
 layout(set = 2, binding = 0) uniform texture2D t_diffuse;
 layout(set = 2, binding = 1) uniform sampler s_diffuse;
+layout(set = 2, binding = 2) uniform AlphaCutoff {
+    float u_alpha_cutoff;
+};
+
+layout(set = 3, binding = 0) uniform texture2DArray t_shadow;
+layout(set = 3, binding = 1) uniform samplerShadow s_shadow;
+layout(set = 3, binding = 2) uniform Shadow {
+    mat4 u_cascades[4];
+    vec4 u_cascade_splits;
+    vec4 u_shadow_params; // x: cascade count, y: texel size
+};
 
 layout(location=0) out vec4 f_color;
 
+const float EMISSION_STRENGTH = 4.0;
+const vec3 BLOCK_LIGHT_COLOR = vec3(1.0, 0.85, 0.6);
+
+// 0 : clear, 1 : fully fogged
+float fog_factor(float depth) {
+    int mode = int(u_fog_params.w);
+    float dist = max(depth - u_fog_params.x, 0.0);
+    if (mode == 1) {
+        return clamp(dist / (u_fog_params.y - u_fog_params.x), 0.0, 1.0);
+    } else if (mode == 2) {
+        return 1.0 - exp(-u_fog_params.z * dist);
+    } else if (mode == 3) {
+        float d = u_fog_params.z * dist;
+        return 1.0 - exp(-d * d);
+    }
+    return 0.0;
+}
+
+// ordered dither threshold of pixel. fading chunks are drawn partially without sorting
+float dither_threshold() {
+    const float bayer[16] = float[16](0.0, 8.0, 2.0, 10.0, 12.0, 4.0, 14.0, 6.0, 3.0, 11.0, 1.0, 9.0, 15.0, 7.0, 13.0, 5.0);
+    ivec2 p = ivec2(gl_FragCoord.xy) % 4;
+    return (bayer[p.y * 4 + p.x] + 0.5) / 16.0;
+}
+
+// light level (0 to 1) to brightness. each level is 80% of level above it
+float light_curve(float level) {
+    return level > 0.0 ? pow(0.8, (1.0 - level) * 15.0) : 0.0;
+}
+
+// 1 : lit, 0 : in shadow. 3x3 pcf in first cascade covering fragment
+float shadow_factor() {
+    float depth = 1.0 / gl_FragCoord.w;
+    int count = int(u_shadow_params.x);
+    for (int i = 0; i < count; ++i) {
+        if (depth < u_cascade_splits[i]) {
+            vec4 pos = u_cascades[i] * vec4(v_world_pos, 1.0);
+            vec2 uv = vec2(pos.x * 0.5 + 0.5, pos.y * -0.5 + 0.5);
+            float lit = 0.0;
+            for (int x = -1; x <= 1; ++x) {
+                for (int y = -1; y <= 1; ++y) {
+                    vec2 offset = vec2(x, y) * u_shadow_params.y;
+                    lit += texture(sampler2DArrayShadow(t_shadow, s_shadow), vec4(uv + offset, float(i), pos.z));
+                }
+            }
+            return lit / 9.0;
+        }
+    }
+    return 1.0;
+}
+
 void main() {
-    f_color = vec4(v_color, 1.0) * texture(sampler2D(t_diffuse, s_diffuse), v_uv);
+    if (v_fade < dither_threshold()) {
+        discard;
+    }
+
+    // sun and ambient reach only as far as sky light, so caves are dark except near torches
+    // emissive blocks are brighter than lit ones, so bloom picks them up
+    vec3 sky = u_ambient_color.rgb + u_sun_color.rgb * max(dot(normalize(v_normal), -u_sun_dir.xyz), 0.0) * shadow_factor();
+    vec3 light = sky * light_curve(v_light.x) + BLOCK_LIGHT_COLOR * light_curve(v_light.y);
+    light += v_emission * EMISSION_STRENGTH;
+    vec4 color = vec4(v_color * light, 1.0) * texture(sampler2D(t_diffuse, s_diffuse), v_uv);
+    if (color.a < u_alpha_cutoff) {
+        discard;
+    }
+    color.rgb = mix(color.rgb, u_fog_color.rgb, fog_factor(1.0 / gl_FragCoord.w));
+    f_color = color;
 }
\ No newline at end of file
@@ -0,0 +1,28 @@
+#version 450
+
+layout(location=0) in vec2 v_uv;
+
+layout(location=0) out vec4 f_color;
+
+layout(set=0, binding=0) uniform texture2D t_source;
+layout(set=0, binding=1) uniform sampler s_linear;
+
+layout(set=1, binding=0) uniform Post {
+    vec4 u_params; // exposure, bloom threshold, bloom intensity, color lut enabled
+    vec4 u_texel;  // xy : texel size of frame, zw : texel size of bloom
+};
+
+layout(set=2, binding=0) uniform Blur {
+    vec4 u_blur_dir; // xy : (1, 0) or (0, 1)
+};
+
+void main() {
+    // 9 tap gaussian in 5 bilinear taps
+    vec2 dir = u_blur_dir.xy * u_texel.zw;
+    vec3 color = texture(sampler2D(t_source, s_linear), v_uv).rgb * 0.2270270270;
+    color += texture(sampler2D(t_source, s_linear), v_uv + dir * 1.3846153846).rgb * 0.3162162162;
+    color += texture(sampler2D(t_source, s_linear), v_uv - dir * 1.3846153846).rgb * 0.3162162162;
+    color += texture(sampler2D(t_source, s_linear), v_uv + dir * 3.2307692308).rgb * 0.0702702703;
+    color += texture(sampler2D(t_source, s_linear), v_uv - dir * 3.2307692308).rgb * 0.0702702703;
+    f_color = vec4(color, 1.0);
+}
\ No newline at end of file
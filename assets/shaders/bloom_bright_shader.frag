@@ -0,0 +1,28 @@
+#version 450
+
+layout(location=0) in vec2 v_uv;
+
+layout(location=0) out vec4 f_color;
+
+layout(set=0, binding=0) uniform texture2D t_scene;
+layout(set=0, binding=1) uniform sampler s_linear;
+
+layout(set=1, binding=0) uniform Post {
+    vec4 u_params; // exposure, bloom threshold, bloom intensity, color lut enabled
+    vec4 u_texel;  // xy : texel size of frame, zw : texel size of bloom
+};
+
+void main() {
+    // 4 bilinear taps cover 4x4 texels of scene
+    vec2 o = u_texel.xy;
+    vec3 color = texture(sampler2D(t_scene, s_linear), v_uv + vec2(-o.x, -o.y)).rgb;
+    color += texture(sampler2D(t_scene, s_linear), v_uv + vec2(o.x, -o.y)).rgb;
+    color += texture(sampler2D(t_scene, s_linear), v_uv + vec2(-o.x, o.y)).rgb;
+    color += texture(sampler2D(t_scene, s_linear), v_uv + vec2(o.x, o.y)).rgb;
+    color *= 0.25 * u_params.x;
+
+    // only brightness over threshold glows
+    float brightness = max(color.r, max(color.g, color.b));
+    float contribution = max(brightness - u_params.y, 0.0) / max(brightness, 0.0001);
+    f_color = vec4(color * contribution, 1.0);
+}
\ No newline at end of file
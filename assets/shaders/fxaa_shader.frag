@@ -0,0 +1,56 @@
+#version 450
+
+layout(location=0) in vec2 v_uv;
+
+layout(location=0) out vec4 f_color;
+
+layout(set=0, binding=0) uniform texture2D t_ldr;
+layout(set=0, binding=1) uniform sampler s_linear;
+
+layout(set=1, binding=0) uniform Post {
+    vec4 u_params; // exposure, bloom threshold, bloom intensity, color lut enabled
+    vec4 u_texel;  // xy : texel size of frame, zw : texel size of bloom
+};
+
+const float REDUCE_MIN = 1.0 / 128.0;
+const float REDUCE_MUL = 1.0 / 8.0;
+const float SPAN_MAX = 8.0;
+
+vec3 sample_ldr(vec2 uv) {
+    return texture(sampler2D(t_ldr, s_linear), uv).rgb;
+}
+
+// perceptual luma. ldr texture is srgb, so sampled color is linear
+float luma(vec3 color) {
+    return sqrt(dot(color, vec3(0.299, 0.587, 0.114)));
+}
+
+void main() {
+    vec2 texel = u_texel.xy;
+    vec3 rgb_m = sample_ldr(v_uv);
+    float luma_nw = luma(sample_ldr(v_uv + vec2(-1.0, -1.0) * texel));
+    float luma_ne = luma(sample_ldr(v_uv + vec2(1.0, -1.0) * texel));
+    float luma_sw = luma(sample_ldr(v_uv + vec2(-1.0, 1.0) * texel));
+    float luma_se = luma(sample_ldr(v_uv + vec2(1.0, 1.0) * texel));
+    float luma_m = luma(rgb_m);
+
+    float luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+    float luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+    // blur along edge
+    vec2 dir = vec2(-((luma_nw + luma_ne) - (luma_sw + luma_se)), (luma_nw + luma_sw) - (luma_ne + luma_se));
+    float dir_reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+    float rcp_dir_min = 1.0 / (min(abs(dir.x), abs(dir.y)) + dir_reduce);
+    dir = clamp(dir * rcp_dir_min, vec2(-SPAN_MAX), vec2(SPAN_MAX)) * texel;
+
+    vec3 rgb_a = 0.5 * (sample_ldr(v_uv + dir * (1.0 / 3.0 - 0.5)) + sample_ldr(v_uv + dir * (2.0 / 3.0 - 0.5)));
+    vec3 rgb_b = rgb_a * 0.5 + 0.25 * (sample_ldr(v_uv - dir * 0.5) + sample_ldr(v_uv + dir * 0.5));
+    float luma_b = luma(rgb_b);
+
+    // wider blur crossed another edge
+    if (luma_b < luma_min || luma_b > luma_max) {
+        f_color = vec4(rgb_a, 1.0);
+    } else {
+        f_color = vec4(rgb_b, 1.0);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,10 @@
+#version 450
+
+layout(location=0) out vec2 v_uv;
+
+void main() {
+    // 1 triangle covering whole screen
+    vec2 ndc = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2) * 2.0 - 1.0;
+    v_uv = vec2(ndc.x * 0.5 + 0.5, ndc.y * -0.5 + 0.5);
+    gl_Position = vec4(ndc, 0.0, 1.0);
+}
\ No newline at end of file
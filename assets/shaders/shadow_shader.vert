@@ -0,0 +1,20 @@
+#version 450
+
+layout(location=0) in vec3 a_position;
+
+layout(set=0, binding=0) uniform Cascade {
+    mat4 u_light_view_proj;
+};
+
+struct ChunkInstance {
+    mat4 world;
+    vec4 fade;
+};
+
+layout(set=1, binding=0) readonly buffer Instances {
+    ChunkInstance u_instances[];
+};
+
+void main() {
+    gl_Position = u_light_view_proj * u_instances[gl_InstanceIndex].world * vec4(a_position, 1.0);
+}
\ No newline at end of file
@@ -0,0 +1,12 @@
+#version 450
+
+layout(location=0) in vec3 v_view_dir;
+
+layout(location=0) out vec4 f_color;
+
+layout(set=1, binding=0) uniform textureCube t_sky;
+layout(set=1, binding=1) uniform sampler s_sky;
+
+void main() {
+    f_color = vec4(texture(samplerCube(t_sky, s_sky), normalize(v_view_dir)).rgb, 1.0);
+}
\ No newline at end of file
@@ -0,0 +1,31 @@
+#version 450
+
+layout(location=0) in vec3 v_view_dir;
+
+layout(location=0) out vec4 f_color;
+
+layout(set=0, binding=0) uniform Sky {
+    mat4 u_inv_view_proj;
+    vec4 u_eye;
+    vec4 u_zenith_color;
+    vec4 u_horizon_color;
+    vec4 u_to_sun;
+    vec4 u_sun_color; // w : cos of sun disc radius
+};
+
+void main() {
+    vec3 dir = normalize(v_view_dir);
+
+    // gradient from horizon to zenith. below horizon stays horizon color
+    float height = sqrt(clamp(dir.y, 0.0, 1.0));
+    vec3 color = mix(u_horizon_color.rgb, u_zenith_color.rgb, height);
+
+    // sun disc with soft edge and glow around it
+    float cos_angle = dot(dir, u_to_sun.xyz);
+    float cos_radius = u_sun_color.w;
+    float disc = smoothstep(cos_radius - (1.0 - cos_radius) * 0.5, cos_radius, cos_angle);
+    float glow = pow(max(cos_angle, 0.0), 64.0) * 0.3;
+    color += u_sun_color.rgb * (disc + glow);
+
+    f_color = vec4(color, 1.0);
+}
\ No newline at end of file
@@ -0,0 +1,22 @@
+#version 450
+
+layout(location=0) out vec3 v_view_dir;
+
+layout(set=0, binding=0) uniform Sky {
+    mat4 u_inv_view_proj;
+    vec4 u_eye;
+    vec4 u_zenith_color;
+    vec4 u_horizon_color;
+    vec4 u_to_sun;
+    vec4 u_sun_color; // w : cos of sun disc radius
+};
+
+void main() {
+    // 1 triangle covering whole screen
+    vec2 ndc = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2) * 2.0 - 1.0;
+
+    vec4 far = u_inv_view_proj * vec4(ndc, 1.0, 1.0);
+    v_view_dir = far.xyz / far.w - u_eye.xyz;
+
+    gl_Position = vec4(ndc, 1.0, 1.0);
+}
\ No newline at end of file
@@ -0,0 +1,49 @@
+#version 450
+
+layout(location=0) in vec2 v_uv;
+
+layout(location=0) out vec4 f_color;
+
+layout(set=0, binding=0) uniform texture2D t_scene;
+layout(set=0, binding=1) uniform texture2D t_bloom;
+layout(set=0, binding=2) uniform sampler s_linear;
+
+layout(set=1, binding=0) uniform Post {
+    vec4 u_params; // exposure, bloom threshold, bloom intensity, color lut enabled
+    vec4 u_texel;  // xy : texel size of frame, zw : texel size of bloom
+};
+
+// n slices of n x n side by side. maps srgb encoded color to srgb encoded color
+layout(set=2, binding=0) uniform texture2D t_lut;
+layout(set=2, binding=1) uniform sampler s_lut;
+
+// aces filmic curve fitted by Krzysztof Narkowicz
+vec3 aces(vec3 x) {
+    return clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), 0.0, 1.0);
+}
+
+vec3 linear_to_srgb(vec3 color) {
+    return mix(color * 12.92, 1.055 * pow(color, vec3(1.0 / 2.4)) - 0.055, step(0.0031308, color));
+}
+
+// lut is srgb texture, so graded color is linear again
+vec3 grade(vec3 color) {
+    float n = float(textureSize(sampler2D(t_lut, s_lut), 0).y);
+    vec3 cell = linear_to_srgb(color) * (n - 1.0);
+    float slice = floor(cell.b);
+    vec2 uv = vec2((slice * n + cell.r + 0.5) / (n * n), (cell.g + 0.5) / n);
+    vec3 lower = textureLod(sampler2D(t_lut, s_lut), uv, 0.0).rgb;
+    vec3 upper = textureLod(sampler2D(t_lut, s_lut), uv + vec2(1.0 / n, 0.0), 0.0).rgb;
+    return mix(lower, upper, cell.b - slice);
+}
+
+void main() {
+    vec3 hdr = texture(sampler2D(t_scene, s_linear), v_uv).rgb * u_params.x;
+    hdr += texture(sampler2D(t_bloom, s_linear), v_uv).rgb * u_params.z;
+
+    vec3 color = aces(hdr);
+    if (u_params.w > 0.5) {
+        color = grade(color);
+    }
+    f_color = vec4(color, 1.0);
+}
\ No newline at end of file
@@ -1,13 +1,17 @@
+use std::sync::Arc;
+
 use legion::*;
 use voxrs_asset::{AssetManager, AssetPath};
-use voxrs_core::res::{CameraRes, ElapsedTimeRes, KeyInputRes, WorldBlockRes};
-use voxrs_render::blueprint::Blueprint;
+use voxrs_core::res::{CameraRes, ElapsedTimeRes, KeyInputRes, StreamSettings, TerrainProvider, WorldBlockRes};
+use voxrs_render::blueprint::{Blueprint, Environment};
 use voxrs_types::{io::FileSystem, Clock};
 
 use voxrs_math::*;
 use winit::event::{ElementState, KeyboardInput};
 
-use super::system::{camera, world_block_render};
+use super::system::{camera, world_block_render, world_block_stream};
+
+const TERRAIN_SEED: u32 = 1;
 
 pub struct Game {
     world: World,
@@ -23,11 +27,23 @@ impl Game {
         let world = World::default();
         let mut res = Resources::default();
 
-        let world_block_res = WorldBlockRes::new(&AssetPath::from("assets/world_01.wb"), asset_manager);
+        // terrain is generated around camera. modified chunks are saved when they are evicted
+        let provider = Arc::new(TerrainProvider::new("saves/world_stream", TERRAIN_SEED));
+        let settings = StreamSettings {
+            load_radius: 6,
+            unload_radius: 7,
+            integrate_budget: 4,
+        };
+        let world_block_res = WorldBlockRes::new_streaming(
+            &AssetPath::from("assets/world_stream.wb"),
+            asset_manager,
+            provider,
+            settings,
+        );
         res.insert(world_block_res);
 
         let camera = CameraRes::new(
-            Vec3::new(3.5, 3.5, -10.0),
+            Vec3::new(3.5, 10.0, -10.0),
             Angle::from_degrees(0.0),
             Angle::from_degrees(0.0),
             width,
@@ -41,7 +57,22 @@ impl Game {
         let key_input = KeyInputRes::new();
         res.insert(key_input);
 
-        let tick_schedule = Schedule::builder().add_system(camera::camera_move_system()).build();
+        // late afternoon sun from behind camera
+        let environment = Environment {
+            sky_color: Vec3::new(0.25, 0.45, 0.8),
+            horizon_color: Vec3::new(0.45, 0.65, 0.9),
+            sun_dir: Vec3::new(-0.4, -0.8, 0.45).normalize(),
+            sun_color: Vec3::new(0.75, 0.7, 0.6),
+            sun_size: 0.03,
+            ambient_color: Vec3::new(0.35, 0.4, 0.45),
+            skybox: None,
+        };
+        res.insert(environment);
+
+        let tick_schedule = Schedule::builder()
+            .add_system(camera::camera_move_system())
+            .add_system(world_block_stream::world_block_stream_system())
+            .build();
 
         let render_schedule = Schedule::builder()
             .add_system(camera::camera_render_system())
@@ -89,6 +120,12 @@ impl Game {
         self.tick_schedule.execute(&mut self.world, &mut self.res);
     }
 
+    /// save streamed world before exit
+    pub fn shutdown(&mut self) {
+        let mut world_block_res = self.res.get_mut::<WorldBlockRes>().unwrap();
+        world_block_res.flush_streaming();
+    }
+
     #[profiling::function]
     pub fn render(&mut self) -> Blueprint {
         self.res.insert(Blueprint::new());
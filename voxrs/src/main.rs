@@ -40,6 +40,7 @@ fn main() {
             _ => {}
         },
         Event::RedrawRequested(_) => {}
+        Event::LoopDestroyed => game.shutdown(),
         Event::MainEventsCleared => {
             game.tick();
 
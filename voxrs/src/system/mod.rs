@@ -1,3 +1,4 @@
 pub mod camera;
 
 pub mod world_block_render;
+pub mod world_block_stream;
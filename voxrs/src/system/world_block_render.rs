@@ -1,17 +1,23 @@
 use legion::system;
 use voxrs_core::res::{CameraRes, WorldBlockRes};
-use voxrs_render::blueprint::Blueprint;
+use voxrs_render::blueprint::{Blueprint, Environment};
 
 #[system]
 pub fn world_block_render(
     #[resource] world_block_res: &WorldBlockRes,
     #[resource] camera_res: &CameraRes,
+    #[resource] environment: &Environment,
     #[resource] bp: &mut Blueprint,
 ) {
+    bp.set_environment(environment.clone());
+
     let asset = world_block_res.handle.get_asset();
 
     bp.set_world_mat(asset.world_material.clone());
     bp.set_block_size(asset.block_size.to_f32());
+    if let Some(fog) = &asset.fog {
+        bp.set_fog(fog.into());
+    }
 
     let culled_chunks = world_block_res.frustum_culling(camera_res);
 
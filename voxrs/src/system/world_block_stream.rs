@@ -0,0 +1,7 @@
+use legion::system;
+use voxrs_core::res::{CameraRes, WorldBlockRes};
+
+#[system]
+pub fn world_block_stream(#[resource] world_block_res: &mut WorldBlockRes, #[resource] camera_res: &CameraRes) {
+    world_block_res.update_streaming(camera_res);
+}
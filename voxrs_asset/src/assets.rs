@@ -7,6 +7,7 @@ use crate::{handle::AssetLoadError, AssetManager, AssetPath};
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum AssetType {
     Texture,
+    Cubemap,
     Text,
     Shader,
     Material,
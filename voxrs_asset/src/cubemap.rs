@@ -0,0 +1,83 @@
+use serde::Deserialize;
+use voxrs_rhi::Texture;
+use voxrs_types::io::FileSystem;
+
+use crate::handle::AssetLoadError;
+
+use super::{
+    assets::{Asset, AssetType},
+    AssetHandle, AssetManager, TextureAsset,
+};
+
+/// 6 face textures of cube. +x, -x, +y, -y, +z, -z
+#[derive(Asset)]
+pub struct CubemapAsset {
+    pub faces: [AssetHandle<TextureAsset>; 6],
+}
+
+#[derive(Deserialize)]
+struct CubemapAssetRaw {
+    px: String,
+    nx: String,
+    py: String,
+    ny: String,
+    pz: String,
+    nz: String,
+}
+
+impl CubemapAsset {
+    pub fn new<F: FileSystem>(s: &str, asset_manager: &mut AssetManager<F>) -> Self {
+        let raw: CubemapAssetRaw = serde_json::from_str(s).unwrap();
+
+        let mut get = |path: String| asset_manager.get::<TextureAsset>(&path.into());
+        let faces = [
+            get(raw.px),
+            get(raw.nx),
+            get(raw.py),
+            get(raw.ny),
+            get(raw.pz),
+            get(raw.nz),
+        ];
+
+        Self { faces }
+    }
+
+    async fn load_asset<F: voxrs_types::io::FileSystem>(
+        path: &crate::AssetPath,
+        manager: &mut crate::AssetManager<F>,
+        _device: Option<&wgpu::Device>,
+        _queue: Option<&wgpu::Queue>,
+    ) -> Result<Self, crate::handle::AssetLoadError> {
+        let result;
+        if let Ok(s) = F::read_text(path).await {
+            result = Ok(CubemapAsset::new(&s, manager));
+        } else {
+            result = Err(AssetLoadError::Failed);
+        }
+        result
+    }
+
+    /// true if build_texture doesn't block. faces can be failed to load
+    pub fn is_loaded(&self) -> bool {
+        self.faces.iter().all(|face| face.is_loaded())
+    }
+
+    /// cube texture from faces. blocks until faces are loaded
+    /// error if any face is failed to load
+    pub fn build_texture(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> anyhow::Result<Texture> {
+        let assets = self.faces.iter().map(|face| face.get_asset()).collect::<Vec<_>>();
+        let bytes = assets
+            .iter()
+            .zip(&self.faces)
+            .map(|(asset, face)| match asset.get_asset() {
+                Some(asset) => Ok(asset.buf.as_slice()),
+                None => Err(anyhow::anyhow!(
+                    "cubemap face is not loaded. path: {:?}",
+                    face.asset_path()
+                )),
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Texture::cube_from_bytes(device, queue, &bytes, "cubemap")
+    }
+}
@@ -42,6 +42,22 @@ impl<T: Asset + 'static> AssetHandle<T> {
         AssetWLock::new(wlock)
     }
 
+    /// true if get_asset doesn't block. loaded asset can be failed to build
+    pub fn is_loaded(&self) -> bool {
+        self.loader.0.state().done() || !self.loader.1.is_empty()
+    }
+
+    /// true if asset is loaded but failed (ex: file is not found). deref of get_asset panics
+    /// never blocks before asset is loaded
+    pub fn is_failed(&self) -> bool {
+        if !self.is_loaded() {
+            return false;
+        }
+
+        self.load_asset();
+        self.asset.read().is_none()
+    }
+
     fn load_asset(&self) {
         self.loader.0.call_once(|| {
             if let Ok(Ok(asset)) = self.loader.1.recv() {
@@ -193,6 +209,34 @@ mod tests {
         assert_eq!(asset.text, "text");
     }
 
+    #[test]
+    fn test_is_loaded() {
+        let (s, r) = crossbeam_channel::unbounded();
+        let handle = AssetHandle::<TextAsset>::new(&"path".into(), r);
+        assert!(!handle.is_loaded());
+
+        let _ = s.send(Ok(TextAsset::new("text".to_string())));
+        assert!(handle.is_loaded());
+        assert_eq!(handle.get_asset().text, "text");
+        assert!(handle.is_loaded());
+    }
+
+    #[test]
+    fn test_is_failed() {
+        let (s, r) = crossbeam_channel::unbounded();
+        let handle = AssetHandle::<TextAsset>::new(&"path".into(), r);
+        assert!(!handle.is_failed());
+
+        let _ = s.send(Err(AssetLoadError::Failed));
+        assert!(handle.is_loaded());
+        assert!(handle.is_failed());
+        assert!(handle.get_asset().get_asset().is_none());
+
+        let handle = prepare_text_asset();
+        assert_eq!(handle.get_asset().text, "text");
+        assert!(!handle.is_failed());
+    }
+
     #[test]
     fn change_asset_test() {
         let mut handle = prepare_text_asset();
@@ -6,6 +6,7 @@ mod assets;
 mod handle;
 mod manager;
 
+mod cubemap;
 mod material;
 mod shader;
 mod text;
@@ -22,14 +23,20 @@ pub use handle::AssetHandle;
 pub use manager::AssetHash;
 pub use manager::AssetManager;
 
+pub use cubemap::CubemapAsset;
+pub use material::MaterialAlpha;
 pub use material::MaterialAsset;
 pub use shader::ShaderAsset;
 pub use text::TextAsset;
 pub use texture::TextureAsset;
+pub use world_block::build_vis;
 pub use world_block::BlockSize;
+pub use world_block::FogMode;
 pub use world_block::WorldBlockAsset;
 pub use world_block::WorldBlockAssetRaw;
 pub use world_block::WorldChunkRaw;
+pub use world_block::WorldFog;
+pub use world_material::TextureArrayShaders;
 pub use world_material::WorldMaterialAsset;
 
 mod font;
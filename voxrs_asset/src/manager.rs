@@ -10,7 +10,8 @@ use voxrs_types::io::FileSystem;
 use super::{
     assets::{Asset, AssetType},
     handle::{AssetHandle, AssetLoadError},
-    AssetPath, FontAsset, MaterialAsset, ShaderAsset, TextAsset, TextureAsset, WorldBlockAsset, WorldMaterialAsset,
+    AssetPath, CubemapAsset, FontAsset, MaterialAsset, ShaderAsset, TextAsset, TextureAsset, WorldBlockAsset,
+    WorldMaterialAsset,
 };
 pub struct AssetManager<F: FileSystem + 'static> {
     internal: Arc<Mutex<AssetManagerInternal<F>>>,
@@ -58,6 +59,7 @@ impl<'wgpu, F: FileSystem + 'static> Clone for AssetManager<F> {
 pub struct AssetManagerInternal<F: FileSystem + 'static> {
     text_assets: HashMap<AssetHash, AssetHandle<TextAsset>>,
     texture_assets: HashMap<AssetHash, AssetHandle<TextureAsset>>,
+    cubemap_assets: HashMap<AssetHash, AssetHandle<CubemapAsset>>,
     shader_assets: HashMap<AssetHash, AssetHandle<ShaderAsset>>,
     material_assets: HashMap<AssetHash, AssetHandle<MaterialAsset>>,
     world_material_assets: HashMap<AssetHash, AssetHandle<WorldMaterialAsset>>,
@@ -82,6 +84,7 @@ impl<'wgpu, F: FileSystem + 'static> AssetManagerInternal<F> {
         Self {
             text_assets: HashMap::new(),
             texture_assets: HashMap::new(),
+            cubemap_assets: HashMap::new(),
             shader_assets: HashMap::new(),
             material_assets: HashMap::new(),
             world_material_assets: HashMap::new(),
@@ -129,6 +132,9 @@ impl<'wgpu, F: FileSystem + 'static> AssetManagerInternal<F> {
             AssetType::Texture => {
                 self.texture_assets.insert(hash, handle.downcast_ref().clone());
             }
+            AssetType::Cubemap => {
+                self.cubemap_assets.insert(hash, handle.downcast_ref().clone());
+            }
             AssetType::Text => {
                 self.text_assets.insert(hash, handle.downcast_ref().clone());
             }
@@ -183,6 +189,10 @@ impl<'wgpu, F: FileSystem + 'static> AssetManagerInternal<F> {
                 let handle = self.texture_assets.get(hash)?;
                 Some(handle.downcast_ref())
             }
+            AssetType::Cubemap => {
+                let handle = self.cubemap_assets.get(hash)?;
+                Some(handle.downcast_ref())
+            }
             AssetType::Shader => {
                 let handle = self.shader_assets.get(hash)?;
                 Some(handle.downcast_ref())
@@ -264,6 +274,18 @@ mod tests {
         assert_eq!(texture_asset.buf, include_bytes!("../../test_assets/texture.png"));
     }
 
+    #[test]
+    fn get_cubemap() {
+        let mut manager = AssetManager::<MockFileSystem>::new();
+        let handle: AssetHandle<CubemapAsset> = manager.get(&AssetPath::from("skybox.cube"));
+        let cubemap_asset = handle.get_asset();
+
+        for face in &cubemap_asset.faces {
+            assert_eq!(face.get_asset().buf, include_bytes!("../../test_assets/texture.png"));
+        }
+        assert!(cubemap_asset.is_loaded());
+    }
+
     #[test]
     fn get_material() {
         let mut manager = AssetManager::<MockFileSystem>::new();
@@ -283,6 +305,12 @@ mod tests {
 
         asset.material_handles.get(&1).unwrap();
         asset.material_handles.get(&10).unwrap();
+        assert!(asset.texture_array.is_none());
+
+        let layers = asset.texture_layers();
+        assert_eq!(layers.len(), 2);
+        assert_eq!(layers[&1], 0);
+        assert_eq!(layers[&10], 1);
     }
 
     #[test]
@@ -1,4 +1,5 @@
 use serde::Deserialize;
+use voxrs_math::Occlusion;
 use voxrs_types::io::FileSystem;
 
 use crate::{handle::AssetLoadError, ShaderAsset};
@@ -14,6 +15,7 @@ pub struct MaterialAsset {
     pub vertex_shader: AssetHandle<ShaderAsset>,
     pub frag_shader: AssetHandle<ShaderAsset>,
     pub alpha: MaterialAlpha,
+    pub emission: u8, // block light level emitted by block with this material
 }
 
 #[derive(Deserialize)]
@@ -22,15 +24,27 @@ struct MaterialAssetRaw {
     vertex_shader: String,
     frag_shader: String,
     alpha: MaterialAlpha,
+    #[serde(default)]
+    emission: u8,
 }
 
-#[derive(Deserialize, Copy, Clone, Debug)]
+#[derive(Deserialize, Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum MaterialAlpha {
     NoAlpha,
     OneBit,
     FullAlpha,
 }
 
+impl MaterialAlpha {
+    /// blocks with alpha don't hide faces of other blocks
+    pub fn occlusion(self) -> Occlusion {
+        match self {
+            MaterialAlpha::NoAlpha => Occlusion::Opaque,
+            MaterialAlpha::OneBit | MaterialAlpha::FullAlpha => Occlusion::Transparent,
+        }
+    }
+}
+
 impl MaterialAsset {
     pub fn new<F: FileSystem>(s: &str, asset_manager: &mut AssetManager<F>) -> Self {
         let raw: MaterialAssetRaw = serde_json::from_str(s).unwrap();
@@ -44,6 +58,7 @@ impl MaterialAsset {
             vertex_shader,
             frag_shader,
             alpha: raw.alpha,
+            emission: raw.emission,
         }
     }
 
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use enumflags2::BitFlags;
 use serde::{Deserialize, Serialize};
 use voxrs_types::io::FileSystem;
@@ -13,10 +15,11 @@ use super::{
 
 #[derive(Asset)]
 pub struct WorldBlockAsset {
-    pub chunk_counts: WorldChunkCounts,
+    pub chunk_counts: Option<WorldChunkCounts>, // None if world is unbounded
     pub block_size: BlockSize,
     pub world_material: AssetHandle<WorldMaterialAsset>,
-    pub world_chunks: Vec<Option<WorldChunk>>, // x, y, z order. None if all empty chunk
+    pub world_chunks: HashMap<ChunkPos, WorldChunk>, // no entry if all empty chunk. moved out when world is built
+    pub fog: Option<WorldFog>,                       // None if world has no fog
 }
 
 impl WorldBlockAsset {
@@ -39,94 +42,75 @@ impl WorldBlockAsset {
         let raw: WorldBlockAssetRaw = serde_json::from_str(s).unwrap();
         raw.validate();
 
-        let chunk_counts: WorldChunkCounts = raw.block_counts.into();
-
-        // match idx (idx is in WorldChunkRaw)
-        let mut raw_world_chunks = Vec::new();
-        raw_world_chunks.resize_with(chunk_counts.len(), Default::default);
-        for raw in raw.world_chunks {
-            let idx = raw.idx;
-            raw_world_chunks[idx as usize] = Some(raw);
-        }
-
-        // create world chunk from asset
-        let mut world_chunks = Vec::new();
-        world_chunks.resize_with(chunk_counts.len(), Default::default);
-
-        for idx in 0..raw_world_chunks.len() {
-            if raw_world_chunks[idx].is_none() {
-                continue;
-            }
+        let chunk_counts: Option<WorldChunkCounts> = raw.block_counts.map(|block_counts| block_counts.into());
 
-            let world_chunk = WorldChunk::new(idx, &chunk_counts, &raw_world_chunks);
-            let chunk_idx = world_chunk.idx as usize;
-            world_chunks[chunk_idx] = Some(world_chunk);
-        }
+        // match position (idx or pos is in WorldChunkRaw)
+        // vis is built by user of asset, because it needs loaded materials
+        let world_chunks = raw
+            .world_chunks
+            .iter()
+            .map(|raw| {
+                let pos = raw.get_chunk_pos(chunk_counts.as_ref());
+                let blocks = PaletteArray::from_slice(&raw.blocks);
+                (pos, WorldChunk { pos, blocks })
+            })
+            .collect();
 
         Self {
             chunk_counts,
             block_size: raw.block_size,
             world_material: asset_manager.get(&AssetPath::from(&raw.world_material)),
             world_chunks,
+            fog: raw.fog,
         }
     }
 
-    pub fn get_world_pos(&self, idx: usize) -> Vec3 {
-        let chunk_pos = ChunkPos::from_index(idx, &self.chunk_counts);
+    pub fn get_world_pos(&self, chunk_pos: ChunkPos) -> Vec3 {
         chunk_pos.get_world_pos(self.block_size.to_f32())
     }
 
-    pub fn get_chunk_aabb(&self, chunk_idx: usize) -> Aabb {
-        let min = self.get_world_pos(chunk_idx);
-        let size = self.block_size.to_f32() * BLOCK_COUNT_IN_CHUNKSIDE as f32;
-        let max = min + Vec3::new(size, size, size);
-        Aabb::new(min, max)
+    pub fn get_chunk_aabb(&self, chunk_pos: ChunkPos) -> Aabb {
+        chunk_pos.aabb(self.block_size.to_f32())
     }
 }
 
 pub struct WorldChunk {
-    pub idx: i32,
-    pub blocks: Vec<u8>,
-    pub vis: Vec<BitFlags<Dir>>,
+    pub pos: ChunkPos,
+    pub blocks: PaletteArray<u8>,
 }
 
-impl WorldChunk {
-    fn new(idx: usize, chunk_counts: &WorldChunkCounts, raw_chunks: &[Option<WorldChunkRaw>]) -> Self {
-        let cur_chunk = raw_chunks[idx].as_ref().unwrap();
-        assert_eq!(idx, cur_chunk.idx as usize);
-
-        Self {
-            idx: cur_chunk.idx,
-            blocks: cur_chunk.blocks.clone(), //todo: too many clone (blocks is big)
-            vis: build_vis(idx, chunk_counts, raw_chunks),
-        }
+/// build visibility of all blocks in chunk (chunk_pos)
+/// get_chunk returns blocks of chunk at some position. None if chunk is empty (or out of world)
+/// occlusion decides which neighbors hide faces
+pub fn build_vis<'a>(
+    chunk_pos: ChunkPos,
+    occlusion: &BlockOcclusion,
+    get_chunk: impl Fn(ChunkPos) -> Option<&'a PaletteArray<u8>>,
+) -> PaletteArray<BitFlags<Dir>> {
+    let cur_chunk = get_chunk(chunk_pos).unwrap();
+
+    // empty chunk has no visible face
+    if cur_chunk.uniform() == Some(0) {
+        return PaletteArray::new(cur_chunk.len(), BitFlags::empty());
     }
-}
-
-fn build_vis(
-    chunk_idx: usize,
-    chunk_counts: &WorldChunkCounts,
-    chunks: &[Option<WorldChunkRaw>],
-) -> Vec<BitFlags<Dir>> {
-    let cur_chunk = chunks[chunk_idx].as_ref().unwrap();
 
     let mut vis_vec = Vec::new();
-    vis_vec.reserve(cur_chunk.blocks.len());
+    vis_vec.reserve(cur_chunk.len());
 
     let full_vis = BitFlags::<Dir>::all();
 
-    for block_idx in 0..cur_chunk.blocks.len() {
+    for (block_idx, cur_block) in cur_chunk.iter().enumerate() {
         // if current block is empty, then skip
-        let cur_block = cur_chunk.blocks[block_idx];
         if cur_block == 0 {
             vis_vec.push(BitFlags::<Dir>::empty());
             continue;
         }
 
+        let block_pos = BlockPos::from_chunk_pos(chunk_pos, block_idx);
         let mut vis = BitFlags::<Dir>::empty();
 
         for dir in full_vis.iter() {
-            if is_visible_dir(chunk_idx, block_idx, dir, chunk_counts, chunks) {
+            if is_visible_dir(block_pos, cur_block, dir, cur_chunk, occlusion, &get_chunk) {
                 vis |= dir;
             }
         }
@@ -134,28 +118,30 @@ fn build_vis(
         vis_vec.push(vis);
     }
 
-    vis_vec
+    PaletteArray::from_slice(&vis_vec)
 }
 
-/// check block(indexed by block_idx) is empty at some direction (dir)
-fn is_visible_dir(
-    chunk_idx: usize,
-    block_idx: usize,
+/// check face of block (block_pos, cur_block) at some direction (dir) is not hidden by neighbor
+/// cur_chunk is chunk of block_pos (skip get_chunk if neighbor is in same chunk)
+fn is_visible_dir<'a>(
+    block_pos: BlockPos,
+    cur_block: u8,
     dir: Dir,
-    chunk_counts: &WorldChunkCounts,
-    chunks: &[Option<WorldChunkRaw>],
+    cur_chunk: &PaletteArray<u8>,
+    occlusion: &BlockOcclusion,
+    get_chunk: &impl Fn(ChunkPos) -> Option<&'a PaletteArray<u8>>,
 ) -> bool {
-    let block_pos = BlockPos::from_index(chunk_idx, block_idx, chunk_counts);
     let neighbor_pos = block_pos.get_neighbor(dir);
 
-    if let Some((neighbor_chunk_idx, neighbor_block_idx)) = neighbor_pos.get_index(chunk_counts) {
-        let neighbor_chunk = &chunks[neighbor_chunk_idx];
-        if let Some(neighbor_chunk) = neighbor_chunk {
-            let block = neighbor_chunk.blocks[neighbor_block_idx];
-            block == 0
-        } else {
-            true
-        }
+    let neighbor_chunk_pos = neighbor_pos.get_chunk_pos();
+    if neighbor_chunk_pos == block_pos.get_chunk_pos() {
+        let neighbor = cur_chunk.get(neighbor_pos.get_block_index());
+        return occlusion.is_face_visible(cur_block, neighbor);
+    }
+
+    if let Some(neighbor_chunk) = get_chunk(neighbor_chunk_pos) {
+        let neighbor = neighbor_chunk.get(neighbor_pos.get_block_index());
+        occlusion.is_face_visible(cur_block, neighbor)
     } else {
         true
     }
@@ -163,12 +149,32 @@ fn is_visible_dir(
 
 #[derive(Serialize, Deserialize, PartialEq, Debug)]
 pub struct WorldBlockAssetRaw {
-    pub block_counts: WorldBlockCounts,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_counts: Option<WorldBlockCounts>, // None if world is unbounded
     pub block_size: BlockSize,
     pub world_material: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fog: Option<WorldFog>,
     pub world_chunks: Vec<WorldChunkRaw>,
 }
 
+/// how fog thickens between start and end
+#[derive(Deserialize, Serialize, Copy, Clone, PartialEq, Debug)]
+pub enum FogMode {
+    Linear,
+    Exp,
+    Exp2,
+}
+
+/// distance fog of world. world is hidden by fog color at end
+#[derive(Deserialize, Serialize, Copy, Clone, PartialEq, Debug)]
+pub struct WorldFog {
+    pub color: [f32; 3],
+    pub start: f32, // distance from camera
+    pub end: f32,
+    pub mode: FogMode,
+}
+
 #[derive(Deserialize, Serialize, Copy, Clone, PartialEq, Debug)]
 pub enum BlockSize {
     Xs, // 0.25
@@ -193,25 +199,43 @@ impl BlockSize {
 impl WorldBlockAssetRaw {
     fn validate(&self) {
         // check world size
-        let chunk_len = (BLOCK_COUNT_IN_CHUNKSIDE as f32 * self.block_size.to_f32()) as i32;
+        if let Some(block_counts) = &self.block_counts {
+            let chunk_len = (BLOCK_COUNT_IN_CHUNKSIDE as f32 * self.block_size.to_f32()) as i32;
 
-        assert_eq!(self.block_counts.x % chunk_len, 0);
-        assert_eq!(self.block_counts.y % chunk_len, 0);
-        assert_eq!(self.block_counts.z % chunk_len, 0);
+            assert_eq!(block_counts.x % chunk_len, 0);
+            assert_eq!(block_counts.y % chunk_len, 0);
+            assert_eq!(block_counts.z % chunk_len, 0);
+        }
 
         // check block counts in chunk
+        // unbounded world should have chunk position
         for chunk in &self.world_chunks {
             assert_eq!(chunk.blocks.len(), TOTAL_BLOCK_COUNTS_IN_CHUNK);
+            assert!(self.block_counts.is_some() || chunk.pos.is_some());
         }
     }
 }
 
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
 pub struct WorldChunkRaw {
-    pub idx: i32,        // chunk index (x, y, z order)
+    #[serde(default)]
+    pub idx: i32, // chunk index (x, y, z order). used in bounded world
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pos: Option<ChunkPos>, // chunk position. used in unbounded world
     pub blocks: Vec<u8>, // == TOTAL_BLOCK_COUNTS_IN_CHUNK
 }
 
+impl WorldChunkRaw {
+    /// pos if exists, else position from idx
+    fn get_chunk_pos(&self, chunk_counts: Option<&WorldChunkCounts>) -> ChunkPos {
+        match (self.pos, chunk_counts) {
+            (Some(pos), _) => pos,
+            (None, Some(chunk_counts)) => ChunkPos::from_index(self.idx as usize, chunk_counts),
+            (None, None) => panic!("chunk in unbounded world should have pos"),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -227,13 +251,20 @@ mod tests {
     fn serialize_world_chunk() {
         let world_chunk = WorldChunkRaw {
             idx: 0,
+            pos: None,
             blocks: vec![1, 2, 3, 4],
         };
 
         let world_block = WorldBlockAssetRaw {
-            block_counts: WorldBlockCounts::new(1, 1, 1),
+            block_counts: Some(WorldBlockCounts::new(1, 1, 1)),
             block_size: BlockSize::M,
             world_material: "world_material.wmt".to_string(),
+            fog: Some(WorldFog {
+                color: [0.5, 0.6, 0.7],
+                start: 10.0,
+                end: 20.0,
+                mode: FogMode::Exp2,
+            }),
             world_chunks: vec![world_chunk],
         };
 
@@ -242,4 +273,62 @@ mod tests {
 
         assert_eq!(read, world_block);
     }
+
+    #[test]
+    fn deserialize_unbounded_world() {
+        let s = r#"{ "block_size": "M", "world_material": "world_material.wmt", "world_chunks": [] }"#;
+        let world_block: WorldBlockAssetRaw = serde_json::from_str(s).unwrap();
+        assert_eq!(world_block.block_counts, None);
+        assert_eq!(world_block.fog, None);
+    }
+
+    #[test]
+    fn test_build_vis_across_negative_chunk() {
+        let mut chunk = PaletteArray::new(TOTAL_BLOCK_COUNTS_IN_CHUNK, 0);
+        chunk.set(0, 1);
+        let mut neg_chunk = PaletteArray::new(TOTAL_BLOCK_COUNTS_IN_CHUNK, 0);
+        neg_chunk.set(BLOCK_COUNT_IN_CHUNKSIDE - 1, 1);
+
+        let mut chunks = HashMap::new();
+        chunks.insert(ChunkPos::new(0, 0, 0), chunk);
+        chunks.insert(ChunkPos::new(-1, 0, 0), neg_chunk);
+
+        let get_chunk = |pos: ChunkPos| chunks.get(&pos);
+
+        let vis = build_vis(ChunkPos::new(0, 0, 0), &BlockOcclusion::default(), get_chunk);
+        assert_eq!(vis.get(0), BitFlags::all() ^ Dir::XNeg);
+
+        let vis = build_vis(ChunkPos::new(-1, 0, 0), &BlockOcclusion::default(), get_chunk);
+        assert_eq!(vis.get(BLOCK_COUNT_IN_CHUNKSIDE - 1), BitFlags::all() ^ Dir::XPos);
+    }
+
+    #[test]
+    fn test_build_vis_empty_chunk() {
+        let chunk = PaletteArray::new(TOTAL_BLOCK_COUNTS_IN_CHUNK, 0);
+        let vis = build_vis(ChunkPos::new(0, 0, 0), &BlockOcclusion::default(), |_| Some(&chunk));
+        assert_eq!(vis.uniform(), Some(BitFlags::empty()));
+    }
+
+    #[test]
+    fn test_build_vis_transparent() {
+        let mut occlusion = BlockOcclusion::default();
+        occlusion.set(2, Occlusion::Transparent);
+
+        // stone(1), glass(2), glass(2), stone(1) along x
+        let mut chunk = PaletteArray::new(TOTAL_BLOCK_COUNTS_IN_CHUNK, 0);
+        for (x, block) in [1, 2, 2, 1].iter().enumerate() {
+            chunk.set(x, *block);
+        }
+
+        let vis = build_vis(ChunkPos::new(0, 0, 0), &occlusion, |_| Some(&chunk));
+
+        // stone face against glass is visible
+        assert!(vis.get(0).contains(Dir::XPos));
+        assert!(vis.get(3).contains(Dir::XNeg));
+
+        // glass face against same glass is culled, against stone is hidden
+        assert!(!vis.get(1).contains(Dir::XPos));
+        assert!(!vis.get(1).contains(Dir::XNeg));
+        assert!(!vis.get(2).contains(Dir::XNeg));
+    }
 }
@@ -4,14 +4,23 @@ use crate::handle::AssetLoadError;
 
 use super::{
     assets::{Asset, AssetType},
-    AssetHandle, AssetManager, AssetPath, MaterialAsset,
+    AssetHandle, AssetManager, AssetPath, MaterialAsset, ShaderAsset,
 };
 use serde::Deserialize;
+use voxrs_math::{BlockEmission, BlockOcclusion};
+use voxrs_rhi::Texture;
 use voxrs_types::io::FileSystem;
 
 #[derive(Asset)]
 pub struct WorldMaterialAsset {
     pub material_handles: HashMap<u8, AssetHandle<MaterialAsset>>,
+    pub texture_array: Option<TextureArrayShaders>, // draw with 1 texture array of every diffuse texture
+}
+
+/// shaders sampling texture array with layer of vertex
+pub struct TextureArrayShaders {
+    pub vertex_shader: AssetHandle<ShaderAsset>,
+    pub frag_shader: AssetHandle<ShaderAsset>,
 }
 
 impl WorldMaterialAsset {
@@ -39,7 +48,63 @@ impl WorldMaterialAsset {
             material_handles.insert(entity.id, material);
         }
 
-        Self { material_handles }
+        let texture_array = raw.texture_array.map(|raw| TextureArrayShaders {
+            vertex_shader: asset_manager.get::<ShaderAsset>(&raw.vertex_shader.into()),
+            frag_shader: asset_manager.get::<ShaderAsset>(&raw.frag_shader.into()),
+        });
+
+        Self {
+            material_handles,
+            texture_array,
+        }
+    }
+
+    /// layer in texture array of each material, in order of material id
+    pub fn texture_layers(&self) -> HashMap<u8, u32> {
+        let mut ids = self.material_handles.keys().copied().collect::<Vec<_>>();
+        ids.sort_unstable();
+        ids.into_iter()
+            .enumerate()
+            .map(|(layer, id)| (id, layer as u32))
+            .collect()
+    }
+
+    /// diffuse textures of every material in texture layers, resized to common size
+    /// blocks until materials and textures are loaded
+    pub fn build_texture_array(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> anyhow::Result<Texture> {
+        let mut layers = self.texture_layers().into_iter().collect::<Vec<_>>();
+        layers.sort_unstable_by_key(|(_, layer)| *layer);
+
+        let textures = layers
+            .iter()
+            .map(|(id, _)| self.material_handles[id].get_asset().diffuse_tex.clone())
+            .collect::<Vec<_>>();
+        let assets = textures.iter().map(|texture| texture.get_asset()).collect::<Vec<_>>();
+        let bytes = assets.iter().map(|asset| asset.buf.as_slice()).collect::<Vec<_>>();
+
+        Texture::array_from_bytes(device, queue, &bytes, "world material texture array")
+    }
+
+    /// occlusion of blocks from alpha of their materials
+    /// block without material is opaque. blocks until materials are loaded
+    pub fn block_occlusion(&self) -> BlockOcclusion {
+        let mut occlusion = BlockOcclusion::default();
+        for (id, material_handle) in &self.material_handles {
+            if *id != 0 {
+                occlusion.set(*id, material_handle.get_asset().alpha.occlusion());
+            }
+        }
+        occlusion
+    }
+
+    /// light emitted by blocks from their materials
+    /// blocks until materials are loaded
+    pub fn block_emission(&self) -> BlockEmission {
+        let mut emission = BlockEmission::default();
+        for (id, material_handle) in &self.material_handles {
+            emission.set(*id, material_handle.get_asset().emission);
+        }
+        emission
     }
 }
 
@@ -49,7 +114,15 @@ struct WorldMaterialEntity {
     pub material: String,
 }
 
+#[derive(Deserialize)]
+struct TextureArrayRaw {
+    pub vertex_shader: String,
+    pub frag_shader: String,
+}
+
 #[derive(Deserialize)]
 struct WorldMaterialAssetRaw {
     pub materials: Vec<WorldMaterialEntity>,
+    #[serde(default)]
+    pub texture_array: Option<TextureArrayRaw>,
 }
@@ -37,6 +37,10 @@ impl CameraRes {
         }
     }
 
+    pub fn eye(&self) -> Vec3 {
+        self.eye
+    }
+
     pub fn build_view_projection_matrix(&self) -> Mat4 {
         let (_, y, z) = self.get_xyz();
         let target = self.eye + z;
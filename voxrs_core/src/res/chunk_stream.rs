@@ -0,0 +1,311 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Condvar, Mutex},
+};
+
+use crossbeam_channel::{Receiver, Sender};
+use enumflags2::BitFlags;
+
+use voxrs_asset::build_vis;
+use voxrs_math::*;
+
+/// source of streamed chunks
+/// called on background workers. saves of the same chunk are never concurrent,
+/// and chunk is not loaded from provider while its save is pending
+pub trait ChunkProvider: Send + Sync {
+    /// load or generate blocks of chunk
+    /// None if chunk is empty
+    fn load(&self, chunk_pos: ChunkPos) -> Option<Vec<u8>>;
+
+    /// save blocks of modified chunk before it is evicted
+    fn save(&self, chunk_pos: ChunkPos, blocks: Vec<u8>);
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct StreamSettings {
+    /// chunks within this radius (in chunks) of camera are loaded
+    pub load_radius: i32,
+    /// chunks out of this radius (in chunks) are saved and evicted. should be >= load_radius
+    pub unload_radius: i32,
+    /// max loaded chunks integrated to world per frame
+    pub integrate_budget: usize,
+}
+
+impl Default for StreamSettings {
+    fn default() -> Self {
+        Self {
+            load_radius: 4,
+            unload_radius: 5,
+            integrate_budget: 4,
+        }
+    }
+}
+
+/// chunk loaded on background worker
+/// vis is built without neighbor chunks. border should be refreshed when integrated
+pub struct LoadedChunk {
+    pub pos: ChunkPos,
+    pub blocks: Option<(PaletteArray<u8>, PaletteArray<BitFlags<Dir>>)>,
+}
+
+/// blocks of evicted chunks until provider saves them
+/// loads are served from here, so stale blocks are never loaded before save lands
+#[derive(Default)]
+struct PendingSaves {
+    chunks: Mutex<HashMap<ChunkPos, PendingSave>>,
+    saved: Condvar, // notified when chunk is removed
+}
+
+struct PendingSave {
+    version: u64, // increased when chunk is evicted again before saved
+    blocks: Arc<Vec<u8>>,
+}
+
+impl PendingSaves {
+    /// return true if no save of chunk is running, so new worker should save it
+    fn push(&self, chunk_pos: ChunkPos, blocks: Vec<u8>) -> bool {
+        let mut chunks = self.chunks.lock().unwrap();
+        let blocks = Arc::new(blocks);
+        match chunks.get_mut(&chunk_pos) {
+            Some(save) => {
+                save.version += 1;
+                save.blocks = blocks;
+                false
+            }
+            None => {
+                chunks.insert(chunk_pos, PendingSave { version: 0, blocks });
+                true
+            }
+        }
+    }
+
+    fn get(&self, chunk_pos: ChunkPos) -> Option<(u64, Arc<Vec<u8>>)> {
+        let chunks = self.chunks.lock().unwrap();
+        chunks.get(&chunk_pos).map(|save| (save.version, save.blocks.clone()))
+    }
+
+    /// remove chunk saved with version. return false if newer blocks should be saved again
+    fn finish(&self, chunk_pos: ChunkPos, version: u64) -> bool {
+        let mut chunks = self.chunks.lock().unwrap();
+        if chunks.get(&chunk_pos).map(|save| save.version) == Some(version) {
+            chunks.remove(&chunk_pos);
+            self.saved.notify_all();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.chunks.lock().unwrap().len()
+    }
+
+    /// block until every pending save lands
+    fn wait(&self) {
+        let mut chunks = self.chunks.lock().unwrap();
+        while !chunks.is_empty() {
+            chunks = self.saved.wait(chunks).unwrap();
+        }
+    }
+}
+
+/// pending save if exists, or blocks from provider
+fn load_blocks(provider: &dyn ChunkProvider, saves: &PendingSaves, chunk_pos: ChunkPos) -> Option<Vec<u8>> {
+    match saves.get(chunk_pos) {
+        Some((_, blocks)) => Some(blocks.to_vec()),
+        None => provider.load(chunk_pos),
+    }
+}
+
+pub struct ChunkStreamer {
+    provider: Arc<dyn ChunkProvider>,
+    settings: StreamSettings,
+    occlusion: Arc<BlockOcclusion>,
+    pending: HashSet<ChunkPos>,
+    empty: HashSet<ChunkPos>,
+    dirty: HashSet<ChunkPos>,
+    saves: Arc<PendingSaves>,
+    sender: Sender<LoadedChunk>,
+    receiver: Receiver<LoadedChunk>,
+}
+
+impl ChunkStreamer {
+    pub fn new(provider: Arc<dyn ChunkProvider>, settings: StreamSettings, occlusion: Arc<BlockOcclusion>) -> Self {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+
+        Self {
+            provider,
+            settings,
+            occlusion,
+            pending: HashSet::new(),
+            empty: HashSet::new(),
+            dirty: HashSet::new(),
+            saves: Arc::new(PendingSaves::default()),
+            sender,
+            receiver,
+        }
+    }
+
+    pub fn settings(&self) -> &StreamSettings {
+        &self.settings
+    }
+
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// evicted chunks not saved yet
+    pub fn saving_count(&self) -> usize {
+        self.saves.len()
+    }
+
+    pub fn is_in_load_range(&self, center: ChunkPos, chunk_pos: ChunkPos) -> bool {
+        dist_sq(center, chunk_pos) <= self.settings.load_radius * self.settings.load_radius
+    }
+
+    pub fn is_in_unload_range(&self, center: ChunkPos, chunk_pos: ChunkPos) -> bool {
+        dist_sq(center, chunk_pos) <= self.settings.unload_radius * self.settings.unload_radius
+    }
+
+    /// provider returned no blocks for chunk, so it is all empty until modified
+    pub fn is_known_empty(&self, chunk_pos: ChunkPos) -> bool {
+        self.empty.contains(&chunk_pos)
+    }
+
+    /// chunk is modified after loaded. it will be saved when evicted
+    pub fn mark_dirty(&mut self, chunk_pos: ChunkPos) {
+        self.empty.remove(&chunk_pos);
+        self.dirty.insert(chunk_pos);
+    }
+
+    /// request chunks in load range, nearest first
+    /// skip chunks which are resident, loading or known empty
+    pub fn request_loads(
+        &mut self,
+        center: ChunkPos,
+        is_resident: impl Fn(ChunkPos) -> bool,
+        is_valid: impl Fn(ChunkPos) -> bool,
+    ) {
+        let radius = self.settings.load_radius;
+
+        let mut requests = Vec::new();
+        for z in -radius..=radius {
+            for y in -radius..=radius {
+                for x in -radius..=radius {
+                    let chunk_pos = ChunkPos::new(center.x + x, center.y + y, center.z + z);
+                    if !self.is_in_load_range(center, chunk_pos)
+                        || !is_valid(chunk_pos)
+                        || is_resident(chunk_pos)
+                        || self.pending.contains(&chunk_pos)
+                        || self.empty.contains(&chunk_pos)
+                    {
+                        continue;
+                    }
+
+                    requests.push(chunk_pos);
+                }
+            }
+        }
+
+        requests.sort_by_key(|chunk_pos| dist_sq(center, *chunk_pos));
+
+        for chunk_pos in requests {
+            self.pending.insert(chunk_pos);
+
+            let provider = self.provider.clone();
+            let saves = self.saves.clone();
+            let sender = self.sender.clone();
+            let occlusion = self.occlusion.clone();
+            rayon::spawn(move || {
+                let blocks = load_blocks(provider.as_ref(), &saves, chunk_pos).map(|blocks| {
+                    let blocks = PaletteArray::from_slice(&blocks);
+                    let vis = build_vis(chunk_pos, &occlusion, |pos| {
+                        if pos == chunk_pos {
+                            Some(&blocks)
+                        } else {
+                            None
+                        }
+                    });
+                    (blocks, vis)
+                });
+
+                let _ = sender.send(LoadedChunk { pos: chunk_pos, blocks });
+            });
+        }
+    }
+
+    /// loaded chunks, at most integrate_budget
+    /// empty chunks are remembered and not returned
+    pub fn receive(&mut self) -> Vec<LoadedChunk> {
+        let mut loaded = Vec::new();
+
+        while loaded.len() < self.settings.integrate_budget {
+            let chunk = match self.receiver.try_recv() {
+                Ok(chunk) => chunk,
+                Err(_) => break,
+            };
+
+            self.pending.remove(&chunk.pos);
+
+            if chunk.blocks.is_some() {
+                loaded.push(chunk);
+            } else {
+                self.empty.insert(chunk.pos);
+            }
+        }
+
+        loaded
+    }
+
+    /// save chunk on background worker if modified
+    /// chunk evicted again while saved is saved again after current save
+    pub fn evict(&mut self, chunk_pos: ChunkPos, blocks: &PaletteArray<u8>) {
+        self.save(chunk_pos, blocks);
+    }
+
+    /// save modified resident chunks, and block until every pending save lands
+    /// dirty chunks are otherwise saved only when evicted, so this should be called before shutdown
+    pub fn flush<'a>(&mut self, resident: impl IntoIterator<Item = (ChunkPos, &'a PaletteArray<u8>)>) {
+        for (chunk_pos, blocks) in resident {
+            self.save(chunk_pos, blocks);
+        }
+
+        self.saves.wait();
+    }
+
+    /// save chunk on background worker if modified. chunk is clean after it
+    fn save(&mut self, chunk_pos: ChunkPos, blocks: &PaletteArray<u8>) {
+        if !self.dirty.remove(&chunk_pos) {
+            return;
+        }
+
+        if !self.saves.push(chunk_pos, blocks.to_vec()) {
+            return;
+        }
+
+        let provider = self.provider.clone();
+        let saves = self.saves.clone();
+        rayon::spawn(move || {
+            while let Some((version, blocks)) = saves.get(chunk_pos) {
+                provider.save(chunk_pos, blocks.to_vec());
+                if saves.finish(chunk_pos, version) {
+                    break;
+                }
+            }
+        });
+    }
+
+    /// forget empty chunks out of unload range
+    pub fn evict_empty(&mut self, center: ChunkPos) {
+        let unload_radius = self.settings.unload_radius;
+        self.empty
+            .retain(|chunk_pos| dist_sq(center, *chunk_pos) <= unload_radius * unload_radius);
+    }
+}
+
+fn dist_sq(lhs: ChunkPos, rhs: ChunkPos) -> i32 {
+    let x = lhs.x - rhs.x;
+    let y = lhs.y - rhs.y;
+    let z = lhs.z - rhs.z;
+    x * x + y * y + z * z
+}
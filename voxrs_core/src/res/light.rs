@@ -0,0 +1,362 @@
+use std::collections::{HashSet, VecDeque};
+
+use enumflags2::BitFlags;
+
+use voxrs_math::*;
+
+/// blocks and light of world used in light propagation
+/// sky light falls down from open sky without decreasing, block light spreads from emitting blocks.
+/// both decrease by 1 per block and pass through blocks which are not opaque
+pub(crate) trait LightWorld {
+    /// block value and packed light (see LightChannel)
+    /// None if chunk of block is not resident or out of world
+    fn get(&self, block_pos: BlockPos) -> Option<(u8, u8)>;
+
+    /// do nothing if chunk of block is not resident
+    fn set_light(&mut self, block_pos: BlockPos, light: u8);
+
+    /// no opaque block can be at or above block_pos, including blocks of chunks which are not resident
+    fn is_open_sky(&self, block_pos: BlockPos) -> bool;
+
+    fn occlusion(&self) -> &BlockOcclusion;
+
+    fn emission(&self) -> &BlockEmission;
+}
+
+const CHANNELS: [LightChannel; 2] = [LightChannel::Sky, LightChannel::Block];
+
+/// recompute light of blocks, and light of other blocks which depends on them
+/// blocks should have every block whose value is changed, every block whose neighbor above is inserted or removed,
+/// and every block under missing block whose open sky is changed
+pub(crate) fn relight(world: &mut impl LightWorld, blocks: &[BlockPos]) {
+    let mut remove = VecDeque::new();
+    let mut add = VecDeque::new();
+
+    // clear light of blocks
+    for block_pos in blocks {
+        if let Some((_, light)) = world.get(*block_pos) {
+            if light != 0 {
+                world.set_light(*block_pos, 0);
+                for channel in CHANNELS.iter() {
+                    let level = channel.get(light);
+                    if level > 0 {
+                        remove.push_back((*block_pos, *channel, level));
+                    }
+                }
+            }
+        }
+    }
+
+    // remove light which came from cleared blocks
+    // neighbors lit by other source are spread again
+    while let Some((block_pos, channel, level)) = remove.pop_front() {
+        for dir in BitFlags::<Dir>::all().iter() {
+            let neighbor_pos = block_pos.get_neighbor(dir);
+            let (neighbor, neighbor_light) = match world.get(neighbor_pos) {
+                Some(value) => value,
+                None => continue,
+            };
+
+            let neighbor_level = channel.get(neighbor_light);
+            if neighbor_level == 0 {
+                continue;
+            }
+
+            if neighbor_level < level || (is_sky_fall(channel, dir, level) && neighbor_level == MAX_LIGHT) {
+                // neighbor may be source itself
+                let source_level = get_source_level(world, neighbor_pos, neighbor, channel);
+                world.set_light(neighbor_pos, channel.set(neighbor_light, source_level));
+                remove.push_back((neighbor_pos, channel, neighbor_level));
+
+                if source_level > 0 {
+                    add.push_back(neighbor_pos);
+                }
+            } else {
+                add.push_back(neighbor_pos);
+            }
+        }
+    }
+
+    // light of blocks from sources, and from neighbors out of blocks
+    let block_set = blocks.iter().copied().collect::<HashSet<_>>();
+    for block_pos in blocks {
+        let (block, light) = match world.get(*block_pos) {
+            Some(value) => value,
+            None => continue,
+        };
+
+        let new_light = CHANNELS.iter().fold(light, |new_light, channel| {
+            let source_level = get_source_level(world, *block_pos, block, *channel);
+            channel.set(new_light, source_level.max(channel.get(light)))
+        });
+
+        if new_light != light {
+            world.set_light(*block_pos, new_light);
+        }
+
+        if new_light != 0 {
+            add.push_back(*block_pos);
+        }
+
+        for dir in BitFlags::<Dir>::all().iter() {
+            let neighbor_pos = block_pos.get_neighbor(dir);
+            if !block_set.contains(&neighbor_pos) {
+                add.push_back(neighbor_pos);
+            }
+        }
+    }
+
+    propagate(world, add);
+}
+
+/// light (packed) of block may come from its neighbor at dir
+pub(crate) fn is_lit_from(light: u8, neighbor_light: u8, dir: Dir) -> bool {
+    CHANNELS.iter().any(|channel| {
+        let level = channel.get(light);
+        level > 0 && level == spread_level(*channel, dir.opposite_dir(), channel.get(neighbor_light))
+    })
+}
+
+/// spread light from blocks in queue
+fn propagate(world: &mut impl LightWorld, mut add: VecDeque<BlockPos>) {
+    while let Some(block_pos) = add.pop_front() {
+        let light = match world.get(block_pos) {
+            Some((_, light)) => light,
+            None => continue,
+        };
+
+        if CHANNELS.iter().all(|channel| channel.get(light) <= 1) {
+            continue;
+        }
+
+        for dir in BitFlags::<Dir>::all().iter() {
+            let neighbor_pos = block_pos.get_neighbor(dir);
+            let neighbor_light = match world.get(neighbor_pos) {
+                Some((block, light)) if world.occlusion().get(block) != Occlusion::Opaque => light,
+                _ => continue,
+            };
+
+            let new_light = CHANNELS.iter().fold(neighbor_light, |new_light, channel| {
+                let level = spread_level(*channel, dir, channel.get(light));
+                channel.set(new_light, level.max(channel.get(neighbor_light)))
+            });
+
+            if new_light != neighbor_light {
+                world.set_light(neighbor_pos, new_light);
+                add.push_back(neighbor_pos);
+            }
+        }
+    }
+}
+
+/// level of neighbor at dir, lit by block with level
+fn spread_level(channel: LightChannel, dir: Dir, level: u8) -> u8 {
+    if is_sky_fall(channel, dir, level) {
+        MAX_LIGHT
+    } else {
+        level.saturating_sub(1)
+    }
+}
+
+/// full sky light falls down without decreasing
+fn is_sky_fall(channel: LightChannel, dir: Dir, level: u8) -> bool {
+    channel == LightChannel::Sky && dir == Dir::YNeg && level == MAX_LIGHT
+}
+
+/// light of block without neighbors
+/// sky: MAX_LIGHT if block is not opaque and block above is missing and open to sky. block: emission of block
+fn get_source_level(world: &impl LightWorld, block_pos: BlockPos, block: u8, channel: LightChannel) -> u8 {
+    match channel {
+        LightChannel::Sky => {
+            let above = block_pos.get_neighbor(Dir::YPos);
+            let is_open = world.get(above).is_none() && world.is_open_sky(above);
+            if is_open && world.occlusion().get(block) != Occlusion::Opaque {
+                MAX_LIGHT
+            } else {
+                0
+            }
+        }
+        LightChannel::Block => world.emission().get(block),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    /// blocks in 0 <= x, z < 8, 0 <= y < 16. no block is out of it
+    #[derive(Default)]
+    struct TestWorld {
+        blocks: HashMap<BlockPos, u8>,
+        lights: HashMap<BlockPos, u8>,
+        occlusion: BlockOcclusion,
+        emission: BlockEmission,
+    }
+
+    impl TestWorld {
+        fn new() -> Self {
+            let mut world = Self::default();
+            world.emission.set(5, 12);
+            world.occlusion.set(6, Occlusion::Transparent);
+            relight(&mut world, &Self::all_blocks());
+            world
+        }
+
+        fn all_blocks() -> Vec<BlockPos> {
+            let mut blocks = Vec::new();
+            for z in 0..8 {
+                for y in 0..16 {
+                    for x in 0..8 {
+                        blocks.push(BlockPos::new(x, y, z));
+                    }
+                }
+            }
+            blocks
+        }
+
+        fn set_block(&mut self, block_pos: BlockPos, block: u8) {
+            self.blocks.insert(block_pos, block);
+            relight(self, &[block_pos]);
+        }
+
+        fn light(&self, block_pos: BlockPos) -> u8 {
+            *self.lights.get(&block_pos).unwrap_or(&0)
+        }
+
+        fn sky(&self, block_pos: BlockPos) -> u8 {
+            LightChannel::Sky.get(self.light(block_pos))
+        }
+
+        fn block_light(&self, block_pos: BlockPos) -> u8 {
+            LightChannel::Block.get(self.light(block_pos))
+        }
+
+        /// light should be same as light built from scratch
+        fn assert_rebuilt(&self) {
+            let mut rebuilt = TestWorld {
+                blocks: self.blocks.clone(),
+                lights: HashMap::new(),
+                occlusion: self.occlusion.clone(),
+                emission: self.emission.clone(),
+            };
+            relight(&mut rebuilt, &Self::all_blocks());
+
+            for block_pos in Self::all_blocks() {
+                assert_eq!(self.light(block_pos), rebuilt.light(block_pos), "{:?}", block_pos);
+            }
+        }
+    }
+
+    impl LightWorld for TestWorld {
+        fn get(&self, block_pos: BlockPos) -> Option<(u8, u8)> {
+            let in_world =
+                (0..8).contains(&block_pos.x) && (0..16).contains(&block_pos.y) && (0..8).contains(&block_pos.z);
+            if in_world {
+                Some((*self.blocks.get(&block_pos).unwrap_or(&0), self.light(block_pos)))
+            } else {
+                None
+            }
+        }
+
+        fn set_light(&mut self, block_pos: BlockPos, light: u8) {
+            self.lights.insert(block_pos, light);
+        }
+
+        fn is_open_sky(&self, block_pos: BlockPos) -> bool {
+            block_pos.y >= 16
+        }
+
+        fn occlusion(&self) -> &BlockOcclusion {
+            &self.occlusion
+        }
+
+        fn emission(&self) -> &BlockEmission {
+            &self.emission
+        }
+    }
+
+    #[test]
+    fn test_sky_light() {
+        let mut world = TestWorld::new();
+        assert_eq!(world.sky(BlockPos::new(3, 0, 3)), MAX_LIGHT);
+
+        // roof over whole world makes cave
+        for z in 0..8 {
+            for x in 0..8 {
+                world.set_block(BlockPos::new(x, 10, z), 1);
+            }
+        }
+        assert_eq!(world.sky(BlockPos::new(3, 11, 3)), MAX_LIGHT);
+        assert_eq!(world.sky(BlockPos::new(3, 10, 3)), 0);
+        assert_eq!(world.sky(BlockPos::new(3, 0, 3)), 0);
+        world.assert_rebuilt();
+
+        // hole in roof
+        world.set_block(BlockPos::new(3, 10, 3), 0);
+        assert_eq!(world.sky(BlockPos::new(3, 0, 3)), MAX_LIGHT);
+        assert_eq!(world.sky(BlockPos::new(4, 0, 3)), MAX_LIGHT - 1);
+        assert_eq!(world.sky(BlockPos::new(5, 0, 3)), MAX_LIGHT - 2);
+        world.assert_rebuilt();
+
+        // transparent block doesn't block sky light
+        world.set_block(BlockPos::new(3, 10, 3), 6);
+        assert_eq!(world.sky(BlockPos::new(3, 0, 3)), MAX_LIGHT);
+        world.assert_rebuilt();
+    }
+
+    #[test]
+    fn test_block_light() {
+        let mut world = TestWorld::new();
+        for z in 0..8 {
+            for x in 0..8 {
+                world.set_block(BlockPos::new(x, 15, z), 1);
+            }
+        }
+
+        let torch_pos = BlockPos::new(1, 5, 1);
+        world.set_block(torch_pos, 5);
+        assert_eq!(world.block_light(torch_pos), 12);
+        assert_eq!(world.block_light(BlockPos::new(4, 5, 1)), 9);
+        assert_eq!(world.sky(BlockPos::new(4, 5, 1)), 0);
+        world.assert_rebuilt();
+
+        // wall between torch and block
+        world.set_block(BlockPos::new(2, 5, 1), 1);
+        assert_eq!(world.block_light(BlockPos::new(2, 5, 1)), 0);
+        assert_eq!(world.block_light(BlockPos::new(3, 5, 1)), 8);
+        world.assert_rebuilt();
+
+        // remove torch
+        world.set_block(torch_pos, 0);
+        assert_eq!(world.block_light(torch_pos), 0);
+        assert_eq!(world.block_light(BlockPos::new(4, 5, 1)), 0);
+        world.assert_rebuilt();
+    }
+
+    #[test]
+    fn test_random_edits() {
+        let mut world = TestWorld::new();
+        let mut seed = 0x1234_5678u64;
+        let mut next = || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+
+        let blocks = [0, 1, 1, 5, 6];
+        for i in 0..300 {
+            let block_pos = BlockPos::new((next() % 8) as i32, (next() % 16) as i32, (next() % 8) as i32);
+            let block = blocks[(next() % blocks.len() as u64) as usize];
+            world.set_block(block_pos, block);
+
+            if i % 30 == 0 {
+                world.assert_rebuilt();
+            }
+        }
+
+        world.assert_rebuilt();
+    }
+}
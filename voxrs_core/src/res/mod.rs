@@ -1,14 +1,22 @@
 mod camera;
 pub use camera::CameraRes;
 
+mod chunk_stream;
+pub use chunk_stream::{ChunkProvider, ChunkStreamer, StreamSettings};
+
 mod elapsed_time;
 pub use elapsed_time::ElapsedTimeRes;
 
 mod key_input;
 pub use key_input::KeyInputRes;
 
+mod light;
+
 mod mouse_input;
 pub use mouse_input::MouseInputRes;
 
+mod terrain;
+pub use terrain::TerrainProvider;
+
 mod world_block;
 pub use world_block::WorldBlockRes;
@@ -0,0 +1,217 @@
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use voxrs_asset::WorldChunkRaw;
+use voxrs_math::{
+    noise::{Fbm, Noise, Simplex},
+    BlockPos, ChunkPos, Vec2, BLOCK_COUNT_IN_CHUNKSIDE, TOTAL_BLOCK_COUNTS_IN_CHUNK,
+};
+
+use super::ChunkProvider;
+
+/// block of terrain surface
+const SURFACE_BLOCK: u8 = 1;
+/// block under surface layer
+const GROUND_BLOCK: u8 = 2;
+/// depth of surface layer (in blocks)
+const SURFACE_DEPTH: i32 = 3;
+
+/// generates chunks of height map terrain from noise
+/// modified chunks are saved to dir (one WorldChunkRaw json per chunk) and loaded instead of generated
+pub struct TerrainProvider {
+    dir: PathBuf,
+    noise: Fbm<Simplex>,
+    /// surface height (in blocks) where noise is 0
+    pub base_height: i32,
+    /// max distance (in blocks) of surface from base_height
+    pub amplitude: f32,
+    /// noise frequency per block
+    pub scale: f32,
+}
+
+impl TerrainProvider {
+    pub fn new<P: Into<PathBuf>>(dir: P, seed: u32) -> Self {
+        Self {
+            dir: dir.into(),
+            noise: Fbm::new(Simplex::new(seed), 4),
+            base_height: 0,
+            amplitude: 12.0,
+            scale: 0.01,
+        }
+    }
+
+    /// surface height (in blocks) of column. blocks below it are filled
+    pub fn height(&self, x: i32, z: i32) -> i32 {
+        let p = Vec2::new(x as f32, z as f32) * self.scale;
+        self.base_height + (self.noise.sample2(p) * self.amplitude).round() as i32
+    }
+
+    /// generated blocks of chunk. None if chunk is above every surface
+    pub fn generate(&self, chunk_pos: ChunkPos) -> Option<Vec<u8>> {
+        let side = BLOCK_COUNT_IN_CHUNKSIDE as i32;
+        let bottom = chunk_pos.y * side;
+        if bottom >= self.base_height + self.amplitude.ceil() as i32 {
+            return None;
+        }
+
+        let mut heights = Vec::with_capacity(BLOCK_COUNT_IN_CHUNKSIDE * BLOCK_COUNT_IN_CHUNKSIDE);
+        for z in 0..side {
+            for x in 0..side {
+                heights.push(self.height(chunk_pos.x * side + x, chunk_pos.z * side + z));
+            }
+        }
+
+        let blocks = (0..TOTAL_BLOCK_COUNTS_IN_CHUNK)
+            .map(|block_idx| {
+                let block_pos = BlockPos::from_chunk_pos(chunk_pos, block_idx);
+                let lx = block_pos.x - chunk_pos.x * side;
+                let lz = block_pos.z - chunk_pos.z * side;
+                let height = heights[(lz * side + lx) as usize];
+
+                if block_pos.y >= height {
+                    0
+                } else if block_pos.y >= height - SURFACE_DEPTH {
+                    SURFACE_BLOCK
+                } else {
+                    GROUND_BLOCK
+                }
+            })
+            .collect::<Vec<_>>();
+
+        if blocks.iter().all(|block| *block == 0) {
+            None
+        } else {
+            Some(blocks)
+        }
+    }
+
+    fn chunk_path(&self, chunk_pos: ChunkPos) -> PathBuf {
+        self.dir
+            .join(format!("{}_{}_{}.json", chunk_pos.x, chunk_pos.y, chunk_pos.z))
+    }
+
+    /// saved blocks of chunk. None if chunk was never saved (or save is broken)
+    fn load_saved(&self, path: &Path, chunk_pos: ChunkPos) -> Option<Vec<u8>> {
+        let s = match fs::read_to_string(path) {
+            Ok(s) => s,
+            Err(err) if err.kind() == ErrorKind::NotFound => return None,
+            Err(err) => {
+                log::error!("read chunk error. path: {:?}, err: {:?}", path, err);
+                return None;
+            }
+        };
+
+        match serde_json::from_str::<WorldChunkRaw>(&s) {
+            Ok(raw) if raw.pos == Some(chunk_pos) && raw.blocks.len() == TOTAL_BLOCK_COUNTS_IN_CHUNK => {
+                Some(raw.blocks)
+            }
+            Ok(_) => {
+                log::error!("chunk file doesn't match chunk. path: {:?}", path);
+                None
+            }
+            Err(err) => {
+                log::error!("parse chunk error. path: {:?}, err: {:?}", path, err);
+                None
+            }
+        }
+    }
+}
+
+impl ChunkProvider for TerrainProvider {
+    fn load(&self, chunk_pos: ChunkPos) -> Option<Vec<u8>> {
+        let path = self.chunk_path(chunk_pos);
+        match self.load_saved(&path, chunk_pos) {
+            Some(blocks) => Some(blocks),
+            None => self.generate(chunk_pos),
+        }
+    }
+
+    fn save(&self, chunk_pos: ChunkPos, blocks: Vec<u8>) {
+        let path = self.chunk_path(chunk_pos);
+        let raw = WorldChunkRaw {
+            idx: 0,
+            pos: Some(chunk_pos),
+            blocks,
+        };
+
+        let s = serde_json::to_string(&raw).unwrap(); // serializing numbers never fails
+        let result = fs::create_dir_all(&self.dir).and_then(|_| fs::write(&path, s));
+
+        if let Err(err) = result {
+            log::error!("save chunk error. path: {:?}, err: {:?}", path, err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process;
+
+    use super::*;
+
+    /// empty dir for test. removed when dropped
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("voxrs_{}_{}", name, process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            Self(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn test_generate() {
+        let provider = TerrainProvider::new("unused", 1);
+
+        // deterministic with seed
+        let other = TerrainProvider::new("unused", 1);
+        let chunk_pos = ChunkPos::new(-1, -1, 2);
+        assert_eq!(provider.generate(chunk_pos), other.generate(chunk_pos));
+
+        // filled below surface, empty above
+        let blocks = provider.generate(chunk_pos).unwrap();
+        for (block_idx, block) in blocks.iter().enumerate() {
+            let block_pos = BlockPos::from_chunk_pos(chunk_pos, block_idx);
+            let height = provider.height(block_pos.x, block_pos.z);
+            assert_eq!(*block != 0, block_pos.y < height);
+        }
+
+        // high chunk is empty
+        assert_eq!(provider.generate(ChunkPos::new(0, 4, 0)), None);
+    }
+
+    #[test]
+    fn test_save_load_round_trip() {
+        let dir = TempDir::new("terrain_round_trip");
+        let provider = TerrainProvider::new(&dir.0, 3);
+
+        let chunk_pos = ChunkPos::new(-2, -1, 5);
+        let mut blocks = provider.load(chunk_pos).unwrap();
+        assert_eq!(Some(&blocks), provider.generate(chunk_pos).as_ref());
+
+        blocks[0] = 0;
+        blocks[TOTAL_BLOCK_COUNTS_IN_CHUNK - 1] = 7;
+        provider.save(chunk_pos, blocks.clone());
+        assert_eq!(provider.load(chunk_pos), Some(blocks.clone()));
+
+        // saved chunk is loaded by new provider, other chunks are still generated
+        let provider = TerrainProvider::new(&dir.0, 3);
+        assert_eq!(provider.load(chunk_pos), Some(blocks));
+        let other_pos = ChunkPos::new(-2, -1, 6);
+        assert_eq!(provider.load(other_pos), provider.generate(other_pos));
+
+        // emptied chunk is saved too, so it is not generated again
+        provider.save(other_pos, vec![0; TOTAL_BLOCK_COUNTS_IN_CHUNK]);
+        assert_eq!(provider.load(other_pos), Some(vec![0; TOTAL_BLOCK_COUNTS_IN_CHUNK]));
+    }
+}
@@ -1,8 +1,13 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
 use enumflags2::BitFlags;
 use rayon::prelude::*;
 
 use voxrs_asset::{
-    AssetHandle, AssetManager, AssetPath, BlockSize, WorldBlockAsset, WorldBlockAssetRaw, WorldChunkRaw,
+    build_vis, AssetHandle, AssetManager, AssetPath, BlockSize, WorldBlockAsset, WorldBlockAssetRaw, WorldChunkRaw,
 };
 use voxrs_math::*;
 use voxrs_types::io::FileSystem;
@@ -11,50 +16,378 @@ use voxrs_types::SafeCloner;
 
 use voxrs_render::blueprint::{BlockMatIdx, Chunk};
 
-use super::CameraRes;
+use super::{
+    light::{is_lit_from, relight, LightWorld},
+    CameraRes, ChunkProvider, ChunkStreamer, StreamSettings,
+};
 
 pub struct WorldBlockRes {
     pub handle: AssetHandle<WorldBlockAsset>,
-    pub chunks: Vec<Option<SafeCloner<Chunk>>>,
-    pub chunk_counts: WorldChunkCounts,
+    pub chunks: HashMap<ChunkPos, SafeCloner<Chunk>>,
+    pub chunk_counts: Option<WorldChunkCounts>, // None if world is unbounded
     pub block_size: BlockSize,
+    pub stream: Option<ChunkStreamer>, // None if all chunks are loaded at once
+    occlusion: Arc<BlockOcclusion>,    // from alpha of world materials
+    emission: BlockEmission,           // from world materials
+    border_dirty: HashSet<ChunkPos>,   // chunks changed since borders of their neighbors are refreshed
+    heights: HashMap<(i32, i32), i32>, // highest opaque block which may exist in each column (x, z). kept on eviction
 }
 
 impl WorldBlockRes {
+    /// blocks are moved out of asset, so they are not kept twice
     pub fn new<F: FileSystem>(path: &AssetPath, asset_manager: &mut AssetManager<F>) -> Self {
-        let handle = asset_manager.get::<WorldBlockAsset>(path);
-        let mut chunks = Vec::new();
+        let mut handle = asset_manager.get::<WorldBlockAsset>(path);
 
         let chunk_counts = handle.get_asset().chunk_counts;
         let block_size = handle.get_asset().block_size;
+        let occlusion = Arc::new(handle.get_asset().world_material.get_asset().block_occlusion());
+        let emission = handle.get_asset().world_material.get_asset().block_emission();
 
-        {
-            let asset = handle.get_asset();
-            let chunk_size = asset.block_size.to_f32() * BLOCK_COUNT_IN_CHUNKSIDE as f32;
-            let chunk_extend = Vec3::new(chunk_size, chunk_size, chunk_size);
-
-            chunks.resize_with(asset.world_chunks.len(), Default::default);
+        let world_chunks = std::mem::take(&mut handle.get_asset_mut().world_chunks);
+        let mut vis = world_chunks
+            .par_iter()
+            .map(|(chunk_pos, _)| {
+                let vis = build_vis(*chunk_pos, &occlusion, |pos| {
+                    world_chunks.get(&pos).map(|chunk| &chunk.blocks)
+                });
+                (*chunk_pos, vis)
+            })
+            .collect::<HashMap<_, _>>();
 
-            for chunk_asset in asset.world_chunks.iter().flatten() {
-                let pos = asset.get_world_pos(chunk_asset.idx as usize);
+        let chunks: HashMap<ChunkPos, SafeCloner<Chunk>> = world_chunks
+            .into_iter()
+            .map(|(chunk_pos, chunk_asset)| {
                 let chunk = SafeCloner::new(Chunk::new(
-                    pos,
-                    Aabb::new(pos, pos + chunk_extend),
-                    chunk_asset.blocks.clone(),
-                    chunk_asset.vis.clone(),
+                    chunk_pos,
+                    chunk_pos.get_world_pos(block_size.to_f32()),
+                    chunk_pos.aabb(block_size.to_f32()),
+                    chunk_asset.blocks,
+                    vis.remove(&chunk_pos).unwrap(),
                 ));
-                chunks[chunk_asset.idx as usize] = Some(chunk);
-            }
-        }
+                (chunk_pos, chunk)
+            })
+            .collect();
 
-        Self {
+        let chunk_positions = chunks.keys().copied().collect::<Vec<_>>();
+
+        let mut world_block = Self {
             handle,
             chunks,
             chunk_counts,
             block_size,
+            stream: None,
+            occlusion,
+            emission,
+            border_dirty: HashSet::new(),
+            heights: HashMap::new(),
+        };
+        world_block.relight_chunks(&chunk_positions, &[]);
+        world_block.border_dirty.extend(chunk_positions);
+        world_block.refresh_borders();
+        world_block
+    }
+
+    /// chunks are not loaded from asset. they are streamed from provider around camera
+    /// asset is used for world size, block size and material
+    pub fn new_streaming<F: FileSystem>(
+        path: &AssetPath,
+        asset_manager: &mut AssetManager<F>,
+        provider: Arc<dyn ChunkProvider>,
+        settings: StreamSettings,
+    ) -> Self {
+        let handle = asset_manager.get::<WorldBlockAsset>(path);
+
+        let chunk_counts = handle.get_asset().chunk_counts;
+        let block_size = handle.get_asset().block_size;
+        let occlusion = Arc::new(handle.get_asset().world_material.get_asset().block_occlusion());
+        let emission = handle.get_asset().world_material.get_asset().block_emission();
+
+        Self {
+            handle,
+            chunks: HashMap::new(),
+            chunk_counts,
+            block_size,
+            stream: Some(ChunkStreamer::new(provider, settings, occlusion.clone())),
+            occlusion,
+            emission,
+            border_dirty: HashSet::new(),
+            heights: HashMap::new(),
         }
     }
 
+    /// evict far chunks, integrate loaded chunks (at most integrate_budget), and request chunks near camera
+    /// do nothing if world is not streaming
+    pub fn update_streaming(&mut self, camera: &CameraRes) {
+        let mut stream = match self.stream.take() {
+            Some(stream) => stream,
+            None => return,
+        };
+
+        let center = BlockPos::from_vec3(&camera.eye(), self.block_size.to_f32()).get_chunk_pos();
+
+        // evict
+        let evicted = self
+            .chunks
+            .keys()
+            .filter(|chunk_pos| !stream.is_in_unload_range(center, **chunk_pos))
+            .copied()
+            .collect::<Vec<_>>();
+
+        for chunk_pos in evicted {
+            if let Some(chunk) = self.chunks.remove(&chunk_pos) {
+                stream.evict(chunk_pos, &chunk.blocks);
+                self.border_dirty.insert(chunk_pos);
+                self.refresh_neighbor_borders(chunk_pos);
+                self.relight_neighbor_borders(chunk_pos, &chunk);
+            }
+        }
+        stream.evict_empty(center);
+
+        // integrate
+        for loaded in stream.receive() {
+            if self.chunks.contains_key(&loaded.pos) || !stream.is_in_unload_range(center, loaded.pos) {
+                continue;
+            }
+
+            if let Some((blocks, vis)) = loaded.blocks {
+                let block_size = self.block_size.to_f32();
+                let chunk = Chunk::new(
+                    loaded.pos,
+                    loaded.pos.get_world_pos(block_size),
+                    loaded.pos.aabb(block_size),
+                    blocks,
+                    vis,
+                );
+                self.chunks.insert(loaded.pos, SafeCloner::new(chunk));
+                self.mark_inserted(loaded.pos);
+                self.refresh_neighbor_borders(loaded.pos);
+                self.relight_chunks(&[loaded.pos], &[]);
+            }
+        }
+        self.refresh_borders();
+
+        // request
+        let chunks = &self.chunks;
+        let chunk_counts = self.chunk_counts;
+        stream.request_loads(
+            center,
+            |chunk_pos| chunks.contains_key(&chunk_pos),
+            |chunk_pos| match &chunk_counts {
+                Some(chunk_counts) => chunk_pos.is_valid(chunk_counts),
+                None => true,
+            },
+        );
+
+        self.stream = Some(stream);
+    }
+
+    /// save modified resident chunks and wait until saves land. game should call this on shutdown
+    /// do nothing if world is not streaming
+    pub fn flush_streaming(&mut self) {
+        if let Some(stream) = &mut self.stream {
+            stream.flush(self.chunks.iter().map(|(chunk_pos, chunk)| (*chunk_pos, &chunk.blocks)));
+        }
+    }
+
+    /// refresh vis of blocks at borders between chunk (chunk_pos) and its neighbors
+    fn refresh_neighbor_borders(&mut self, chunk_pos: ChunkPos) {
+        for dir in BitFlags::<Dir>::all().iter() {
+            for block_pos in chunk_pos.border_blocks(dir) {
+                self.refresh_block_vis(block_pos);
+                self.refresh_block_vis(block_pos.get_neighbor(dir));
+            }
+        }
+    }
+
+    /// relight blocks at borders of neighbors facing removed chunk (chunk_pos)
+    /// only blocks lit from removed chunk, and blocks which become open to sky are relit
+    fn relight_neighbor_borders(&mut self, chunk_pos: ChunkPos, removed: &Chunk) {
+        let mut blocks = Vec::new();
+        for dir in BitFlags::<Dir>::all().iter() {
+            for block_pos in chunk_pos.get_neighbor(dir).border_blocks(dir.opposite_dir()) {
+                let light = match self.get(block_pos) {
+                    Some((_, light)) => light,
+                    None => continue,
+                };
+
+                let removed_pos = block_pos.get_neighbor(dir.opposite_dir());
+                let removed_light = removed.light.get(removed_pos.get_block_index());
+                let is_open =
+                    dir == Dir::YNeg && self.is_open_sky(removed_pos) && LightChannel::Sky.get(light) < MAX_LIGHT;
+
+                if is_open || is_lit_from(light, removed_light, dir.opposite_dir()) {
+                    blocks.push(block_pos);
+                }
+            }
+        }
+
+        relight(self, &blocks);
+    }
+
+    /// relight all blocks of inserted or wholly changed chunks, and changed blocks
+    fn relight_chunks(&mut self, chunks: &[ChunkPos], changed: &[BlockPos]) {
+        let mut blocks = Vec::with_capacity(chunks.len() * TOTAL_BLOCK_COUNTS_IN_CHUNK + changed.len());
+        for chunk_pos in chunks {
+            blocks.extend(
+                (0..TOTAL_BLOCK_COUNTS_IN_CHUNK).map(|block_idx| BlockPos::from_chunk_pos(*chunk_pos, block_idx)),
+            );
+        }
+        blocks.extend_from_slice(changed);
+        blocks.extend(self.update_heights(chunks, changed));
+        relight(self, &blocks);
+
+        // top blocks of chunks below were open to sky. relight them if sky doesn't fall from above anymore
+        let sky = |res: &Self, block_pos| res.get(block_pos).map(|(_, light)| LightChannel::Sky.get(light));
+        let covered = chunks
+            .iter()
+            .flat_map(|chunk_pos| chunk_pos.get_neighbor(Dir::YNeg).border_blocks(Dir::YPos))
+            .filter(|block_pos| {
+                sky(self, *block_pos) == Some(MAX_LIGHT)
+                    && sky(self, block_pos.get_neighbor(Dir::YPos)) != Some(MAX_LIGHT)
+            })
+            .collect::<Vec<_>>();
+        relight(self, &covered);
+    }
+
+    /// update heights of columns in inserted or wholly changed chunks, and of changed blocks
+    /// return top blocks of resident chunks under missing chunks, whose open sky is changed
+    fn update_heights(&mut self, chunks: &[ChunkPos], changed: &[BlockPos]) -> Vec<BlockPos> {
+        let side = BLOCK_COUNT_IN_CHUNKSIDE as i32;
+
+        // changed range (bottom, top inclusive) of each column
+        let mut ranges = Vec::with_capacity(chunks.len() * (side * side) as usize + changed.len());
+        for chunk_pos in chunks {
+            let origin = BlockPos::from_chunk_pos(*chunk_pos, 0);
+            for z in origin.z..origin.z + side {
+                for x in origin.x..origin.x + side {
+                    ranges.push((x, z, origin.y, origin.y + side - 1));
+                }
+            }
+        }
+        ranges.extend(
+            changed
+                .iter()
+                .map(|block_pos| (block_pos.x, block_pos.z, block_pos.y, block_pos.y)),
+        );
+
+        let mut columns = None; // built only if some height is changed
+        let mut sources = Vec::new();
+        for (x, z, bottom, top) in ranges {
+            // higher block is not changed
+            let old = self.column_height(x, z);
+            if old > top {
+                continue;
+            }
+
+            let chunk_pos = BlockPos::new(x, 0, z).get_chunk_pos();
+            let new = match (bottom..=top).rev().find(|y| self.is_opaque(BlockPos::new(x, *y, z))) {
+                Some(y) => y,
+                None if old < bottom => old,
+                None => {
+                    let columns = columns.get_or_insert_with(|| self.resident_columns());
+                    let lowest = columns.get(&(chunk_pos.x, chunk_pos.z)).and_then(|ys| ys.iter().min());
+                    self.scan_height(x, bottom - 1, z, lowest.copied())
+                }
+            };
+
+            if new == old {
+                continue;
+            }
+
+            if new == i32::MIN {
+                self.heights.remove(&(x, z));
+            } else {
+                self.heights.insert((x, z), new);
+            }
+
+            // missing blocks between old and new height are opened or covered
+            let columns = columns.get_or_insert_with(|| self.resident_columns());
+            let (low, high) = (old.min(new), old.max(new));
+            for cy in columns.get(&(chunk_pos.x, chunk_pos.z)).into_iter().flatten() {
+                let top_y = cy * side + side - 1;
+                let above = ChunkPos::new(chunk_pos.x, cy + 1, chunk_pos.z);
+                if (low..high).contains(&top_y) && !self.chunks.contains_key(&above) {
+                    sources.push(BlockPos::new(x, top_y, z));
+                }
+            }
+
+            // so are borders of resident neighbors facing them
+            let near = (-1..=1)
+                .flat_map(|dz| (-1..=1).map(move |dx| (chunk_pos.x + dx, chunk_pos.z + dz)))
+                .filter_map(|column| columns.get(&column))
+                .flatten()
+                .flat_map(|cy| cy - 1..=cy + 1);
+            for cy in near {
+                let missing = ChunkPos::new(chunk_pos.x, cy, chunk_pos.z);
+                if cy * side + side - 1 > low && cy * side <= high && !self.chunks.contains_key(&missing) {
+                    self.border_dirty.insert(missing);
+                }
+            }
+        }
+
+        sources
+    }
+
+    /// y of highest opaque block which may exist in column (x, z). i32::MIN if none
+    fn column_height(&self, x: i32, z: i32) -> i32 {
+        self.heights.get(&(x, z)).copied().unwrap_or(i32::MIN)
+    }
+
+    /// highest opaque block which may exist in column (x, z) at or below y
+    /// missing chunk may have blocks if it is not editable (see is_editable_chunk)
+    /// blocks below lowest resident chunk (lowest_chunk) are never above resident block, so they are ignored
+    fn scan_height(&self, x: i32, mut y: i32, z: i32, lowest_chunk: Option<i32>) -> i32 {
+        let side = BLOCK_COUNT_IN_CHUNKSIDE as i32;
+        let lowest = match lowest_chunk {
+            Some(cy) => cy * side,
+            None => return i32::MIN,
+        };
+
+        while y >= lowest {
+            let block_pos = BlockPos::new(x, y, z);
+            let chunk_pos = block_pos.get_chunk_pos();
+            if self.chunks.contains_key(&chunk_pos) {
+                if self.is_opaque(block_pos) {
+                    return y;
+                }
+                y -= 1;
+            } else if !self.is_editable_chunk(chunk_pos) {
+                return y;
+            } else {
+                y = chunk_pos.y * side - 1;
+            }
+        }
+
+        i32::MIN
+    }
+
+    /// y of resident chunks in each column of chunks (x, z)
+    fn resident_columns(&self) -> HashMap<(i32, i32), Vec<i32>> {
+        let mut columns = HashMap::new();
+        for chunk_pos in self.chunks.keys() {
+            columns
+                .entry((chunk_pos.x, chunk_pos.z))
+                .or_insert_with(Vec::new)
+                .push(chunk_pos.y);
+        }
+        columns
+    }
+
+    /// rebuild vis of a block from its neighbors
+    /// do nothing if chunk of block is not resident
+    fn refresh_block_vis(&mut self, block_pos: BlockPos) {
+        let vis = match self.get_block(block_pos) {
+            Some(block) if block != 0 => BitFlags::<Dir>::all()
+                .iter()
+                .filter(|dir| self.is_block_visible_dir(block_pos, block, *dir))
+                .collect(),
+            Some(_) => BitFlags::empty(),
+            None => return,
+        };
+
+        self.set_block_vis(block_pos, vis);
+    }
+
     pub fn frustum_culling(&self, camera: &CameraRes) -> Vec<&SafeCloner<Chunk>> {
         //let mut culled = Vec::new();
 
@@ -64,8 +397,8 @@ impl WorldBlockRes {
         let chunks = self
             .chunks
             .par_iter()
-            .filter_map(|c| c.as_ref()) // remove none
-            .filter(|c| camera_sphere.intersect_aabb(&c.aabb) && frustum.cull_aabb(&c.aabb))
+            .map(|(_, c)| c)
+            .filter(|c| frustum.check_sphere_aabb(&camera_sphere, &c.aabb) != CullResult::Outside)
             .collect();
 
         chunks
@@ -81,110 +414,397 @@ impl WorldBlockRes {
         // culled
     }
 
+    /// bounded world: block_pos should be in world
+    /// unbounded world: always true
+    pub fn is_valid_block(&self, block_pos: BlockPos) -> bool {
+        match &self.chunk_counts {
+            Some(chunk_counts) => block_pos.is_valid(chunk_counts),
+            None => true,
+        }
+    }
+
     pub fn get_block(&self, block_pos: BlockPos) -> Option<u8> {
-        if let Some((chunk_idx, block_idx)) = block_pos.get_index(&self.chunk_counts) {
-            let chunk = self.chunks[chunk_idx].as_ref();
-            chunk.map(|chunk| chunk.blocks[block_idx])
-        } else {
-            None
+        if !self.is_valid_block(block_pos) {
+            return None;
         }
+
+        let chunk = self.chunks.get(&block_pos.get_chunk_pos());
+        chunk.map(|chunk| chunk.blocks.get(block_pos.get_block_index()))
     }
 
+    /// ignored if chunk is not loaded yet while streaming (see is_editable_chunk)
     pub fn set_block(&mut self, block_pos: BlockPos, block_val: BlockMatIdx) {
-        let idx = block_pos.get_index(&self.chunk_counts);
-        if idx == None {
+        if !self.is_valid_block(block_pos) {
             return;
         }
 
-        let (chunk_idx, block_idx) = idx.unwrap();
+        let chunk_pos = block_pos.get_chunk_pos();
+        let block_idx = block_pos.get_block_index();
+
+        if !self.is_editable_chunk(chunk_pos) {
+            return;
+        }
+
+        // missing chunk is already empty
+        if block_val == 0 && !self.chunks.contains_key(&chunk_pos) {
+            return;
+        }
+
+        if let Some(stream) = &mut self.stream {
+            stream.mark_dirty(chunk_pos);
+        }
 
         // change block value
-        let chunk = self.chunks.get_mut(chunk_idx).unwrap().as_mut();
-        if let Some(chunk) = chunk {
-            chunk.blocks[block_idx] = block_val;
+        let created = !self.chunks.contains_key(&chunk_pos);
+        self.get_or_create_chunk(chunk_pos).blocks.set(block_idx, block_val);
+        self.border_dirty.insert(chunk_pos);
+
+        if created {
+            self.relight_chunks(&[chunk_pos], &[]);
         } else {
-            let asset = self.handle.get_asset();
+            self.relight_chunks(&[], &[block_pos]);
+        }
+
+        // refresh vis of block and its neighbors from blocks, so it is always same as build_vis
+        self.refresh_block_vis(block_pos);
+        for dir in BitFlags::<Dir>::all().iter() {
+            self.refresh_block_vis(block_pos.get_neighbor(dir));
+        }
+
+        self.refresh_borders();
+    }
+
+    /// fill blocks in region (min, max inclusive) with block_val
+    /// chunks fully inside region are filled at once
+    /// return modified chunks
+    pub fn fill_region(&mut self, min: BlockPos, max: BlockPos, block_val: BlockMatIdx) -> HashSet<ChunkPos> {
+        self.edit_region(min, max, |_| Some(block_val))
+    }
 
-            let pos = asset.get_world_pos(chunk_idx);
-            let aabb = asset.get_chunk_aabb(chunk_idx);
+    /// replace blocks which are from_val in region (min, max inclusive) with to_val
+    /// return modified chunks
+    pub fn replace_in_region(
+        &mut self,
+        min: BlockPos,
+        max: BlockPos,
+        from_val: BlockMatIdx,
+        to_val: BlockMatIdx,
+    ) -> HashSet<ChunkPos> {
+        self.edit_region(min, max, |block| if block == from_val { Some(to_val) } else { None })
+    }
+
+    /// set blocks in bulk. vis is refreshed once after all edits
+    /// edits to chunks not loaded yet while streaming are ignored
+    /// return modified chunks
+    pub fn apply_edits(&mut self, edits: impl IntoIterator<Item = (BlockPos, BlockMatIdx)>) -> HashSet<ChunkPos> {
+        let mut modified = HashSet::new();
+        let mut created = Vec::new();
+        let mut changed = Vec::new();
+
+        for (block_pos, block_val) in edits {
+            if !self.is_valid_block(block_pos) {
+                continue;
+            }
 
-            let mut blocks = Vec::new();
-            blocks.resize_with(TOTAL_BLOCK_COUNTS_IN_CHUNK, Default::default);
-            blocks[block_idx] = block_val;
+            let chunk_pos = block_pos.get_chunk_pos();
+            let block_idx = block_pos.get_block_index();
 
-            let mut vis = Vec::<BitFlags<Dir>>::new();
-            vis.extend([BitFlags::empty(); TOTAL_BLOCK_COUNTS_IN_CHUNK].iter());
-            if block_val != 0 {
-                vis[block_idx] = BitFlags::all();
+            if !self.is_editable_chunk(chunk_pos) {
+                continue;
             }
 
-            let chunk = SafeCloner::new(Chunk::new(pos, aabb, blocks, vis));
+            if self.get_block(block_pos).unwrap_or(0) == block_val {
+                continue;
+            }
+
+            if !self.chunks.contains_key(&chunk_pos) {
+                created.push(chunk_pos);
+            }
 
-            self.chunks[chunk_idx] = Some(chunk);
+            let chunk = self.get_or_create_chunk(chunk_pos);
+            chunk.blocks.set(block_idx, block_val);
+            modified.insert(chunk_pos);
+            changed.push(block_pos);
         }
 
-        // refresh vis
+        self.refresh_modified_chunks(&modified);
+        self.relight_chunks(&created, &changed);
+        self.refresh_borders();
 
-        // check self vis
-        {
-            //let mut vis = BitFlags::<Dir>::empty();
-            let mut vis = BitFlags::<Dir>::default();
-            for dir in BitFlags::<Dir>::all().iter() {
-                if self.is_block_visible_dir(block_pos, dir) {
-                    vis |= dir;
+        modified
+    }
+
+    /// change blocks in region (min, max inclusive)
+    /// edit returns new value from old value, or None if not changed
+    fn edit_region(
+        &mut self,
+        min: BlockPos,
+        max: BlockPos,
+        edit: impl Fn(BlockMatIdx) -> Option<BlockMatIdx>,
+    ) -> HashSet<ChunkPos> {
+        let (min, max) = (
+            BlockPos::new(min.x.min(max.x), min.y.min(max.y), min.z.min(max.z)),
+            BlockPos::new(min.x.max(max.x), min.y.max(max.y), min.z.max(max.z)),
+        );
+
+        let side = BLOCK_COUNT_IN_CHUNKSIDE as i32;
+        let min_chunk = min.get_chunk_pos();
+        let max_chunk = max.get_chunk_pos();
+
+        let mut modified = HashSet::new();
+        let mut relit = Vec::new(); // created or wholly filled chunks
+        let mut changed = Vec::new();
+
+        for cz in min_chunk.z..=max_chunk.z {
+            for cy in min_chunk.y..=max_chunk.y {
+                for cx in min_chunk.x..=max_chunk.x {
+                    let chunk_pos = ChunkPos::new(cx, cy, cz);
+                    if let Some(chunk_counts) = &self.chunk_counts {
+                        if !chunk_pos.is_valid(chunk_counts) {
+                            continue;
+                        }
+                    }
+
+                    if !self.is_editable_chunk(chunk_pos) {
+                        continue;
+                    }
+
+                    // region in this chunk
+                    let chunk_min = BlockPos::new(cx * side, cy * side, cz * side);
+                    let from = BlockPos::new(min.x.max(chunk_min.x), min.y.max(chunk_min.y), min.z.max(chunk_min.z));
+                    let to = BlockPos::new(
+                        max.x.min(chunk_min.x + side - 1),
+                        max.y.min(chunk_min.y + side - 1),
+                        max.z.min(chunk_min.z + side - 1),
+                    );
+                    let full = from == chunk_min
+                        && to == BlockPos::new(chunk_min.x + side - 1, chunk_min.y + side - 1, chunk_min.z + side - 1);
+
+                    // missing chunk is all empty (known empty if streaming)
+                    let uniform = match self.chunks.get(&chunk_pos) {
+                        Some(chunk) => chunk.blocks.uniform(),
+                        None => Some(0),
+                    };
+
+                    // whole chunk has same value, so edit once
+                    if let (true, Some(uniform)) = (full, uniform) {
+                        match edit(uniform) {
+                            Some(block_val) if block_val != uniform => {
+                                self.get_or_create_chunk(chunk_pos).blocks.fill(block_val);
+                                modified.insert(chunk_pos);
+                                relit.push(chunk_pos);
+                            }
+                            _ => {}
+                        }
+                        continue;
+                    }
+
+                    // nothing to change in missing chunk
+                    if !self.chunks.contains_key(&chunk_pos) && !matches!(edit(0), Some(block_val) if block_val != 0) {
+                        continue;
+                    }
+
+                    if !self.chunks.contains_key(&chunk_pos) {
+                        relit.push(chunk_pos);
+                    }
+
+                    let chunk = self.get_or_create_chunk(chunk_pos);
+                    let changed_count = changed.len();
+                    for z in from.z..=to.z {
+                        for y in from.y..=to.y {
+                            for x in from.x..=to.x {
+                                let block_pos = BlockPos::new(x, y, z);
+                                let block_idx = block_pos.get_block_index();
+                                let block = chunk.blocks.get(block_idx);
+                                if let Some(block_val) = edit(block) {
+                                    if block_val != block {
+                                        chunk.blocks.set(block_idx, block_val);
+                                        changed.push(block_pos);
+                                    }
+                                }
+                            }
+                        }
+                    }
+
+                    if changed.len() > changed_count {
+                        modified.insert(chunk_pos);
+                    }
                 }
             }
-            self.set_block_vis(block_pos, vis);
         }
 
-        // check neighbor
-        {
-            let check_dirs = BitFlags::<Dir>::all();
-            for check_dir in check_dirs.iter() {
-                let neighbor_pos = block_pos.get_neighbor(check_dir);
-                if neighbor_pos.is_valid(&self.chunk_counts) {
-                    let neighbor_vis = self.get_block_vis(neighbor_pos);
-                    if let Some(mut neighbor_vis) = neighbor_vis {
-                        if block_val == 0 {
-                            neighbor_vis |= check_dir.opposite_dir();
-                        } else {
-                            neighbor_vis ^= check_dir.opposite_dir();
+        self.refresh_modified_chunks(&modified);
+        self.relight_chunks(&relit, &changed);
+        self.refresh_borders();
+
+        modified
+    }
+
+    /// resident chunk, or missing chunk which is all empty
+    /// while streaming, missing chunk is empty only if provider returned no blocks for it.
+    /// other missing chunks are loading or not requested yet, so edits to them would lose loaded blocks
+    fn is_editable_chunk(&self, chunk_pos: ChunkPos) -> bool {
+        if self.chunks.contains_key(&chunk_pos) {
+            return true;
+        }
+
+        match &self.stream {
+            Some(stream) => stream.is_known_empty(chunk_pos),
+            None => true,
+        }
+    }
+
+    /// chunk at chunk_pos. new empty chunk is inserted if not exists
+    fn get_or_create_chunk(&mut self, chunk_pos: ChunkPos) -> &mut Chunk {
+        if !self.chunks.contains_key(&chunk_pos) {
+            self.mark_inserted(chunk_pos);
+        }
+
+        let block_size = self.block_size.to_f32();
+        let chunk = self.chunks.entry(chunk_pos).or_insert_with(|| {
+            SafeCloner::new(Chunk::new(
+                chunk_pos,
+                chunk_pos.get_world_pos(block_size),
+                chunk_pos.aabb(block_size),
+                PaletteArray::new(TOTAL_BLOCK_COUNTS_IN_CHUNK, 0),
+                PaletteArray::new(TOTAL_BLOCK_COUNTS_IN_CHUNK, BitFlags::empty()),
+            ))
+        });
+
+        chunk
+    }
+
+    /// border of inserted chunk is filled from all of its neighbors
+    fn mark_inserted(&mut self, chunk_pos: ChunkPos) {
+        self.border_dirty.insert(chunk_pos);
+        for (dx, dy, dz) in neighbor_offsets() {
+            self.border_dirty
+                .insert(ChunkPos::new(chunk_pos.x + dx, chunk_pos.y + dy, chunk_pos.z + dz));
+        }
+    }
+
+    /// copy light and opacity of chunks changed since last refresh into borders of their neighbors
+    /// chunk is written only if its border is changed, so its mesh is not rebuilt for nothing
+    fn refresh_borders(&mut self) {
+        let side = BLOCK_COUNT_IN_CHUNKSIDE as i32;
+
+        for changed in std::mem::take(&mut self.border_dirty) {
+            // changed chunk is at offset from target
+            for (dx, dy, dz) in neighbor_offsets() {
+                let target = ChunkPos::new(changed.x - dx, changed.y - dy, changed.z - dz);
+                let chunk = match self.chunks.get(&target) {
+                    Some(chunk) => chunk,
+                    None => continue,
+                };
+
+                // border blocks of target in changed chunk. local to target
+                let range = |d: i32| match d {
+                    -1 => -1..=-1,
+                    0 => 0..=side - 1,
+                    _ => side..=side,
+                };
+                let origin = BlockPos::from_chunk_pos(target, 0);
+                let mut updates = Vec::new();
+                for z in range(dz) {
+                    for y in range(dy) {
+                        for x in range(dx) {
+                            let local_pos = BlockPos::new(x, y, z);
+                            let block_pos = BlockPos::new(origin.x + x, origin.y + y, origin.z + z);
+                            let light = self.get_border_light(block_pos);
+                            let opaque = self.is_opaque(block_pos);
+                            if chunk.border.get_light(local_pos) != light || chunk.border.is_opaque(local_pos) != opaque
+                            {
+                                updates.push((local_pos, light, opaque));
+                            }
                         }
+                    }
+                }
 
-                        self.set_block_vis(neighbor_pos, neighbor_vis);
+                if !updates.is_empty() {
+                    let chunk = self.chunks.get_mut(&target).unwrap();
+                    for (local_pos, light, opaque) in updates {
+                        chunk.border.set_light(local_pos, light);
+                        chunk.border.set_opaque(local_pos, opaque);
                     }
                 }
             }
         }
     }
 
-    /// chunk indicated by block_pos should valid
-    /// else this function panic
+    /// light of block seen from border of neighbor chunk
+    /// missing block is lit only by sky, if it is open to sky
+    fn get_border_light(&self, block_pos: BlockPos) -> u8 {
+        match self.get(block_pos) {
+            Some((_, light)) => light,
+            None if self.is_open_sky(block_pos) => LightChannel::Sky.set(0, MAX_LIGHT),
+            None => 0,
+        }
+    }
+
+    /// rebuild vis of modified chunks, and borders of their unmodified neighbors
+    /// mark modified chunks dirty for streaming and for border refresh
+    fn refresh_modified_chunks(&mut self, modified: &HashSet<ChunkPos>) {
+        self.border_dirty.extend(modified.iter().copied());
+        for chunk_pos in modified {
+            let vis = build_vis(*chunk_pos, &self.occlusion, |pos| {
+                self.chunks.get(&pos).map(|chunk| &chunk.blocks)
+            });
+            self.chunks.get_mut(chunk_pos).unwrap().vis = vis;
+
+            if let Some(stream) = &mut self.stream {
+                stream.mark_dirty(*chunk_pos);
+            }
+        }
+
+        for chunk_pos in modified {
+            for dir in BitFlags::<Dir>::all().iter() {
+                let neighbor_pos = chunk_pos.get_neighbor(dir);
+                if modified.contains(&neighbor_pos) || !self.chunks.contains_key(&neighbor_pos) {
+                    continue;
+                }
+
+                for block_pos in neighbor_pos.border_blocks(dir.opposite_dir()) {
+                    self.refresh_block_vis(block_pos);
+                }
+            }
+        }
+    }
+
+    /// do nothing if chunk indicated by block_pos not exists
     fn set_block_vis(&mut self, block_pos: BlockPos, vis: BitFlags<Dir>) {
-        let (chunk_idx, block_idx) = block_pos.get_index(&self.chunk_counts).unwrap();
-        let chunk = self.chunks[chunk_idx].as_mut().unwrap();
-        chunk.vis[block_idx] = vis;
+        let block_idx = block_pos.get_block_index();
+        if let Some(chunk) = self.chunks.get_mut(&block_pos.get_chunk_pos()) {
+            // writing chunk clones it if it is shared with renderer, so skip if not changed
+            if chunk.vis.get(block_idx) != vis {
+                chunk.vis.set(block_idx, vis);
+            }
+        }
+    }
+
+    /// packed sky and block light (see LightChannel)
+    pub fn get_block_light(&self, block_pos: BlockPos) -> Option<u8> {
+        if !self.is_valid_block(block_pos) {
+            return None;
+        }
+
+        let chunk = self.chunks.get(&block_pos.get_chunk_pos());
+        chunk.map(|chunk| chunk.light.get(block_pos.get_block_index()))
     }
 
     fn get_block_vis(&self, block_pos: BlockPos) -> Option<BitFlags<Dir>> {
-        let idx = block_pos.get_index(&self.chunk_counts);
-        if let Some((chunk_idx, block_idx)) = idx {
-            let chunk = self.chunks[chunk_idx].as_ref();
-            chunk.map(|chunk| chunk.vis[block_idx])
-        } else {
-            None
+        if !self.is_valid_block(block_pos) {
+            return None;
         }
+
+        let chunk = self.chunks.get(&block_pos.get_chunk_pos());
+        chunk.map(|chunk| chunk.vis.get(block_pos.get_block_index()))
     }
 
-    fn is_block_visible_dir(&self, block_pos: BlockPos, dir: Dir) -> bool {
+    /// face of block at block_pos toward dir is not hidden by neighbor
+    fn is_block_visible_dir(&self, block_pos: BlockPos, block: u8, dir: Dir) -> bool {
         let neighbor_pos = block_pos.get_neighbor(dir);
-        if neighbor_pos.is_valid(&self.chunk_counts) {
-            let block = self.get_block(neighbor_pos);
-            if let Some(block) = block {
-                block == 0
-            } else {
-                true
-            }
+        if let Some(neighbor) = self.get_block(neighbor_pos) {
+            self.occlusion.is_face_visible(block, neighbor)
         } else {
             true
         }
@@ -195,20 +815,16 @@ impl WorldBlockRes {
 
         let block_iter = ray.block_iter_nth(block_size, 100);
         for block_pos in block_iter {
-            if let Some((chunk_idx, block_idx)) = block_pos.get_index(&self.chunk_counts) {
-                let chunk = &self.chunks[chunk_idx];
-                if let Some(chunk) = chunk {
-                    let block = chunk.blocks[block_idx];
-                    if block == 0 {
-                        continue;
-                    }
+            if let Some(block) = self.get_block(block_pos) {
+                if block == 0 {
+                    continue;
+                }
 
-                    let block_aabb = block_pos.aabb(block_size);
-                    if let RayAabbResult::Intersect { dir, .. } = ray.check_aabb(&block_aabb) {
-                        return Some((block_pos, dir));
-                    } else {
-                        log::error!("miss. block pos: {:?}, ray: {:?}", block_pos, ray);
-                    }
+                let block_aabb = block_pos.aabb(block_size);
+                if let RayAabbResult::Intersect { dir, .. } = ray.check_aabb(&block_aabb) {
+                    return Some((block_pos, dir));
+                } else {
+                    log::error!("miss. block pos: {:?}, ray: {:?}", block_pos, ray);
                 }
             }
         }
@@ -216,54 +832,370 @@ impl WorldBlockRes {
         None
     }
 
-    pub fn get_world_chunk_counts(&self) -> WorldChunkCounts {
-        let asset = self.handle.get_asset();
-        asset.chunk_counts
+    /// move aabb with motion, stopping and sliding at solid blocks
+    pub fn move_aabb(&self, aabb: &Aabb, motion: Vec3) -> MoveResult {
+        move_aabb(aabb, motion, self.block_size.to_f32(), |pos| self.is_solid(pos))
+    }
+
+    /// first solid block hit by aabb moving with motion
+    pub fn sweep_aabb(&self, aabb: &Aabb, motion: Vec3) -> Option<SweepHit> {
+        sweep_aabb(aabb, motion, self.block_size.to_f32(), |pos| self.is_solid(pos))
+    }
+
+    fn is_solid(&self, block_pos: BlockPos) -> bool {
+        matches!(self.get_block(block_pos), Some(block) if block != 0)
+    }
+
+    fn is_opaque(&self, block_pos: BlockPos) -> bool {
+        matches!(self.get_block(block_pos), Some(block) if self.occlusion.get(block) == Occlusion::Opaque)
+    }
+
+    /// None if world is unbounded
+    pub fn get_world_chunk_counts(&self) -> Option<WorldChunkCounts> {
+        self.chunk_counts
+    }
+
+    /// bytes used by resident chunks
+    pub fn memory_usage(&self) -> usize {
+        self.chunks.values().map(|chunk| chunk.memory_usage()).sum()
     }
 
     pub fn clear_blocks(&mut self) {
-        for chunk in self.chunks.iter_mut() {
-            *chunk = None;
-        }
+        self.chunks.clear();
+        self.border_dirty.clear();
+        self.heights.clear();
     }
 
     pub fn make_raw_asset(&self) -> WorldBlockAssetRaw {
-        let block_counts = WorldBlockCounts::new(
-            self.chunk_counts.x * BLOCK_COUNT_IN_CHUNKSIDE as i32,
-            self.chunk_counts.y * BLOCK_COUNT_IN_CHUNKSIDE as i32,
-            self.chunk_counts.z * BLOCK_COUNT_IN_CHUNKSIDE as i32,
-        );
+        let block_counts = self.chunk_counts.map(|chunk_counts| {
+            WorldBlockCounts::new(
+                chunk_counts.x * BLOCK_COUNT_IN_CHUNKSIDE as i32,
+                chunk_counts.y * BLOCK_COUNT_IN_CHUNKSIDE as i32,
+                chunk_counts.z * BLOCK_COUNT_IN_CHUNKSIDE as i32,
+            )
+        });
 
         let world_block = self.handle.get_asset();
         let world_material = world_block.world_material.asset_path();
 
-        let world_chunks = self
+        // bounded world saves chunk index, unbounded world saves chunk position
+        let mut world_chunks = self
             .chunks
             .iter()
-            .enumerate()
-            .filter_map(|(idx, chunk)| {
-                chunk.as_ref().map(|chunk| WorldChunkRaw {
-                    idx: idx as i32,
-                    blocks: chunk.blocks.clone(),
-                })
+            .map(|(chunk_pos, chunk)| match &self.chunk_counts {
+                Some(chunk_counts) => WorldChunkRaw {
+                    idx: chunk_pos.get_index(chunk_counts).unwrap() as i32,
+                    pos: None,
+                    blocks: chunk.blocks.to_vec(),
+                },
+                None => WorldChunkRaw {
+                    idx: 0,
+                    pos: Some(*chunk_pos),
+                    blocks: chunk.blocks.to_vec(),
+                },
             })
             .collect::<Vec<_>>();
 
+        world_chunks.sort_by_key(|chunk| (chunk.idx, chunk.pos.map(|pos| (pos.z, pos.y, pos.x))));
+
         WorldBlockAssetRaw {
             block_counts,
             block_size: self.block_size,
             world_material: world_material.to_string(),
+            fog: world_block.fog,
             world_chunks,
         }
     }
 }
 
+/// offsets of 26 chunks sharing face, edge or corner with chunk
+fn neighbor_offsets() -> impl Iterator<Item = (i32, i32, i32)> {
+    (0..27)
+        .map(|i| (i % 3 - 1, i / 3 % 3 - 1, i / 9 - 1))
+        .filter(|offset| *offset != (0, 0, 0))
+}
+
+impl LightWorld for WorldBlockRes {
+    fn get(&self, block_pos: BlockPos) -> Option<(u8, u8)> {
+        if !self.is_valid_block(block_pos) {
+            return None;
+        }
+
+        let block_idx = block_pos.get_block_index();
+        let chunk = self.chunks.get(&block_pos.get_chunk_pos());
+        chunk.map(|chunk| (chunk.blocks.get(block_idx), chunk.light.get(block_idx)))
+    }
+
+    fn set_light(&mut self, block_pos: BlockPos, light: u8) {
+        if !self.is_valid_block(block_pos) {
+            return;
+        }
+
+        let block_idx = block_pos.get_block_index();
+        if let Some(chunk) = self.chunks.get_mut(&block_pos.get_chunk_pos()) {
+            // writing chunk clones it if it is shared with renderer, so skip if not changed
+            if chunk.light.get(block_idx) != light {
+                chunk.light.set(block_idx, light);
+                self.border_dirty.insert(block_pos.get_chunk_pos());
+            }
+        }
+    }
+
+    fn is_open_sky(&self, block_pos: BlockPos) -> bool {
+        block_pos.y > self.column_height(block_pos.x, block_pos.z)
+    }
+
+    fn occlusion(&self) -> &BlockOcclusion {
+        &self.occlusion
+    }
+
+    fn emission(&self) -> &BlockEmission {
+        &self.emission
+    }
+}
+
 #[cfg(test)]
 mod test {
+    use std::{sync::Mutex, thread, time::Duration};
+
+    use proptest::prelude::*;
     use voxrs_types::io::tests::MockFileSystem;
 
     use super::*;
 
+    /// every chunk is full of block 1. saved chunk positions are recorded
+    #[derive(Default)]
+    struct SolidProvider {
+        saved: Mutex<Vec<ChunkPos>>,
+    }
+
+    impl ChunkProvider for SolidProvider {
+        fn load(&self, _chunk_pos: ChunkPos) -> Option<Vec<u8>> {
+            Some(vec![1; TOTAL_BLOCK_COUNTS_IN_CHUNK])
+        }
+
+        fn save(&self, chunk_pos: ChunkPos, _blocks: Vec<u8>) {
+            self.saved.lock().unwrap().push(chunk_pos);
+        }
+    }
+
+    /// chunks are full of block 1 until saved. save is slow, so chunk can be loaded again while it is saved
+    #[derive(Default)]
+    struct SlowSaveProvider {
+        chunks: Mutex<HashMap<ChunkPos, Vec<u8>>>,
+    }
+
+    impl ChunkProvider for SlowSaveProvider {
+        fn load(&self, chunk_pos: ChunkPos) -> Option<Vec<u8>> {
+            let chunks = self.chunks.lock().unwrap();
+            Some(
+                chunks
+                    .get(&chunk_pos)
+                    .cloned()
+                    .unwrap_or_else(|| vec![1; TOTAL_BLOCK_COUNTS_IN_CHUNK]),
+            )
+        }
+
+        fn save(&self, chunk_pos: ChunkPos, blocks: Vec<u8>) {
+            thread::sleep(Duration::from_millis(100));
+            self.chunks.lock().unwrap().insert(chunk_pos, blocks);
+        }
+    }
+
+    /// chunks at y 0 are full of block 1 except top layer, chunks at y 2 are full of block 1. others are empty
+    struct LayerProvider;
+
+    impl ChunkProvider for LayerProvider {
+        fn load(&self, chunk_pos: ChunkPos) -> Option<Vec<u8>> {
+            let side = BLOCK_COUNT_IN_CHUNKSIDE as i32;
+            match chunk_pos.y {
+                0 => Some(
+                    (0..TOTAL_BLOCK_COUNTS_IN_CHUNK)
+                        .map(|block_idx| (BlockPos::from_chunk_pos(chunk_pos, block_idx).y < side - 1) as u8)
+                        .collect(),
+                ),
+                2 => Some(vec![1; TOTAL_BLOCK_COUNTS_IN_CHUNK]),
+                _ => None,
+            }
+        }
+
+        fn save(&self, _chunk_pos: ChunkPos, _blocks: Vec<u8>) {}
+    }
+
+    fn create_camera(eye: Vec3) -> CameraRes {
+        CameraRes::new(
+            eye,
+            Angle::from_degrees(0.0),
+            Angle::from_degrees(0.0),
+            100,
+            100,
+            45.0,
+            0.1,
+            100.0,
+        )
+    }
+
+    fn create_unbounded() -> WorldBlockRes {
+        let mut manager = AssetManager::<MockFileSystem>::new();
+        let path: AssetPath = "world_block.wb".into();
+        let mut res = WorldBlockRes::new(&path, &mut manager);
+        res.clear_blocks();
+        res.chunk_counts = None;
+        res
+    }
+
+    /// block 2 and 3 are transparent
+    fn transparent_occlusion() -> Arc<BlockOcclusion> {
+        let mut occlusion = BlockOcclusion::default();
+        occlusion.set(2, Occlusion::Transparent);
+        occlusion.set(3, Occlusion::Transparent);
+        Arc::new(occlusion)
+    }
+
+    /// vis of every chunk should equal to vis built from scratch
+    fn assert_vis_rebuilt(res: &WorldBlockRes) {
+        for (chunk_pos, chunk) in &res.chunks {
+            let vis = build_vis(*chunk_pos, &res.occlusion, |pos| {
+                res.chunks.get(&pos).map(|chunk| &chunk.blocks)
+            });
+            assert_eq!(chunk.vis, vis, "vis mismatch in chunk {:?}", chunk_pos);
+        }
+    }
+
+    /// light of every block should equal to light relit from scratch
+    fn assert_light_rebuilt(res: &mut WorldBlockRes) {
+        let chunk_positions = res.chunks.keys().copied().collect::<Vec<_>>();
+        let lights = chunk_positions
+            .iter()
+            .map(|chunk_pos| (*chunk_pos, res.chunks[chunk_pos].light.clone()))
+            .collect::<Vec<_>>();
+
+        for chunk in res.chunks.values_mut() {
+            chunk.light.fill(0);
+        }
+        res.relight_chunks(&chunk_positions, &[]);
+
+        for (chunk_pos, light) in lights {
+            assert_eq!(
+                res.chunks[&chunk_pos].light, light,
+                "light mismatch in chunk {:?}",
+                chunk_pos
+            );
+        }
+    }
+
+    /// border of every chunk should equal to light and opacity of its neighbors
+    fn assert_borders_rebuilt(res: &WorldBlockRes) {
+        let side = BLOCK_COUNT_IN_CHUNKSIDE as i32;
+        for (chunk_pos, chunk) in &res.chunks {
+            let origin = BlockPos::from_chunk_pos(*chunk_pos, 0);
+            for z in -1..=side {
+                for y in -1..=side {
+                    for x in -1..=side {
+                        let local_pos = BlockPos::new(x, y, z);
+                        let block_pos = BlockPos::new(origin.x + x, origin.y + y, origin.z + z);
+                        if block_pos.get_chunk_pos() == *chunk_pos {
+                            continue;
+                        }
+
+                        assert_eq!(
+                            chunk.border.is_opaque(local_pos),
+                            res.is_opaque(block_pos),
+                            "border opacity mismatch in chunk {:?} at {:?}",
+                            chunk_pos,
+                            local_pos
+                        );
+                        assert_eq!(
+                            chunk.border.get_light(local_pos),
+                            res.get_border_light(block_pos),
+                            "border mismatch in chunk {:?} at {:?}",
+                            chunk_pos,
+                            local_pos
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// edit applied by property tests
+    #[derive(Clone, Debug)]
+    enum Edit {
+        Set(BlockPos, BlockMatIdx),
+        Fill(BlockPos, BlockPos, BlockMatIdx),
+        Batch(Vec<(BlockPos, BlockMatIdx)>),
+    }
+
+    impl Edit {
+        fn apply(&self, res: &mut WorldBlockRes) {
+            match self {
+                Edit::Set(block_pos, block) => {
+                    res.set_block(*block_pos, *block);
+                }
+                Edit::Fill(min, max, block) => {
+                    res.fill_region(*min, *max, *block);
+                }
+                Edit::Batch(edits) => {
+                    res.apply_edits(edits.clone());
+                }
+            }
+        }
+    }
+
+    /// coordinate in chunk -2..=1, biased to chunk borders
+    fn coord() -> impl Strategy<Value = i32> {
+        let side = BLOCK_COUNT_IN_CHUNKSIDE as i32;
+        prop_oneof![
+            -18..=18i32,
+            (-1..=1i32, -2..2i32).prop_map(move |(chunk, offset)| chunk * side + offset),
+        ]
+    }
+
+    /// z is narrow, so edited blocks are often neighbors
+    fn block_pos() -> impl Strategy<Value = BlockPos> {
+        (coord(), coord(), -2..=2i32).prop_map(|(x, y, z)| BlockPos::new(x, y, z))
+    }
+
+    /// 0 is empty, 2 and 3 are transparent with transparent_occlusion
+    fn block() -> impl Strategy<Value = BlockMatIdx> {
+        0..=4 as BlockMatIdx
+    }
+
+    fn edit() -> impl Strategy<Value = Edit> {
+        prop_oneof![
+            8 => (block_pos(), block()).prop_map(|(block_pos, block)| Edit::Set(block_pos, block)),
+            1 => (block_pos(), 0..=4i32, 0..=4i32, block()).prop_map(|(min, dx, dy, block)| {
+                Edit::Fill(min, BlockPos::new(min.x + dx, min.y + dy, min.z), block)
+            }),
+            1 => prop::collection::vec((block_pos(), block()), 1..8).prop_map(Edit::Batch),
+        ]
+    }
+
+    fn edits(max_len: usize) -> impl Strategy<Value = Vec<Edit>> {
+        prop::collection::vec(edit(), 1..max_len)
+    }
+
+    fn wait_streaming(res: &mut WorldBlockRes, camera: &CameraRes) {
+        for _ in 0..1000 {
+            res.update_streaming(camera);
+            if res.stream.as_ref().unwrap().pending_count() == 0 {
+                return;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        panic!("streaming is not finished");
+    }
+
+    fn wait_saving(res: &WorldBlockRes) {
+        for _ in 0..1000 {
+            if res.stream.as_ref().unwrap().saving_count() == 0 {
+                return;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        panic!("saving is not finished");
+    }
+
     #[test]
     fn test_create() {
         let mut manager = AssetManager::<MockFileSystem>::new();
@@ -271,6 +1203,16 @@ mod test {
         let _res = WorldBlockRes::new(&path, &mut manager);
     }
 
+    #[test]
+    fn test_blocks_moved_from_asset() {
+        let mut manager = AssetManager::<MockFileSystem>::new();
+        let path: AssetPath = "world_block.wb".into();
+        let res = WorldBlockRes::new(&path, &mut manager);
+
+        assert_eq!(res.chunks.len(), 2);
+        assert!(res.handle.get_asset().world_chunks.is_empty());
+    }
+
     #[test]
     fn test_set_block() {
         let mut manager = AssetManager::<MockFileSystem>::new();
@@ -293,6 +1235,20 @@ mod test {
         assert_eq!(vis.contains(Dir::XPos), false);
     }
 
+    #[test]
+    fn test_move_aabb() {
+        let mut manager = AssetManager::<MockFileSystem>::new();
+        let path: AssetPath = "world_block.wb".into();
+        let mut res = WorldBlockRes::new(&path, &mut manager);
+        res.clear_blocks();
+        res.set_block(BlockPos::new(0, 0, 0), 1);
+
+        let aabb = Aabb::new(Vec3::new(0.2, 3.0, 0.2), Vec3::new(0.8, 4.0, 0.8));
+        let result = res.move_aabb(&aabb, Vec3::new(0.0, -10.0, 0.0));
+        assert_eq!(result.aabb.min.y, 1.0);
+        assert_eq!(result.contacts, Dir::YPos);
+    }
+
     #[test]
     fn test_set_block_from_empty() {
         let mut manager = AssetManager::<MockFileSystem>::new();
@@ -321,4 +1277,521 @@ mod test {
         let vis2 = res.get_block_vis(block_pos_2).unwrap();
         assert_eq!(vis2, Dir::XPos | Dir::XNeg | Dir::YPos | Dir::ZPos | Dir::ZNeg);
     }
+
+    #[test]
+    fn test_unbounded_set_block() {
+        let mut manager = AssetManager::<MockFileSystem>::new();
+        let path: AssetPath = "world_block.wb".into();
+        let mut res = WorldBlockRes::new(&path, &mut manager);
+        res.clear_blocks();
+        res.chunk_counts = None;
+
+        // blocks in different negative chunks
+        let block_pos_1 = BlockPos::new(-1, -100, 0);
+        let block_pos_2 = BlockPos::new(0, -100, 0);
+        res.set_block(block_pos_1, 1);
+        res.set_block(block_pos_2, 1);
+
+        assert_eq!(res.chunks.len(), 2);
+        assert_eq!(res.get_block(block_pos_1), Some(1));
+        assert_eq!(res.get_block(block_pos_2), Some(1));
+
+        let vis1 = res.get_block_vis(block_pos_1).unwrap();
+        assert_eq!(vis1, BitFlags::all() ^ Dir::XPos);
+
+        let vis2 = res.get_block_vis(block_pos_2).unwrap();
+        assert_eq!(vis2, BitFlags::all() ^ Dir::XNeg);
+
+        let ray = Ray::from_values(&Vec3::new(-10.5, -99.5, 0.5), &Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(res.trace(&ray), Some((block_pos_1, Dir::XNeg)));
+    }
+
+    #[test]
+    fn test_streaming() {
+        let mut manager = AssetManager::<MockFileSystem>::new();
+        let path: AssetPath = "world_block.wb".into();
+        let provider = Arc::new(SolidProvider::default());
+        let settings = StreamSettings {
+            load_radius: 1,
+            unload_radius: 1,
+            integrate_budget: 1,
+        };
+        let mut res = WorldBlockRes::new_streaming(&path, &mut manager, provider.clone(), settings);
+
+        // only chunk (0, 0, 0) and its neighbors in world are loaded, one chunk per frame
+        let mut camera = create_camera(Vec3::new(1.0, 1.0, 1.0));
+        while res.chunks.is_empty() {
+            res.update_streaming(&camera);
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(res.chunks.len(), 1);
+
+        wait_streaming(&mut res, &camera);
+        assert_eq!(res.chunks.len(), 4);
+
+        // border between loaded chunks is hidden
+        let side = BLOCK_COUNT_IN_CHUNKSIDE as i32;
+        let vis = res.get_block_vis(BlockPos::new(side - 1, 0, 0)).unwrap();
+        assert_eq!(vis.contains(Dir::XPos), false);
+        let vis = res.get_block_vis(BlockPos::new(side, 0, 0)).unwrap();
+        assert_eq!(vis.contains(Dir::XNeg), false);
+
+        // modified chunk is saved when evicted
+        res.set_block(BlockPos::new(side, 0, 0), 0);
+        camera.move_camera(&Vec3::new(100.0, 0.0, 0.0));
+        wait_streaming(&mut res, &camera);
+        assert!(res.chunks.is_empty());
+
+        for _ in 0..1000 {
+            if !provider.saved.lock().unwrap().is_empty() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(*provider.saved.lock().unwrap(), vec![ChunkPos::new(1, 0, 0)]);
+    }
+
+    #[test]
+    fn test_streaming_edit_pending_chunk() {
+        let mut manager = AssetManager::<MockFileSystem>::new();
+        let path: AssetPath = "world_block.wb".into();
+        let provider = Arc::new(SolidProvider::default());
+        let settings = StreamSettings {
+            load_radius: 1,
+            unload_radius: 1,
+            integrate_budget: 8,
+        };
+        let mut res = WorldBlockRes::new_streaming(&path, &mut manager, provider.clone(), settings);
+
+        // chunks are requested, but not integrated yet
+        let camera = create_camera(Vec3::new(1.0, 1.0, 1.0));
+        res.update_streaming(&camera);
+        assert!(res.stream.as_ref().unwrap().pending_count() > 0);
+
+        res.set_block(BlockPos::new(0, 0, 0), 2);
+        res.fill_region(BlockPos::new(0, 1, 0), BlockPos::new(3, 1, 0), 0);
+        res.apply_edits(vec![(BlockPos::new(0, 2, 0), 0)]);
+        assert!(res.chunks.is_empty());
+
+        // loaded blocks are not overwritten by edits
+        wait_streaming(&mut res, &camera);
+        assert_eq!(res.get_block(BlockPos::new(0, 0, 0)), Some(1));
+        assert_eq!(res.get_block(BlockPos::new(2, 1, 0)), Some(1));
+        assert_eq!(res.get_block(BlockPos::new(0, 2, 0)), Some(1));
+        assert_eq!(res.chunks[&ChunkPos::new(0, 0, 0)].blocks.uniform(), Some(1));
+
+        // resident chunk can be edited
+        res.set_block(BlockPos::new(0, 0, 0), 2);
+        assert_eq!(res.get_block(BlockPos::new(0, 0, 0)), Some(2));
+    }
+
+    #[test]
+    fn test_streaming_flush() {
+        let mut manager = AssetManager::<MockFileSystem>::new();
+        let path: AssetPath = "world_block.wb".into();
+        let provider = Arc::new(SlowSaveProvider::default());
+        let settings = StreamSettings {
+            load_radius: 1,
+            unload_radius: 1,
+            integrate_budget: 8,
+        };
+        let mut res = WorldBlockRes::new_streaming(&path, &mut manager, provider.clone(), settings);
+
+        let camera = create_camera(Vec3::new(1.0, 1.0, 1.0));
+        wait_streaming(&mut res, &camera);
+        res.set_block(BlockPos::new(0, 0, 0), 0);
+
+        // modified chunk is saved without eviction, and saves are landed when flush returns
+        res.flush_streaming();
+        assert_eq!(res.stream.as_ref().unwrap().saving_count(), 0);
+        assert_eq!(res.chunks.len(), 4);
+        {
+            let saved = provider.chunks.lock().unwrap();
+            assert_eq!(saved.keys().copied().collect::<Vec<_>>(), vec![ChunkPos::new(0, 0, 0)]);
+            assert_eq!(
+                saved[&ChunkPos::new(0, 0, 0)][BlockPos::new(0, 0, 0).get_block_index()],
+                0
+            );
+        }
+
+        // chunk is clean after flush, and dirty again when modified
+        provider.chunks.lock().unwrap().clear();
+        res.flush_streaming();
+        assert!(provider.chunks.lock().unwrap().is_empty());
+
+        res.set_block(BlockPos::new(1, 0, 0), 0);
+        res.flush_streaming();
+        assert_eq!(provider.chunks.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_streaming_reload_while_saving() {
+        let mut manager = AssetManager::<MockFileSystem>::new();
+        let path: AssetPath = "world_block.wb".into();
+        let provider = Arc::new(SlowSaveProvider::default());
+        let settings = StreamSettings {
+            load_radius: 1,
+            unload_radius: 1,
+            integrate_budget: 8,
+        };
+        let mut res = WorldBlockRes::new_streaming(&path, &mut manager, provider.clone(), settings);
+
+        let mut camera = create_camera(Vec3::new(1.0, 1.0, 1.0));
+        wait_streaming(&mut res, &camera);
+        res.set_block(BlockPos::new(0, 0, 0), 0);
+
+        // evicted chunk comes back before its save lands
+        camera.move_camera(&Vec3::new(100.0, 0.0, 0.0));
+        wait_streaming(&mut res, &camera);
+        camera.move_camera(&Vec3::new(-100.0, 0.0, 0.0));
+        wait_streaming(&mut res, &camera);
+        assert_eq!(res.get_block(BlockPos::new(0, 0, 0)), Some(0));
+
+        // evicted again while first save is running. latest blocks are saved last
+        res.set_block(BlockPos::new(1, 0, 0), 0);
+        camera.move_camera(&Vec3::new(100.0, 0.0, 0.0));
+        wait_streaming(&mut res, &camera);
+        wait_saving(&res);
+
+        let saved = provider.chunks.lock().unwrap()[&ChunkPos::new(0, 0, 0)].clone();
+        assert_eq!(saved[BlockPos::new(0, 0, 0).get_block_index()], 0);
+        assert_eq!(saved[BlockPos::new(1, 0, 0).get_block_index()], 0);
+        assert_eq!(saved[BlockPos::new(2, 0, 0).get_block_index()], 1);
+    }
+
+    #[test]
+    fn test_border_light() {
+        let mut res = create_unbounded();
+        res.set_block(BlockPos::new(15, 0, 0), 1);
+
+        // roof over chunk (1, 0, 0) darkens border of chunk (0, 0, 0). sky light reaches under it only from side
+        res.fill_region(BlockPos::new(16, 1, 0), BlockPos::new(31, 1, 15), 1);
+        let border_light = |res: &WorldBlockRes| {
+            res.chunks[&ChunkPos::new(0, 0, 0)]
+                .border
+                .get_light(BlockPos::new(16, 0, 0))
+        };
+        assert!(LightChannel::Sky.get(border_light(&res)) < MAX_LIGHT);
+        assert_borders_rebuilt(&res);
+
+        // hole in roof
+        let version = res.chunks[&ChunkPos::new(0, 0, 0)].version;
+        res.set_block(BlockPos::new(16, 1, 0), 0);
+        assert_eq!(LightChannel::Sky.get(border_light(&res)), MAX_LIGHT);
+        assert!(res.chunks[&ChunkPos::new(0, 0, 0)].version > version);
+        assert_borders_rebuilt(&res);
+
+        // missing neighbor is open sky
+        res.clear_blocks();
+        res.set_block(BlockPos::new(15, 0, 0), 1);
+        assert_eq!(LightChannel::Sky.get(border_light(&res)), MAX_LIGHT);
+        assert_borders_rebuilt(&res);
+    }
+
+    #[test]
+    fn test_border_opacity() {
+        let mut res = create_unbounded();
+        res.set_block(BlockPos::new(15, 0, 0), 1);
+        let is_border_opaque = |res: &WorldBlockRes| {
+            res.chunks[&ChunkPos::new(0, 0, 0)]
+                .border
+                .is_opaque(BlockPos::new(16, 1, 0))
+        };
+        assert!(!is_border_opaque(&res));
+
+        // block in neighbor chunk darkens corners of faces at border
+        res.set_block(BlockPos::new(16, 1, 0), 1);
+        assert!(is_border_opaque(&res));
+        assert_borders_rebuilt(&res);
+
+        res.apply_edits(vec![(BlockPos::new(16, 1, 0), 0)]);
+        assert!(!is_border_opaque(&res));
+        assert_borders_rebuilt(&res);
+    }
+
+    #[test]
+    fn test_missing_chunk_under_roof() {
+        let mut res = create_unbounded();
+        let sky = |res: &WorldBlockRes, block_pos| LightChannel::Sky.get(res.get_block_light(block_pos).unwrap());
+
+        // top layer of chunk (0, 0, 0) is empty
+        res.fill_region(BlockPos::new(0, 0, 0), BlockPos::new(15, 14, 15), 1);
+        assert_eq!(sky(&res, BlockPos::new(5, 15, 5)), MAX_LIGHT);
+
+        // roof two chunks above. missing chunk between them is not open to sky
+        res.fill_region(BlockPos::new(0, 32, 0), BlockPos::new(15, 47, 15), 1);
+        assert!(!res.chunks.contains_key(&ChunkPos::new(0, 1, 0)));
+        assert_eq!(sky(&res, BlockPos::new(5, 15, 5)), 0);
+        let border = &res.chunks[&ChunkPos::new(0, 0, 0)].border;
+        assert_eq!(border.get_light(BlockPos::new(5, 16, 5)), 0);
+        assert_light_rebuilt(&mut res);
+        assert_borders_rebuilt(&res);
+
+        // hole in roof
+        res.fill_region(BlockPos::new(5, 32, 5), BlockPos::new(5, 47, 5), 0);
+        assert_eq!(sky(&res, BlockPos::new(5, 15, 5)), MAX_LIGHT);
+        assert_eq!(sky(&res, BlockPos::new(7, 15, 5)), MAX_LIGHT - 2);
+        assert_light_rebuilt(&mut res);
+        assert_borders_rebuilt(&res);
+    }
+
+    #[test]
+    fn test_streaming_evicted_roof() {
+        let mut manager = AssetManager::<MockFileSystem>::new();
+        let path: AssetPath = "world_block.wb".into();
+        let settings = StreamSettings {
+            load_radius: 1,
+            unload_radius: 1,
+            integrate_budget: 8,
+        };
+        let mut res = WorldBlockRes::new_streaming(&path, &mut manager, Arc::new(LayerProvider), settings);
+        res.chunk_counts = None;
+        let sky = |res: &WorldBlockRes, block_pos| LightChannel::Sky.get(res.get_block_light(block_pos).unwrap());
+
+        // chunk (0, 1, 0) between solid chunks is empty
+        let mut camera = create_camera(Vec3::new(1.0, 17.0, 1.0));
+        wait_streaming(&mut res, &camera);
+        assert!(res.chunks.contains_key(&ChunkPos::new(0, 2, 0)));
+        assert!(!res.chunks.contains_key(&ChunkPos::new(0, 1, 0)));
+        assert_eq!(sky(&res, BlockPos::new(5, 15, 5)), 0);
+
+        // solid chunk two chunks above is evicted, but it still covers sky
+        camera.move_camera(&Vec3::new(0.0, -32.0, 0.0));
+        wait_streaming(&mut res, &camera);
+        assert!(!res.chunks.contains_key(&ChunkPos::new(0, 2, 0)));
+        assert!(res.chunks.contains_key(&ChunkPos::new(0, 0, 0)));
+        assert_eq!(sky(&res, BlockPos::new(5, 15, 5)), 0);
+        assert_borders_rebuilt(&res);
+    }
+
+    #[test]
+    fn test_memory_usage() {
+        let mut manager = AssetManager::<MockFileSystem>::new();
+        let path: AssetPath = "world_block.wb".into();
+        let mut res = WorldBlockRes::new(&path, &mut manager);
+        res.clear_blocks();
+        assert_eq!(res.memory_usage(), 0);
+
+        // one bit per block for blocks and vis
+        res.set_block(BlockPos::new(0, 0, 0), 1);
+        assert!(res.memory_usage() < TOTAL_BLOCK_COUNTS_IN_CHUNK / 2);
+    }
+
+    #[test]
+    fn test_fill_region() {
+        let mut res = create_unbounded();
+
+        let modified = res.fill_region(BlockPos::new(0, 0, 0), BlockPos::new(63, 63, 63), 1);
+        assert_eq!(modified.len(), 64);
+        for chunk in res.chunks.values() {
+            assert_eq!(chunk.blocks.uniform(), Some(1));
+        }
+
+        assert_eq!(
+            res.get_block_vis(BlockPos::new(0, 0, 0)),
+            Some(Dir::XNeg | Dir::YNeg | Dir::ZNeg)
+        );
+        assert_eq!(res.get_block_vis(BlockPos::new(15, 0, 0)), Some(Dir::YNeg | Dir::ZNeg));
+        assert_eq!(res.get_block_vis(BlockPos::new(1, 1, 1)), Some(BitFlags::empty()));
+        assert_vis_rebuilt(&res);
+
+        // partial fill across chunk border
+        let modified = res.fill_region(BlockPos::new(10, 10, 10), BlockPos::new(20, 10, 10), 0);
+        assert_eq!(modified.len(), 2);
+        assert_eq!(res.get_block(BlockPos::new(15, 10, 10)), Some(0));
+        assert_vis_rebuilt(&res);
+    }
+
+    #[test]
+    fn test_replace_in_region() {
+        let mut res = create_unbounded();
+        res.fill_region(BlockPos::new(0, 0, 0), BlockPos::new(31, 0, 0), 1);
+        res.apply_edits(vec![(BlockPos::new(5, 0, 0), 2)]);
+
+        let modified = res.replace_in_region(BlockPos::new(0, 0, 0), BlockPos::new(10, 0, 0), 1, 3);
+        assert_eq!(modified.len(), 1);
+        assert_eq!(res.get_block(BlockPos::new(0, 0, 0)), Some(3));
+        assert_eq!(res.get_block(BlockPos::new(5, 0, 0)), Some(2));
+        assert_eq!(res.get_block(BlockPos::new(11, 0, 0)), Some(1));
+
+        // empty blocks are replaced even if chunk not exists
+        let modified = res.replace_in_region(BlockPos::new(-1, 0, 0), BlockPos::new(-1, 0, 0), 0, 4);
+        assert_eq!(modified.len(), 1);
+        assert_eq!(res.get_block(BlockPos::new(-1, 0, 0)), Some(4));
+        assert_vis_rebuilt(&res);
+
+        // nothing to replace
+        let modified = res.replace_in_region(BlockPos::new(100, 0, 0), BlockPos::new(120, 0, 0), 1, 2);
+        assert!(modified.is_empty());
+        assert_eq!(res.get_block(BlockPos::new(100, 0, 0)), None);
+    }
+
+    #[test]
+    fn test_apply_edits() {
+        let mut res = create_unbounded();
+
+        let edits = (0..32).map(|x| (BlockPos::new(x, 0, 0), 1));
+        let modified = res.apply_edits(edits);
+        assert_eq!(modified.len(), 2);
+        assert_eq!(
+            res.get_block_vis(BlockPos::new(15, 0, 0)),
+            Some(BitFlags::all() ^ Dir::XPos ^ Dir::XNeg)
+        );
+        assert_vis_rebuilt(&res);
+
+        // same value is not modification
+        let modified = res.apply_edits(vec![(BlockPos::new(0, 0, 0), 1)]);
+        assert!(modified.is_empty());
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn prop_vis_equals_build_vis(edits in edits(300)) {
+            let mut res = create_unbounded();
+            for edit in &edits {
+                edit.apply(&mut res);
+            }
+            assert_vis_rebuilt(&res);
+            assert_borders_rebuilt(&res);
+        }
+
+        #[test]
+        fn prop_vis_transparent(edits in edits(300)) {
+            let mut res = create_unbounded();
+            res.occlusion = transparent_occlusion();
+            for edit in &edits {
+                edit.apply(&mut res);
+            }
+            assert_vis_rebuilt(&res);
+        }
+
+        /// edits out of world are ignored, and neighbors out of world are visible
+        #[test]
+        fn prop_vis_bounded(edits in edits(300)) {
+            let mut manager = AssetManager::<MockFileSystem>::new();
+            let path: AssetPath = "world_block.wb".into();
+            let mut res = WorldBlockRes::new(&path, &mut manager);
+            res.clear_blocks();
+            for edit in &edits {
+                edit.apply(&mut res);
+            }
+            assert_vis_rebuilt(&res);
+        }
+
+        #[test]
+        fn prop_light_equals_rebuild(edits in edits(200)) {
+            let mut res = create_unbounded();
+            res.occlusion = transparent_occlusion();
+            res.emission.set(3, 12);
+            for edit in &edits {
+                edit.apply(&mut res);
+            }
+            assert_light_rebuilt(&mut res);
+        }
+    }
+
+    #[test]
+    fn test_repeated_placement() {
+        let mut res = create_unbounded();
+        let block_pos = BlockPos::new(0, 0, 0);
+        let neighbor_pos = BlockPos::new(1, 0, 0);
+        res.set_block(neighbor_pos, 1);
+
+        for _ in 0..3 {
+            res.set_block(block_pos, 1);
+            assert_eq!(res.get_block_vis(neighbor_pos), Some(BitFlags::all() ^ Dir::XNeg));
+        }
+
+        res.set_block(block_pos, 0);
+        assert_eq!(res.get_block_vis(neighbor_pos), Some(BitFlags::all()));
+        assert_vis_rebuilt(&res);
+    }
+
+    #[test]
+    fn test_set_block_next_to_missing_chunk() {
+        let mut res = create_unbounded();
+
+        // neighbor chunk (-1, 0, 0) not exists
+        res.set_block(BlockPos::new(0, 0, 0), 1);
+        res.set_block(BlockPos::new(0, 0, 0), 0);
+        assert_eq!(res.chunks.len(), 1);
+        assert_vis_rebuilt(&res);
+    }
+
+    #[test]
+    fn test_transparent_neighbor() {
+        let mut res = create_unbounded();
+        res.occlusion = transparent_occlusion();
+
+        // stone(1), glass(2), glass(2) along x. glass crosses chunk border
+        let stone_pos = BlockPos::new(-2, 0, 0);
+        let glass_pos = BlockPos::new(-1, 0, 0);
+        let glass_pos2 = BlockPos::new(0, 0, 0);
+        res.set_block(stone_pos, 1);
+        res.set_block(glass_pos, 2);
+        res.set_block(glass_pos2, 2);
+
+        // stone face against glass stays visible
+        assert_eq!(res.get_block_vis(stone_pos), Some(BitFlags::all()));
+
+        // faces between same glass are culled, glass face against stone is hidden
+        assert_eq!(
+            res.get_block_vis(glass_pos),
+            Some(BitFlags::all() ^ Dir::XPos ^ Dir::XNeg)
+        );
+        assert_eq!(res.get_block_vis(glass_pos2), Some(BitFlags::all() ^ Dir::XNeg));
+
+        // different transparent block shows face
+        res.set_block(glass_pos2, 3);
+        assert_eq!(res.get_block_vis(glass_pos), Some(BitFlags::all() ^ Dir::XNeg));
+        assert_eq!(res.get_block_vis(glass_pos2), Some(BitFlags::all()));
+        assert_vis_rebuilt(&res);
+    }
+
+    #[test]
+    fn test_cave_is_dark() {
+        let mut res = create_unbounded();
+        let sky = |res: &WorldBlockRes, block_pos| LightChannel::Sky.get(res.get_block_light(block_pos).unwrap());
+
+        // ground with cave under it
+        res.fill_region(BlockPos::new(-8, -20, -8), BlockPos::new(8, -1, 8), 1);
+        res.fill_region(BlockPos::new(-2, -6, -2), BlockPos::new(2, -4, 2), 0);
+        assert_eq!(sky(&res, BlockPos::new(12, -1, 0)), MAX_LIGHT);
+        assert_eq!(sky(&res, BlockPos::new(0, -5, 0)), 0);
+
+        // light from hole in ceiling
+        res.set_block(BlockPos::new(0, -3, 0), 0);
+        res.set_block(BlockPos::new(0, -2, 0), 0);
+        res.set_block(BlockPos::new(0, -1, 0), 0);
+        assert_eq!(sky(&res, BlockPos::new(0, -6, 0)), MAX_LIGHT);
+        assert_eq!(sky(&res, BlockPos::new(2, -6, 0)), MAX_LIGHT - 2);
+
+        // torch in closed cave
+        res.set_block(BlockPos::new(0, -1, 0), 1);
+        res.emission.set(3, 14);
+        res.set_block(BlockPos::new(-2, -5, -2), 3);
+        let block_light = |block_pos| LightChannel::Block.get(res.get_block_light(block_pos).unwrap());
+        assert_eq!(block_light(BlockPos::new(-2, -5, -1)), 13);
+        assert_eq!(block_light(BlockPos::new(2, -5, 2)), 6);
+        assert_eq!(sky(&res, BlockPos::new(0, -6, 0)), 0);
+
+        assert_light_rebuilt(&mut res);
+    }
+
+    #[test]
+    fn test_randomized_light_equals_rebuild() {
+        for seed in 1..=2u64 {
+            let mut edits = RandomEdits::new(seed.wrapping_mul(0xD6E8_FEB8_6659_FD93));
+            let mut res = create_unbounded();
+            res.occlusion = transparent_occlusion();
+            res.emission.set(3, 12);
+
+            for _ in 0..2 {
+                edits.apply(&mut res, 150);
+                assert_light_rebuilt(&mut res);
+            }
+        }
+    }
 }
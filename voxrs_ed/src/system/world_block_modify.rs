@@ -23,11 +23,10 @@ pub(crate) fn modify(
 
     let ray = camera.create_ray(mouse_input.get_position());
     let result = world_block_res.trace(&ray);
-    let chunk_counts = world_block_res.get_world_chunk_counts();
 
     if let Some((block_pos, dir)) = result {
         // add or remove block
-        if block_pos.is_valid(&chunk_counts) {
+        if world_block_res.is_valid_block(block_pos) {
             if key_input.is_shift_pressed() {
                 // delete picked block
                 let del_block = command::ModifyBlock::delete_block(block_pos);
@@ -36,7 +35,7 @@ pub(crate) fn modify(
             } else {
                 // create new block
                 let neighbor_pos = block_pos.get_neighbor(dir);
-                if neighbor_pos.is_valid(&chunk_counts) {
+                if world_block_res.is_valid_block(neighbor_pos) {
                     let mat_id = editor_res.block_mat_id;
                     let create_block = command::ModifyBlock::create_block(neighbor_pos, mat_id);
                     let undo = create_block.exec(world_block_res);
@@ -58,7 +57,6 @@ pub fn indicator_render(
 ) {
     let ray = camera.create_ray(mouse_input.get_position());
     let result = world_block_res.trace(&ray);
-    let chunk_counts = world_block_res.get_world_chunk_counts();
 
     if let Some((block_pos, dir)) = result {
         // show modifiable block or block pos
@@ -68,7 +66,7 @@ pub fn indicator_render(
             blueprint.dynamic_blocks.push(indicator);
         } else {
             let neighbor_pos = block_pos.get_neighbor(dir);
-            if neighbor_pos.is_valid(&chunk_counts) {
+            if world_block_res.is_valid_block(neighbor_pos) {
                 let aabb = neighbor_pos.aabb(world_block_res.block_size.to_f32());
                 let indicator = DynamicBlock::new(aabb, editor_asset.block_indicator_mat.clone());
                 blueprint.dynamic_blocks.push(indicator);
@@ -12,6 +12,9 @@ pub fn render(
 
     bp.set_world_mat(asset.world_material.clone());
     bp.set_block_size(asset.block_size.to_f32());
+    if let Some(fog) = &asset.fog {
+        bp.set_fog(fog.into());
+    }
 
     let culled_chunks = world_block_res.frustum_culling(camera_res);
 
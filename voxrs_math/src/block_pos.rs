@@ -1,6 +1,6 @@
 use crate::{chunk_pos::ChunkPos, Aabb, Dir, Vec3, WorldChunkCounts, BLOCK_COUNT_IN_CHUNKSIDE};
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct BlockPos {
     pub x: i32,
     pub y: i32,
@@ -14,7 +14,11 @@ impl BlockPos {
 
     pub fn from_index(chunk_idx: usize, block_idx: usize, chunk_counts: &WorldChunkCounts) -> Self {
         let chunk_pos = ChunkPos::from_index(chunk_idx, chunk_counts);
+        Self::from_chunk_pos(chunk_pos, block_idx)
+    }
 
+    /// block in chunk (chunk_pos) at local index (block_idx)
+    pub fn from_chunk_pos(chunk_pos: ChunkPos, block_idx: usize) -> Self {
         // get chunk xyz
         let x = chunk_pos.x * BLOCK_COUNT_IN_CHUNKSIDE as i32;
         let y = chunk_pos.y * BLOCK_COUNT_IN_CHUNKSIDE as i32;
@@ -49,10 +53,12 @@ impl BlockPos {
             && self.z < chunk_counts.z * BLOCK_COUNT_IN_CHUNKSIDE as i32
     }
 
+    /// chunk containing this block
+    /// negative coordinates round toward negative infinity (-1 is in chunk -1)
     pub fn get_chunk_pos(&self) -> ChunkPos {
-        let x = self.x / BLOCK_COUNT_IN_CHUNKSIDE as i32;
-        let y = self.y / BLOCK_COUNT_IN_CHUNKSIDE as i32;
-        let z = self.z / BLOCK_COUNT_IN_CHUNKSIDE as i32;
+        let x = self.x.div_euclid(BLOCK_COUNT_IN_CHUNKSIDE as i32);
+        let y = self.y.div_euclid(BLOCK_COUNT_IN_CHUNKSIDE as i32);
+        let z = self.z.div_euclid(BLOCK_COUNT_IN_CHUNKSIDE as i32);
 
         ChunkPos::new(x, y, z)
     }
@@ -68,19 +74,24 @@ impl BlockPos {
         let chunk_pos = self.get_chunk_pos();
         let chunk_idx = chunk_pos.get_index(chunk_counts);
 
+        Some((chunk_idx.unwrap() as usize, self.get_block_index()))
+    }
+
+    /// index of this block in its chunk (works with any chunk coordinate)
+    pub fn get_block_index(&self) -> usize {
         let local_pos = self.get_local_pos();
         let block_idx = local_pos.0
             + local_pos.1 * BLOCK_COUNT_IN_CHUNKSIDE as i32
             + local_pos.2 * (BLOCK_COUNT_IN_CHUNKSIDE * BLOCK_COUNT_IN_CHUNKSIDE) as i32;
 
-        Some((chunk_idx.unwrap() as usize, block_idx as usize))
+        block_idx as usize
     }
 
     fn get_local_pos(&self) -> (i32, i32, i32) {
         (
-            self.x % BLOCK_COUNT_IN_CHUNKSIDE as i32,
-            self.y % BLOCK_COUNT_IN_CHUNKSIDE as i32,
-            self.z % BLOCK_COUNT_IN_CHUNKSIDE as i32,
+            self.x.rem_euclid(BLOCK_COUNT_IN_CHUNKSIDE as i32),
+            self.y.rem_euclid(BLOCK_COUNT_IN_CHUNKSIDE as i32),
+            self.z.rem_euclid(BLOCK_COUNT_IN_CHUNKSIDE as i32),
         )
     }
 
@@ -193,4 +204,35 @@ mod tests {
         let block = BlockPos::from_vec3(&(-0.5, -0.5, -0.5).into(), 1.0);
         assert_eq!(block, BlockPos::new(-1, -1, -1));
     }
+
+    #[test]
+    fn test_negative_chunk_pos() {
+        let side = BLOCK_COUNT_IN_CHUNKSIDE as i32;
+
+        let block = BlockPos::new(-1, 0, 0);
+        assert_eq!(block.get_chunk_pos(), ChunkPos::new(-1, 0, 0));
+        assert_eq!(block.get_block_index(), BLOCK_COUNT_IN_CHUNKSIDE - 1);
+
+        let block = BlockPos::new(-side, -side - 1, 0);
+        assert_eq!(block.get_chunk_pos(), ChunkPos::new(-1, -2, 0));
+        assert_eq!(
+            block.get_block_index(),
+            (BLOCK_COUNT_IN_CHUNKSIDE - 1) * BLOCK_COUNT_IN_CHUNKSIDE
+        );
+    }
+
+    #[test]
+    fn test_from_chunk_pos() {
+        let blocks = [
+            BlockPos::new(0, 0, 0),
+            BlockPos::new(-1, -1, -1),
+            BlockPos::new(-17, 5, 40),
+            BlockPos::new(100, -100, 3),
+        ];
+
+        for block in blocks.iter() {
+            let restored = BlockPos::from_chunk_pos(block.get_chunk_pos(), block.get_block_index());
+            assert_eq!(restored, *block);
+        }
+    }
 }
@@ -1,6 +1,8 @@
-use crate::{Dir, Vec3, WorldChunkCounts, BLOCK_COUNT_IN_CHUNKSIDE};
+use serde::{Deserialize, Serialize};
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+use crate::{Aabb, BlockPos, Dir, Vec3, WorldChunkCounts, BLOCK_COUNT_IN_CHUNKSIDE};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub struct ChunkPos {
     pub x: i32,
     pub y: i32,
@@ -62,6 +64,34 @@ impl ChunkPos {
             (self.z * BLOCK_COUNT_IN_CHUNKSIDE as i32) as f32 * block_size,
         )
     }
+
+    pub fn aabb(&self, block_size: f32) -> Aabb {
+        let min = self.get_world_pos(block_size);
+        let size = block_size * BLOCK_COUNT_IN_CHUNKSIDE as f32;
+        let max = min + Vec3::new(size, size, size);
+
+        Aabb::new(min, max)
+    }
+
+    /// blocks of this chunk touching neighbor chunk at dir
+    pub fn border_blocks(&self, dir: Dir) -> impl Iterator<Item = BlockPos> {
+        let side = BLOCK_COUNT_IN_CHUNKSIDE as i32;
+        let base = BlockPos::new(self.x * side, self.y * side, self.z * side);
+
+        (0..side * side).map(move |i| {
+            let (a, b) = (i % side, i / side);
+            let (x, y, z) = match dir {
+                Dir::XPos => (side - 1, a, b),
+                Dir::XNeg => (0, a, b),
+                Dir::YPos => (a, side - 1, b),
+                Dir::YNeg => (a, 0, b),
+                Dir::ZPos => (a, b, side - 1),
+                Dir::ZNeg => (a, b, 0),
+            };
+
+            BlockPos::new(base.x + x, base.y + y, base.z + z)
+        })
+    }
 }
 
 #[cfg(test)]
@@ -94,4 +124,20 @@ mod tests {
         let chunk_pos = ChunkPos::new(0, 0, 4);
         assert_eq!(chunk_pos.is_valid(&chunk_counts), false);
     }
+
+    #[test]
+    fn test_border_blocks() {
+        let chunk_pos = ChunkPos::new(-1, 2, 0);
+
+        for dir in enumflags2::BitFlags::<Dir>::all().iter() {
+            let neighbor = chunk_pos.get_neighbor(dir);
+            let blocks = chunk_pos.border_blocks(dir).collect::<Vec<_>>();
+            assert_eq!(blocks.len(), BLOCK_COUNT_IN_CHUNKSIDE * BLOCK_COUNT_IN_CHUNKSIDE);
+
+            for block in blocks {
+                assert_eq!(block.get_chunk_pos(), chunk_pos);
+                assert_eq!(block.get_neighbor(dir).get_chunk_pos(), neighbor);
+            }
+        }
+    }
 }
@@ -1,6 +1,34 @@
 use std::borrow::Borrow;
 
-use crate::{get_matrix, Aabb, Mat4, Plane};
+use enumflags2::{bitflags, BitFlags};
+
+use crate::{get_matrix, Aabb, Mat4, Plane, Sphere};
+
+/// planes of frustum
+/// used as BitFlags<FrustumPlane> for plane mask
+#[bitflags]
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FrustumPlane {
+    Near = 0b0000_0001,
+    Far = 0b0000_0010,
+    Left = 0b0000_0100,
+    Right = 0b0000_1000,
+    Top = 0b0001_0000,
+    Bottom = 0b0010_0000,
+}
+
+/// CullResult
+/// Outside: aabb is fully outside of frustum
+/// Intersecting: aabb is partially inside of frustum
+/// Inside: aabb is fully inside of frustum
+#[must_use]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CullResult {
+    Outside,
+    Intersecting,
+    Inside,
+}
 
 #[derive(Debug)]
 pub struct Frustum {
@@ -67,37 +95,68 @@ impl Frustum {
         }
     }
 
+    pub fn plane(&self, plane: FrustumPlane) -> &Plane {
+        match plane {
+            FrustumPlane::Near => &self.near,
+            FrustumPlane::Far => &self.far,
+            FrustumPlane::Left => &self.left,
+            FrustumPlane::Right => &self.right,
+            FrustumPlane::Top => &self.top,
+            FrustumPlane::Bottom => &self.bottom,
+        }
+    }
+
     /// culling frustum and aabb
     /// is aabb is inside or intersect with fructum, return true
     /// else false
     pub fn cull_aabb(&self, aabb: impl Borrow<Aabb>) -> bool {
-        let aabb = Borrow::<Aabb>::borrow(&aabb);
+        self.check_aabb(aabb) != CullResult::Outside
+    }
 
-        if self.near.dist_aabb(aabb) < 0.0 {
-            return false;
-        }
+    /// test aabb against all planes of frustum
+    pub fn check_aabb(&self, aabb: impl Borrow<Aabb>) -> CullResult {
+        self.check_aabb_masked(aabb, BitFlags::all()).0
+    }
 
-        if self.far.dist_aabb(aabb) < 0.0 {
-            return false;
-        }
+    /// test aabb only against planes in mask
+    /// returned mask has planes that children of aabb should test again.
+    /// (planes which aabb is fully inside are removed)
+    /// if mask is empty, parent is fully inside, so aabb is Inside without any test
+    pub fn check_aabb_masked(
+        &self,
+        aabb: impl Borrow<Aabb>,
+        mask: BitFlags<FrustumPlane>,
+    ) -> (CullResult, BitFlags<FrustumPlane>) {
+        let aabb = Borrow::<Aabb>::borrow(&aabb);
 
-        if self.left.dist_aabb(aabb) < 0.0 {
-            return false;
-        }
+        let mut child_mask = mask;
+        for plane in mask.iter() {
+            let dist = self.plane(plane).dist_aabb(aabb);
+            if dist < 0.0 {
+                return (CullResult::Outside, BitFlags::empty());
+            }
 
-        if self.right.dist_aabb(aabb) < 0.0 {
-            return false;
+            if dist > 0.0 {
+                child_mask.remove(plane);
+            }
         }
 
-        if self.top.dist_aabb(aabb) < 0.0 {
-            return false;
+        if child_mask.is_empty() {
+            (CullResult::Inside, child_mask)
+        } else {
+            (CullResult::Intersecting, child_mask)
         }
+    }
+
+    /// test bounding sphere of frustum first (cheap rejection), then planes of frustum
+    pub fn check_sphere_aabb(&self, sphere: &Sphere, aabb: impl Borrow<Aabb>) -> CullResult {
+        let aabb = Borrow::<Aabb>::borrow(&aabb);
 
-        if self.bottom.dist_aabb(aabb) < 0.0 {
-            return false;
+        if !sphere.intersect_aabb(aabb) {
+            return CullResult::Outside;
         }
 
-        true
+        self.check_aabb(aabb)
     }
 }
 
@@ -106,8 +165,7 @@ mod test {
     use super::*;
     use crate::Vec3;
 
-    #[test]
-    fn test_cull_aabb() {
+    fn create_frustum() -> Frustum {
         let eye = Vec3::new(0.0, 0.0, 10.0);
         let target = Vec3::new(0.0, 0.0, 20.0);
         let up = Vec3::Y;
@@ -118,7 +176,12 @@ mod test {
         let view = Mat4::look_at_lh(eye, target, up);
         let proj = Mat4::perspective_lh(fovy, aspect, znear, zfar);
         let vp = proj * view;
-        let frustum = Frustum::new(&vp);
+        Frustum::new(&vp)
+    }
+
+    #[test]
+    fn test_cull_aabb() {
+        let frustum = create_frustum();
 
         let aabb = Aabb::new(Vec3::new(-1.0, -1.0, 20.0), Vec3::new(1.0, 1.0, 30.0));
         assert_eq!(frustum.cull_aabb(&aabb), true);
@@ -126,4 +189,58 @@ mod test {
         let aabb = Aabb::new(Vec3::new(-1.0, -1.0, 120.0), Vec3::new(1.0, 1.0, 130.0));
         assert_eq!(frustum.cull_aabb(&aabb), false);
     }
+
+    #[test]
+    fn test_check_aabb() {
+        let frustum = create_frustum();
+
+        // fully inside
+        let aabb = Aabb::new(Vec3::new(-1.0, -1.0, 20.0), Vec3::new(1.0, 1.0, 30.0));
+        assert_eq!(frustum.check_aabb(aabb), CullResult::Inside);
+
+        // cross far plane
+        let aabb = Aabb::new(Vec3::new(-1.0, -1.0, 100.0), Vec3::new(1.0, 1.0, 120.0));
+        assert_eq!(frustum.check_aabb(aabb), CullResult::Intersecting);
+
+        // behind far plane
+        let aabb = Aabb::new(Vec3::new(-1.0, -1.0, 120.0), Vec3::new(1.0, 1.0, 130.0));
+        assert_eq!(frustum.check_aabb(aabb), CullResult::Outside);
+
+        // behind camera
+        let aabb = Aabb::new(Vec3::new(-1.0, -1.0, -10.0), Vec3::new(1.0, 1.0, 0.0));
+        assert_eq!(frustum.check_aabb(aabb), CullResult::Outside);
+    }
+
+    #[test]
+    fn test_check_aabb_masked() {
+        let frustum = create_frustum();
+
+        // parent cross far plane only
+        let parent = Aabb::new(Vec3::new(-1.0, -1.0, 100.0), Vec3::new(1.0, 1.0, 120.0));
+        let (result, mask) = frustum.check_aabb_masked(parent, BitFlags::all());
+        assert_eq!(result, CullResult::Intersecting);
+        assert_eq!(mask, FrustumPlane::Far);
+
+        // child only test far plane
+        let child = Aabb::new(Vec3::new(-1.0, -1.0, 100.0), Vec3::new(1.0, 1.0, 105.0));
+        let (result, mask) = frustum.check_aabb_masked(child, mask);
+        assert_eq!(result, CullResult::Inside);
+        assert!(mask.is_empty());
+
+        // empty mask means parent is fully inside
+        let (result, _) = frustum.check_aabb_masked(child, BitFlags::empty());
+        assert_eq!(result, CullResult::Inside);
+    }
+
+    #[test]
+    fn test_check_sphere_aabb() {
+        let frustum = create_frustum();
+        let sphere = Sphere::new(Vec3::new(0.0, 0.0, 60.0), 60.0);
+
+        let aabb = Aabb::new(Vec3::new(-1.0, -1.0, 20.0), Vec3::new(1.0, 1.0, 30.0));
+        assert_eq!(frustum.check_sphere_aabb(&sphere, aabb), CullResult::Inside);
+
+        let aabb = Aabb::new(Vec3::new(-1.0, -1.0, 120.0), Vec3::new(1.0, 1.0, 130.0));
+        assert_eq!(frustum.check_sphere_aabb(&sphere, aabb), CullResult::Outside);
+    }
 }
@@ -2,23 +2,33 @@ mod aabb;
 mod angle;
 mod dir;
 mod frustum;
+mod light;
 //mod matrix4;
+mod occlusion;
+mod palette;
 mod plane;
 //mod quat;
 mod ray;
 mod rect;
 mod sphere;
+mod sweep;
 
 pub use aabb::Aabb;
 pub use angle::Angle;
 pub use dir::Dir;
+pub use frustum::CullResult;
 pub use frustum::Frustum;
+pub use frustum::FrustumPlane;
 pub use glam::{IVec2, IVec3, Mat3, Mat4, Quat, Vec2, Vec3, Vec4};
+pub use light::{BlockEmission, LightChannel, MAX_LIGHT};
+pub use occlusion::{BlockOcclusion, Occlusion};
+pub use palette::PaletteArray;
 pub use plane::Plane;
 pub use ray::Ray;
 pub use ray::RayAabbResult;
 pub use rect::Rect2;
 pub use sphere::Sphere;
+pub use sweep::{move_aabb, sweep_aabb, MoveResult, SweepHit};
 
 mod chunk;
 
@@ -36,6 +46,10 @@ pub use block_pos::BlockPos;
 mod matrix4;
 pub use matrix4::{get_matrix, set_matrix};
 
+pub mod noise;
+
+pub mod shape;
+
 #[cfg(test)]
 mod vector3;
 
@@ -0,0 +1,66 @@
+pub const MAX_LIGHT: u8 = 15;
+
+/// light of block is packed in u8
+/// sky light in high 4 bits, block light in low 4 bits
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum LightChannel {
+    Sky,
+    Block,
+}
+
+impl LightChannel {
+    pub fn get(self, light: u8) -> u8 {
+        match self {
+            LightChannel::Sky => light >> 4,
+            LightChannel::Block => light & 0x0f,
+        }
+    }
+
+    /// light with level of this channel changed
+    pub fn set(self, light: u8, level: u8) -> u8 {
+        debug_assert!(level <= MAX_LIGHT);
+        match self {
+            LightChannel::Sky => (light & 0x0f) | (level << 4),
+            LightChannel::Block => (light & 0xf0) | level,
+        }
+    }
+}
+
+/// block light emitted by each block value. 0 by default
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockEmission {
+    table: [u8; 256],
+}
+
+impl BlockEmission {
+    pub fn get(&self, block: u8) -> u8 {
+        self.table[block as usize]
+    }
+
+    pub fn set(&mut self, block: u8, emission: u8) {
+        self.table[block as usize] = emission.min(MAX_LIGHT);
+    }
+}
+
+impl Default for BlockEmission {
+    fn default() -> Self {
+        Self { table: [0; 256] }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_light_channel() {
+        let light = LightChannel::Sky.set(0, 15);
+        let light = LightChannel::Block.set(light, 7);
+        assert_eq!(LightChannel::Sky.get(light), 15);
+        assert_eq!(LightChannel::Block.get(light), 7);
+
+        let light = LightChannel::Sky.set(light, 3);
+        assert_eq!(LightChannel::Sky.get(light), 3);
+        assert_eq!(LightChannel::Block.get(light), 7);
+    }
+}
@@ -0,0 +1,175 @@
+use super::Noise;
+use crate::{Vec2, Vec3};
+
+/// offset applied to each octave so that octaves do not share lattice origin
+const OCTAVE_OFFSET: f32 = 19.19;
+
+/// fractal brownian motion
+/// sum of octaves, each with frequency * lacunarity and amplitude * gain
+/// normalized to [-1, 1]
+#[derive(Clone)]
+pub struct Fbm<N: Noise> {
+    pub noise: N,
+    pub octaves: u32,
+    pub lacunarity: f32,
+    pub gain: f32,
+}
+
+impl<N: Noise> Fbm<N> {
+    /// lacunarity 2, gain 0.5
+    pub fn new(noise: N, octaves: u32) -> Self {
+        Self {
+            noise,
+            octaves,
+            lacunarity: 2.0,
+            gain: 0.5,
+        }
+    }
+}
+
+impl<N: Noise> Noise for Fbm<N> {
+    fn sample2(&self, p: Vec2) -> f32 {
+        let mut sum = 0.0;
+        let mut amp = 1.0;
+        let mut amp_sum = 0.0;
+        let mut freq = 1.0;
+
+        for octave in 0..self.octaves {
+            let offset = octave as f32 * OCTAVE_OFFSET;
+            sum += self.noise.sample2(p * freq + Vec2::new(offset, offset)) * amp;
+            amp_sum += amp;
+            amp *= self.gain;
+            freq *= self.lacunarity;
+        }
+
+        if amp_sum > 0.0 {
+            sum / amp_sum
+        } else {
+            0.0
+        }
+    }
+
+    fn sample3(&self, p: Vec3) -> f32 {
+        let mut sum = 0.0;
+        let mut amp = 1.0;
+        let mut amp_sum = 0.0;
+        let mut freq = 1.0;
+
+        for octave in 0..self.octaves {
+            let offset = octave as f32 * OCTAVE_OFFSET;
+            sum += self.noise.sample3(p * freq + Vec3::new(offset, offset, offset)) * amp;
+            amp_sum += amp;
+            amp *= self.gain;
+            freq *= self.lacunarity;
+        }
+
+        if amp_sum > 0.0 {
+            sum / amp_sum
+        } else {
+            0.0
+        }
+    }
+}
+
+/// ridged multifractal
+/// uses (1 - |noise|)^2 for each octave, so there are sharp ridges at zero crossing
+/// each octave is weighted by previous octave
+/// normalized to [-1, 1]
+#[derive(Clone)]
+pub struct Ridged<N: Noise> {
+    pub noise: N,
+    pub octaves: u32,
+    pub lacunarity: f32,
+    pub gain: f32,
+}
+
+impl<N: Noise> Ridged<N> {
+    /// lacunarity 2, gain 0.5
+    pub fn new(noise: N, octaves: u32) -> Self {
+        Self {
+            noise,
+            octaves,
+            lacunarity: 2.0,
+            gain: 0.5,
+        }
+    }
+
+    fn accumulate(&self, mut sample: impl FnMut(u32, f32) -> f32) -> f32 {
+        let mut sum = 0.0;
+        let mut amp = 1.0;
+        let mut amp_sum = 0.0;
+        let mut freq = 1.0;
+        let mut weight = 1.0;
+
+        for octave in 0..self.octaves {
+            let signal = 1.0 - sample(octave, freq).abs();
+            let signal = signal * signal * weight;
+            weight = (signal * 2.0).clamp(0.0, 1.0);
+
+            sum += signal * amp;
+            amp_sum += amp;
+            amp *= self.gain;
+            freq *= self.lacunarity;
+        }
+
+        if amp_sum > 0.0 {
+            sum / amp_sum * 2.0 - 1.0
+        } else {
+            0.0
+        }
+    }
+}
+
+impl<N: Noise> Noise for Ridged<N> {
+    fn sample2(&self, p: Vec2) -> f32 {
+        self.accumulate(|octave, freq| {
+            let offset = octave as f32 * OCTAVE_OFFSET;
+            self.noise.sample2(p * freq + Vec2::new(offset, offset))
+        })
+    }
+
+    fn sample3(&self, p: Vec3) -> f32 {
+        self.accumulate(|octave, freq| {
+            let offset = octave as f32 * OCTAVE_OFFSET;
+            self.noise.sample3(p * freq + Vec3::new(offset, offset, offset))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::noise::{
+        tests::{check_continuous, check_noise},
+        Perlin, Simplex,
+    };
+
+    #[test]
+    fn test_fbm() {
+        check_noise(&Fbm::new(Perlin::new(1), 5), &Fbm::new(Perlin::new(1), 5));
+        check_continuous(&Fbm::new(Perlin::new(1), 3));
+    }
+
+    #[test]
+    fn test_fbm_one_octave_is_base_noise() {
+        let base = Simplex::new(4);
+        let fbm = Fbm::new(Simplex::new(4), 1);
+        let p = Vec3::new(0.3, 12.4, -3.3);
+        assert_eq!(base.sample3(p), fbm.sample3(p));
+    }
+
+    #[test]
+    fn test_ridged() {
+        check_noise(&Ridged::new(Simplex::new(1), 4), &Ridged::new(Simplex::new(1), 4));
+        check_continuous(&Ridged::new(Perlin::new(1), 3));
+    }
+
+    #[test]
+    fn test_zero_octave() {
+        let fbm = Fbm::new(Perlin::new(1), 0);
+        assert_eq!(fbm.sample2(Vec2::new(0.5, 0.5)), 0.0);
+
+        let ridged = Ridged::new(Perlin::new(1), 0);
+        assert_eq!(ridged.sample2(Vec2::new(0.5, 0.5)), 0.0);
+    }
+}
@@ -0,0 +1,129 @@
+mod fractal;
+mod perlin;
+mod permutation;
+mod simplex;
+mod value;
+mod warp;
+
+pub use fractal::{Fbm, Ridged};
+pub use perlin::Perlin;
+pub use simplex::Simplex;
+pub use value::ValueNoise;
+pub use warp::DomainWarp;
+
+use crate::{Vec2, Vec3};
+
+/// coherent noise in 2d and 3d
+/// returned value is in [-1, 1]
+/// every noise is seeded and uses only integer hashing and basic f32 arithmetic,
+/// so same seed produces same value on every platform
+pub trait Noise {
+    fn sample2(&self, p: Vec2) -> f32;
+    fn sample3(&self, p: Vec3) -> f32;
+}
+
+impl<N: Noise + ?Sized> Noise for &N {
+    fn sample2(&self, p: Vec2) -> f32 {
+        (**self).sample2(p)
+    }
+
+    fn sample3(&self, p: Vec3) -> f32 {
+        (**self).sample3(p)
+    }
+}
+
+/// smoothstep of quintic (6t^5 - 15t^4 + 10t^3)
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+/// dot product with one of 8 gradients
+fn grad2(hash: u8, x: f32, y: f32) -> f32 {
+    match hash & 7 {
+        0 => x + y,
+        1 => -x + y,
+        2 => x - y,
+        3 => -x - y,
+        4 => x,
+        5 => -x,
+        6 => y,
+        _ => -y,
+    }
+}
+
+/// dot product with one of 12 gradients (edges of cube)
+fn grad3(hash: u8, x: f32, y: f32, z: f32) -> f32 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 {
+        y
+    } else if h == 12 || h == 14 {
+        x
+    } else {
+        z
+    };
+
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// sample noise at fractional grid points and check range and determinism
+    pub(crate) fn check_noise<N: Noise>(noise: &N, same_seed: &N) {
+        for i in 0..500 {
+            let x = i as f32 * 0.173 - 40.0;
+            let y = i as f32 * 0.311 + 7.0;
+            let z = i as f32 * -0.057 + 3.0;
+
+            let v2 = noise.sample2(Vec2::new(x, y));
+            let v3 = noise.sample3(Vec3::new(x, y, z));
+            assert!((-1.0..=1.0).contains(&v2), "2d out of range: {}", v2);
+            assert!((-1.0..=1.0).contains(&v3), "3d out of range: {}", v3);
+
+            assert_eq!(v2.to_bits(), same_seed.sample2(Vec2::new(x, y)).to_bits());
+            assert_eq!(v3.to_bits(), same_seed.sample3(Vec3::new(x, y, z)).to_bits());
+        }
+    }
+
+    /// noise should be continuous
+    pub(crate) fn check_continuous<N: Noise>(noise: &N) {
+        for i in 0..200 {
+            let p = Vec3::new(i as f32 * 0.37, i as f32 * 0.11 - 5.0, i as f32 * 0.23);
+            let d = Vec3::new(0.001, 0.001, 0.001);
+            assert!((noise.sample3(p) - noise.sample3(p + d)).abs() < 0.05);
+
+            let p = Vec2::new(p.x, p.y);
+            let d = Vec2::new(0.001, 0.001);
+            assert!((noise.sample2(p) - noise.sample2(p + d)).abs() < 0.05);
+        }
+    }
+
+    /// values are fixed, so generated worlds are same between runs and machines
+    #[test]
+    fn test_reproducible() {
+        use approx::assert_abs_diff_eq;
+
+        let p2 = Vec2::new(1.25, -2.5);
+        let p3 = Vec3::new(1.25, -2.5, 3.75);
+
+        assert_abs_diff_eq!(Perlin::new(1).sample2(p2), 0.150_878_9, epsilon = 1e-6);
+        assert_abs_diff_eq!(Perlin::new(1).sample3(p3), 0.181_284_43, epsilon = 1e-6);
+        assert_abs_diff_eq!(Simplex::new(1).sample2(p2), 0.257_237_82, epsilon = 1e-6);
+        assert_abs_diff_eq!(Simplex::new(1).sample3(p3), 0.495_049_33, epsilon = 1e-6);
+        assert_abs_diff_eq!(ValueNoise::new(1).sample2(p2), -0.338_871, epsilon = 1e-6);
+        assert_abs_diff_eq!(ValueNoise::new(1).sample3(p3), 0.010_525_807, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn test_fade() {
+        assert_eq!(fade(0.0), 0.0);
+        assert_eq!(fade(1.0), 1.0);
+        assert_eq!(fade(0.5), 0.5);
+    }
+}
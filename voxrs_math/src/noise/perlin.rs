@@ -0,0 +1,100 @@
+use super::{fade, grad2, grad3, lerp, permutation::Permutation, Noise};
+use crate::{Vec2, Vec3};
+
+/// improved perlin noise
+/// reference: <https://mrl.cs.nyu.edu/~perlin/noise/>
+#[derive(Clone)]
+pub struct Perlin {
+    perm: Permutation,
+}
+
+impl Perlin {
+    pub fn new(seed: u32) -> Self {
+        Self {
+            perm: Permutation::new(seed),
+        }
+    }
+}
+
+impl Noise for Perlin {
+    fn sample2(&self, p: Vec2) -> f32 {
+        let x0 = p.x.floor();
+        let y0 = p.y.floor();
+        let (xi, yi) = (x0 as i32, y0 as i32);
+        let (x, y) = (p.x - x0, p.y - y0);
+
+        let u = fade(x);
+        let v = fade(y);
+
+        let n00 = grad2(self.perm.hash2(xi, yi), x, y);
+        let n10 = grad2(self.perm.hash2(xi + 1, yi), x - 1.0, y);
+        let n01 = grad2(self.perm.hash2(xi, yi + 1), x, y - 1.0);
+        let n11 = grad2(self.perm.hash2(xi + 1, yi + 1), x - 1.0, y - 1.0);
+
+        let n = lerp(lerp(n00, n10, u), lerp(n01, n11, u), v);
+        n.clamp(-1.0, 1.0)
+    }
+
+    fn sample3(&self, p: Vec3) -> f32 {
+        let x0 = p.x.floor();
+        let y0 = p.y.floor();
+        let z0 = p.z.floor();
+        let (xi, yi, zi) = (x0 as i32, y0 as i32, z0 as i32);
+        let (x, y, z) = (p.x - x0, p.y - y0, p.z - z0);
+
+        let u = fade(x);
+        let v = fade(y);
+        let w = fade(z);
+
+        let h = |dx: i32, dy: i32, dz: i32| self.perm.hash3(xi + dx, yi + dy, zi + dz);
+
+        let n000 = grad3(h(0, 0, 0), x, y, z);
+        let n100 = grad3(h(1, 0, 0), x - 1.0, y, z);
+        let n010 = grad3(h(0, 1, 0), x, y - 1.0, z);
+        let n110 = grad3(h(1, 1, 0), x - 1.0, y - 1.0, z);
+        let n001 = grad3(h(0, 0, 1), x, y, z - 1.0);
+        let n101 = grad3(h(1, 0, 1), x - 1.0, y, z - 1.0);
+        let n011 = grad3(h(0, 1, 1), x, y - 1.0, z - 1.0);
+        let n111 = grad3(h(1, 1, 1), x - 1.0, y - 1.0, z - 1.0);
+
+        let n = lerp(
+            lerp(lerp(n000, n100, u), lerp(n010, n110, u), v),
+            lerp(lerp(n001, n101, u), lerp(n011, n111, u), v),
+            w,
+        );
+        n.clamp(-1.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::noise::tests::{check_continuous, check_noise};
+
+    #[test]
+    fn test_range_and_determinism() {
+        check_noise(&Perlin::new(42), &Perlin::new(42));
+    }
+
+    #[test]
+    fn test_continuous() {
+        check_continuous(&Perlin::new(7));
+    }
+
+    #[test]
+    fn test_zero_at_lattice() {
+        let noise = Perlin::new(3);
+        for i in -5..5 {
+            assert_eq!(noise.sample2(Vec2::new(i as f32, (i * 3) as f32)), 0.0);
+            assert_eq!(noise.sample3(Vec3::new(i as f32, (i * 2) as f32, -i as f32)), 0.0);
+        }
+    }
+
+    #[test]
+    fn test_seed_changes_noise() {
+        let a = Perlin::new(1);
+        let b = Perlin::new(2);
+        let p = Vec3::new(1.3, 2.7, 0.4);
+        assert_ne!(a.sample3(p), b.sample3(p));
+    }
+}
@@ -0,0 +1,73 @@
+/// seeded permutation table for hashing lattice points
+/// 256 entries are doubled to avoid wrapping index
+#[derive(Clone)]
+pub(crate) struct Permutation {
+    table: [u8; 512],
+}
+
+impl Permutation {
+    pub fn new(seed: u32) -> Self {
+        let mut perm = [0_u8; 256];
+        for (i, p) in perm.iter_mut().enumerate() {
+            *p = i as u8;
+        }
+
+        // fisher-yates shuffle with splitmix64
+        let mut state = seed as u64;
+        for i in (1..256).rev() {
+            let j = (splitmix64(&mut state) % (i as u64 + 1)) as usize;
+            perm.swap(i, j);
+        }
+
+        let mut table = [0_u8; 512];
+        for (i, t) in table.iter_mut().enumerate() {
+            *t = perm[i & 255];
+        }
+
+        Self { table }
+    }
+
+    pub fn hash2(&self, x: i32, y: i32) -> u8 {
+        let x = (x & 255) as usize;
+        let y = (y & 255) as usize;
+        self.table[self.table[x] as usize + y]
+    }
+
+    pub fn hash3(&self, x: i32, y: i32, z: i32) -> u8 {
+        let x = (x & 255) as usize;
+        let y = (y & 255) as usize;
+        let z = (z & 255) as usize;
+        self.table[self.table[self.table[x] as usize + y] as usize + z]
+    }
+}
+
+/// reference: <https://prng.di.unimi.it/splitmix64.c>
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_permutation() {
+        let perm = Permutation::new(1234);
+        let mut found = [false; 256];
+        for &v in &perm.table[..256] {
+            found[v as usize] = true;
+        }
+        assert!(found.iter().all(|f| *f));
+        assert_eq!(perm.table[..256], perm.table[256..]);
+    }
+
+    #[test]
+    fn test_seed() {
+        assert_eq!(Permutation::new(1).table[..], Permutation::new(1).table[..]);
+        assert_ne!(Permutation::new(1).table[..], Permutation::new(2).table[..]);
+    }
+}
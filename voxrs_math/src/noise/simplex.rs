@@ -0,0 +1,151 @@
+use super::{grad2, grad3, permutation::Permutation, Noise};
+use crate::{Vec2, Vec3};
+
+/// skew factor for 2d, 0.5 * (sqrt(3) - 1)
+const F2: f32 = 0.366_025_42;
+/// unskew factor for 2d, (3 - sqrt(3)) / 6
+const G2: f32 = 0.211_324_87;
+/// skew factor for 3d
+const F3: f32 = 1.0 / 3.0;
+/// unskew factor for 3d
+const G3: f32 = 1.0 / 6.0;
+
+/// simplex noise
+/// reference: <https://weber.itn.liu.se/~stegu/simplexnoise/simplexnoise.pdf>
+#[derive(Clone)]
+pub struct Simplex {
+    perm: Permutation,
+}
+
+impl Simplex {
+    pub fn new(seed: u32) -> Self {
+        Self {
+            perm: Permutation::new(seed),
+        }
+    }
+}
+
+impl Noise for Simplex {
+    fn sample2(&self, p: Vec2) -> f32 {
+        // skew input space to find simplex cell
+        let s = (p.x + p.y) * F2;
+        let i = (p.x + s).floor();
+        let j = (p.y + s).floor();
+        let t = (i + j) * G2;
+
+        // distance from cell origin
+        let x0 = p.x - (i - t);
+        let y0 = p.y - (j - t);
+
+        // which triangle (lower or upper)
+        let (i1, j1) = if x0 > y0 { (1, 0) } else { (0, 1) };
+
+        let x1 = x0 - i1 as f32 + G2;
+        let y1 = y0 - j1 as f32 + G2;
+        let x2 = x0 - 1.0 + 2.0 * G2;
+        let y2 = y0 - 1.0 + 2.0 * G2;
+
+        let (ii, jj) = (i as i32, j as i32);
+
+        let corner = |hash: u8, x: f32, y: f32| {
+            let t = 0.5 - x * x - y * y;
+            if t < 0.0 {
+                0.0
+            } else {
+                let t2 = t * t;
+                t2 * t2 * grad2(hash, x, y)
+            }
+        };
+
+        let n0 = corner(self.perm.hash2(ii, jj), x0, y0);
+        let n1 = corner(self.perm.hash2(ii + i1, jj + j1), x1, y1);
+        let n2 = corner(self.perm.hash2(ii + 1, jj + 1), x2, y2);
+
+        (70.0 * (n0 + n1 + n2)).clamp(-1.0, 1.0)
+    }
+
+    fn sample3(&self, p: Vec3) -> f32 {
+        // skew input space to find simplex cell
+        let s = (p.x + p.y + p.z) * F3;
+        let i = (p.x + s).floor();
+        let j = (p.y + s).floor();
+        let k = (p.z + s).floor();
+        let t = (i + j + k) * G3;
+
+        // distance from cell origin
+        let x0 = p.x - (i - t);
+        let y0 = p.y - (j - t);
+        let z0 = p.z - (k - t);
+
+        // which of six tetrahedrons
+        let ((i1, j1, k1), (i2, j2, k2)) = if x0 >= y0 {
+            if y0 >= z0 {
+                ((1, 0, 0), (1, 1, 0))
+            } else if x0 >= z0 {
+                ((1, 0, 0), (1, 0, 1))
+            } else {
+                ((0, 0, 1), (1, 0, 1))
+            }
+        } else if y0 < z0 {
+            ((0, 0, 1), (0, 1, 1))
+        } else if x0 < z0 {
+            ((0, 1, 0), (0, 1, 1))
+        } else {
+            ((0, 1, 0), (1, 1, 0))
+        };
+
+        let x1 = x0 - i1 as f32 + G3;
+        let y1 = y0 - j1 as f32 + G3;
+        let z1 = z0 - k1 as f32 + G3;
+        let x2 = x0 - i2 as f32 + 2.0 * G3;
+        let y2 = y0 - j2 as f32 + 2.0 * G3;
+        let z2 = z0 - k2 as f32 + 2.0 * G3;
+        let x3 = x0 - 1.0 + 3.0 * G3;
+        let y3 = y0 - 1.0 + 3.0 * G3;
+        let z3 = z0 - 1.0 + 3.0 * G3;
+
+        let (ii, jj, kk) = (i as i32, j as i32, k as i32);
+
+        let corner = |hash: u8, x: f32, y: f32, z: f32| {
+            let t = 0.6 - x * x - y * y - z * z;
+            if t < 0.0 {
+                0.0
+            } else {
+                let t2 = t * t;
+                t2 * t2 * grad3(hash, x, y, z)
+            }
+        };
+
+        let n0 = corner(self.perm.hash3(ii, jj, kk), x0, y0, z0);
+        let n1 = corner(self.perm.hash3(ii + i1, jj + j1, kk + k1), x1, y1, z1);
+        let n2 = corner(self.perm.hash3(ii + i2, jj + j2, kk + k2), x2, y2, z2);
+        let n3 = corner(self.perm.hash3(ii + 1, jj + 1, kk + 1), x3, y3, z3);
+
+        (32.0 * (n0 + n1 + n2 + n3)).clamp(-1.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::noise::tests::{check_continuous, check_noise};
+
+    #[test]
+    fn test_range_and_determinism() {
+        check_noise(&Simplex::new(42), &Simplex::new(42));
+    }
+
+    #[test]
+    fn test_continuous() {
+        check_continuous(&Simplex::new(7));
+    }
+
+    #[test]
+    fn test_not_flat() {
+        let noise = Simplex::new(9);
+        let sum: f32 = (0..100)
+            .map(|i| noise.sample2(Vec2::new(i as f32 * 0.7, i as f32 * 0.3)).abs())
+            .sum();
+        assert!(sum > 1.0);
+    }
+}
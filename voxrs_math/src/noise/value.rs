@@ -0,0 +1,79 @@
+use super::{fade, lerp, permutation::Permutation, Noise};
+use crate::{Vec2, Vec3};
+
+/// value noise
+/// random value at each lattice point, interpolated smoothly
+#[derive(Clone)]
+pub struct ValueNoise {
+    perm: Permutation,
+}
+
+impl ValueNoise {
+    pub fn new(seed: u32) -> Self {
+        Self {
+            perm: Permutation::new(seed),
+        }
+    }
+}
+
+/// map hash (0 ~ 255) to [-1, 1]
+fn hash_to_value(hash: u8) -> f32 {
+    hash as f32 / 127.5 - 1.0
+}
+
+impl Noise for ValueNoise {
+    fn sample2(&self, p: Vec2) -> f32 {
+        let x0 = p.x.floor();
+        let y0 = p.y.floor();
+        let (xi, yi) = (x0 as i32, y0 as i32);
+
+        let u = fade(p.x - x0);
+        let v = fade(p.y - y0);
+
+        let h = |dx: i32, dy: i32| hash_to_value(self.perm.hash2(xi + dx, yi + dy));
+
+        lerp(lerp(h(0, 0), h(1, 0), u), lerp(h(0, 1), h(1, 1), u), v)
+    }
+
+    fn sample3(&self, p: Vec3) -> f32 {
+        let x0 = p.x.floor();
+        let y0 = p.y.floor();
+        let z0 = p.z.floor();
+        let (xi, yi, zi) = (x0 as i32, y0 as i32, z0 as i32);
+
+        let u = fade(p.x - x0);
+        let v = fade(p.y - y0);
+        let w = fade(p.z - z0);
+
+        let h = |dx: i32, dy: i32, dz: i32| hash_to_value(self.perm.hash3(xi + dx, yi + dy, zi + dz));
+
+        lerp(
+            lerp(lerp(h(0, 0, 0), h(1, 0, 0), u), lerp(h(0, 1, 0), h(1, 1, 0), u), v),
+            lerp(lerp(h(0, 0, 1), h(1, 0, 1), u), lerp(h(0, 1, 1), h(1, 1, 1), u), v),
+            w,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::noise::tests::{check_continuous, check_noise};
+
+    #[test]
+    fn test_range_and_determinism() {
+        check_noise(&ValueNoise::new(42), &ValueNoise::new(42));
+    }
+
+    #[test]
+    fn test_continuous() {
+        check_continuous(&ValueNoise::new(7));
+    }
+
+    #[test]
+    fn test_lattice_value() {
+        let noise = ValueNoise::new(5);
+        let v = noise.sample3(Vec3::new(3.0, 4.0, 5.0));
+        assert_eq!(v, hash_to_value(noise.perm.hash3(3, 4, 5)));
+    }
+}
@@ -0,0 +1,69 @@
+use super::Noise;
+use crate::{Vec2, Vec3};
+
+/// offsets for sampling independent warp value per axis
+const WARP_OFFSET_Y: f32 = 31.7;
+const WARP_OFFSET_Z: f32 = 57.3;
+
+/// domain warping
+/// sample position is displaced by warp noise before sampling noise
+/// reference: <https://iquilezles.org/articles/warp/>
+#[derive(Clone)]
+pub struct DomainWarp<N: Noise, W: Noise> {
+    pub noise: N,
+    pub warp: W,
+    pub amplitude: f32,
+}
+
+impl<N: Noise, W: Noise> DomainWarp<N, W> {
+    pub fn new(noise: N, warp: W, amplitude: f32) -> Self {
+        Self { noise, warp, amplitude }
+    }
+
+    pub fn warp2(&self, p: Vec2) -> Vec2 {
+        let dx = self.warp.sample2(p);
+        let dy = self.warp.sample2(p + Vec2::new(WARP_OFFSET_Y, WARP_OFFSET_Y));
+        p + Vec2::new(dx, dy) * self.amplitude
+    }
+
+    pub fn warp3(&self, p: Vec3) -> Vec3 {
+        let dx = self.warp.sample3(p);
+        let dy = self
+            .warp
+            .sample3(p + Vec3::new(WARP_OFFSET_Y, WARP_OFFSET_Y, WARP_OFFSET_Y));
+        let dz = self
+            .warp
+            .sample3(p + Vec3::new(WARP_OFFSET_Z, WARP_OFFSET_Z, WARP_OFFSET_Z));
+        p + Vec3::new(dx, dy, dz) * self.amplitude
+    }
+}
+
+impl<N: Noise, W: Noise> Noise for DomainWarp<N, W> {
+    fn sample2(&self, p: Vec2) -> f32 {
+        self.noise.sample2(self.warp2(p))
+    }
+
+    fn sample3(&self, p: Vec3) -> f32 {
+        self.noise.sample3(self.warp3(p))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::noise::{tests::check_noise, Fbm, Perlin, Simplex};
+
+    #[test]
+    fn test_domain_warp() {
+        let create = || DomainWarp::new(Perlin::new(1), Fbm::new(Simplex::new(2), 3), 4.0);
+        check_noise(&create(), &create());
+    }
+
+    #[test]
+    fn test_zero_amplitude() {
+        let warp = DomainWarp::new(Perlin::new(1), Simplex::new(2), 0.0);
+        let base = Perlin::new(1);
+        let p = Vec3::new(1.5, 2.25, 3.75);
+        assert_eq!(warp.sample3(p), base.sample3(p));
+    }
+}
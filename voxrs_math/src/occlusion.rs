@@ -0,0 +1,71 @@
+/// how block hides faces of its neighbors
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Occlusion {
+    None,        // empty block. every neighbor face is visible
+    Opaque,      // hides neighbor faces
+    Transparent, // glass, leaves, water... hides faces of same block only
+}
+
+/// occlusion of each block value
+/// 0 is empty, others are opaque by default
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockOcclusion {
+    table: [Occlusion; 256],
+}
+
+impl BlockOcclusion {
+    pub fn get(&self, block: u8) -> Occlusion {
+        self.table[block as usize]
+    }
+
+    pub fn set(&mut self, block: u8, occlusion: Occlusion) {
+        self.table[block as usize] = occlusion;
+    }
+
+    pub fn is_transparent(&self, block: u8) -> bool {
+        self.get(block) == Occlusion::Transparent
+    }
+
+    /// face of block toward neighbor is visible
+    /// faces against transparent neighbor are visible, but culled between same transparent blocks
+    pub fn is_face_visible(&self, block: u8, neighbor: u8) -> bool {
+        match self.get(neighbor) {
+            Occlusion::None => true,
+            Occlusion::Opaque => false,
+            Occlusion::Transparent => block != neighbor,
+        }
+    }
+}
+
+impl Default for BlockOcclusion {
+    fn default() -> Self {
+        let mut table = [Occlusion::Opaque; 256];
+        table[0] = Occlusion::None;
+        Self { table }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_face_visible() {
+        let mut occlusion = BlockOcclusion::default();
+        occlusion.set(3, Occlusion::Transparent);
+        occlusion.set(4, Occlusion::Transparent);
+
+        // stone next to empty, stone, glass
+        assert!(occlusion.is_face_visible(1, 0));
+        assert!(!occlusion.is_face_visible(1, 2));
+        assert!(occlusion.is_face_visible(1, 3));
+
+        // glass next to stone, same glass, other glass
+        assert!(!occlusion.is_face_visible(3, 1));
+        assert!(!occlusion.is_face_visible(3, 3));
+        assert!(occlusion.is_face_visible(3, 4));
+
+        assert!(occlusion.is_transparent(3));
+        assert!(!occlusion.is_transparent(1));
+    }
+}
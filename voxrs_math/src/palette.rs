@@ -0,0 +1,312 @@
+use std::mem::size_of;
+
+/// fixed length array compressed with palette
+/// all same values are stored in O(1) (uniform)
+/// else values are stored as bit-packed indices into palette
+#[derive(Clone, Debug)]
+pub struct PaletteArray<T> {
+    len: usize,
+    repr: Repr<T>,
+}
+
+#[derive(Clone, Debug)]
+enum Repr<T> {
+    Uniform(T),
+    Packed {
+        palette: Vec<T>,
+        counts: Vec<u32>, // reference count of each palette entry. 0 if entry is free
+        bits: u32,        // 1, 2, 4, 8, 16, 32 (entries never cross u64 word)
+        words: Vec<u64>,
+    },
+}
+
+impl<T: Copy + PartialEq> PaletteArray<T> {
+    /// array filled with value
+    pub fn new(len: usize, value: T) -> Self {
+        Self {
+            len,
+            repr: Repr::Uniform(value),
+        }
+    }
+
+    pub fn from_slice(values: &[T]) -> Self {
+        let mut palette = Vec::new();
+        let mut counts = Vec::new();
+        let mut indices = Vec::with_capacity(values.len());
+
+        for value in values {
+            let idx = match palette.iter().position(|v| v == value) {
+                Some(idx) => idx,
+                None => {
+                    palette.push(*value);
+                    counts.push(0);
+                    palette.len() - 1
+                }
+            };
+
+            counts[idx] += 1;
+            indices.push(idx as u32);
+        }
+
+        if palette.len() <= 1 {
+            return match palette.first() {
+                Some(value) => Self::new(values.len(), *value),
+                None => Self {
+                    len: 0,
+                    repr: Repr::Packed {
+                        palette,
+                        counts,
+                        bits: 1,
+                        words: Vec::new(),
+                    },
+                },
+            };
+        }
+
+        let bits = bits_for(palette.len());
+        let mut words = vec![0; word_count(values.len(), bits)];
+        for (i, idx) in indices.into_iter().enumerate() {
+            write_index(&mut words, bits, i, idx);
+        }
+
+        Self {
+            len: values.len(),
+            repr: Repr::Packed {
+                palette,
+                counts,
+                bits,
+                words,
+            },
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Some(value) if all values are same
+    pub fn uniform(&self) -> Option<T> {
+        match &self.repr {
+            Repr::Uniform(value) => Some(*value),
+            Repr::Packed { .. } => None,
+        }
+    }
+
+    pub fn get(&self, idx: usize) -> T {
+        assert!(idx < self.len, "index out of range: {} >= {}", idx, self.len);
+
+        match &self.repr {
+            Repr::Uniform(value) => *value,
+            Repr::Packed {
+                palette, bits, words, ..
+            } => palette[read_index(words, *bits, idx) as usize],
+        }
+    }
+
+    pub fn set(&mut self, idx: usize, value: T) {
+        assert!(idx < self.len, "index out of range: {} >= {}", idx, self.len);
+
+        if let Repr::Uniform(uniform) = self.repr {
+            if uniform == value {
+                return;
+            }
+
+            // every index is 0 (uniform value)
+            self.repr = Repr::Packed {
+                palette: vec![uniform],
+                counts: vec![self.len as u32],
+                bits: 1,
+                words: vec![0; word_count(self.len, 1)],
+            };
+        }
+
+        let len = self.len;
+        if let Repr::Packed {
+            palette,
+            counts,
+            bits,
+            words,
+        } = &mut self.repr
+        {
+            let old_idx = read_index(words, *bits, idx) as usize;
+            if palette[old_idx] == value {
+                return;
+            }
+
+            // find palette entry, reuse free entry or add new one
+            let new_idx = match palette
+                .iter()
+                .zip(counts.iter())
+                .position(|(v, c)| *c > 0 && *v == value)
+            {
+                Some(new_idx) => new_idx,
+                None => match counts.iter().position(|c| *c == 0) {
+                    Some(free_idx) => {
+                        palette[free_idx] = value;
+                        free_idx
+                    }
+                    None => {
+                        palette.push(value);
+                        counts.push(0);
+                        palette.len() - 1
+                    }
+                },
+            };
+
+            // grow bits if needed
+            let new_bits = bits_for(palette.len());
+            if new_bits > *bits {
+                let mut new_words = vec![0; word_count(len, new_bits)];
+                for i in 0..len {
+                    write_index(&mut new_words, new_bits, i, read_index(words, *bits, i));
+                }
+                *words = new_words;
+                *bits = new_bits;
+            }
+
+            counts[old_idx] -= 1;
+            counts[new_idx] += 1;
+            write_index(words, *bits, idx, new_idx as u32);
+
+            if counts[new_idx] as usize == len {
+                self.repr = Repr::Uniform(value);
+            }
+        }
+    }
+
+    /// set all values (becomes uniform)
+    pub fn fill(&mut self, value: T) {
+        self.repr = Repr::Uniform(value);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = T> + '_ {
+        (0..self.len).map(move |idx| self.get(idx))
+    }
+
+    pub fn to_vec(&self) -> Vec<T> {
+        self.iter().collect()
+    }
+
+    /// bytes used by this array, including heap allocations
+    pub fn memory_usage(&self) -> usize {
+        size_of::<Self>() + self.heap_usage()
+    }
+
+    /// bytes allocated in heap. 0 if uniform
+    pub fn heap_usage(&self) -> usize {
+        match &self.repr {
+            Repr::Uniform(_) => 0,
+            Repr::Packed {
+                palette, counts, words, ..
+            } => {
+                palette.capacity() * size_of::<T>()
+                    + counts.capacity() * size_of::<u32>()
+                    + words.capacity() * size_of::<u64>()
+            }
+        }
+    }
+}
+
+impl<T: Copy + PartialEq> PartialEq for PaletteArray<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+/// bits per index for palette length (power of 2)
+fn bits_for(palette_len: usize) -> u32 {
+    let mut bits = 1;
+    while (1usize << bits) < palette_len {
+        bits *= 2;
+    }
+    bits
+}
+
+fn word_count(len: usize, bits: u32) -> usize {
+    let per_word = (64 / bits) as usize;
+    len.div_ceil(per_word)
+}
+
+fn read_index(words: &[u64], bits: u32, idx: usize) -> u32 {
+    let per_word = (64 / bits) as usize;
+    let shift = (idx % per_word) as u32 * bits;
+    let mask = (1u64 << bits) - 1;
+    ((words[idx / per_word] >> shift) & mask) as u32
+}
+
+fn write_index(words: &mut [u64], bits: u32, idx: usize, value: u32) {
+    let per_word = (64 / bits) as usize;
+    let shift = (idx % per_word) as u32 * bits;
+    let mask = ((1u64 << bits) - 1) << shift;
+    let word = &mut words[idx / per_word];
+    *word = (*word & !mask) | ((value as u64) << shift);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TOTAL_BLOCK_COUNTS_IN_CHUNK;
+
+    #[test]
+    fn test_uniform() {
+        let mut array = PaletteArray::new(TOTAL_BLOCK_COUNTS_IN_CHUNK, 0u8);
+        assert_eq!(array.uniform(), Some(0));
+        assert_eq!(array.get(100), 0);
+        assert_eq!(array.memory_usage(), size_of::<PaletteArray<u8>>());
+
+        array.set(100, 3);
+        assert_eq!(array.uniform(), None);
+        assert_eq!(array.get(100), 3);
+        assert_eq!(array.get(101), 0);
+
+        // back to uniform
+        array.set(100, 0);
+        assert_eq!(array.uniform(), Some(0));
+    }
+
+    #[test]
+    fn test_grow_palette() {
+        let mut array = PaletteArray::new(TOTAL_BLOCK_COUNTS_IN_CHUNK, 0u8);
+        for i in 0..TOTAL_BLOCK_COUNTS_IN_CHUNK {
+            array.set(i, (i % 200) as u8);
+        }
+
+        for i in 0..TOTAL_BLOCK_COUNTS_IN_CHUNK {
+            assert_eq!(array.get(i), (i % 200) as u8);
+        }
+
+        // 8 bits per block
+        assert!(array.memory_usage() >= TOTAL_BLOCK_COUNTS_IN_CHUNK);
+        assert!(array.memory_usage() < TOTAL_BLOCK_COUNTS_IN_CHUNK * 2);
+    }
+
+    #[test]
+    fn test_reuse_free_entry() {
+        let mut array = PaletteArray::from_slice(&[0u8, 1, 2, 3]);
+        array.set(1, 0);
+        array.set(1, 4);
+
+        // 1 is freed and reused for 4, so still 2 bits
+        assert_eq!(array.to_vec(), vec![0, 4, 2, 3]);
+        assert_eq!(array, PaletteArray::from_slice(&[0, 4, 2, 3]));
+    }
+
+    #[test]
+    fn test_from_slice() {
+        let mut values = vec![0u8; TOTAL_BLOCK_COUNTS_IN_CHUNK];
+        assert_eq!(PaletteArray::from_slice(&values).uniform(), Some(0));
+
+        values[10] = 1;
+        values[4000] = 2;
+        let array = PaletteArray::from_slice(&values);
+        assert_eq!(array.to_vec(), values);
+        assert_eq!(array, PaletteArray::from_slice(&values));
+
+        // 2 bits per block
+        assert!(array.memory_usage() < TOTAL_BLOCK_COUNTS_IN_CHUNK / 2);
+    }
+}
@@ -0,0 +1,343 @@
+use crate::{BlockPos, Dir, IVec3, Plane, Vec3};
+
+/// all blocks in box from min to max (both inclusive)
+/// iterate x first, then y, then z (same order with block index in chunk)
+pub fn cuboid(min: BlockPos, max: BlockPos) -> impl Iterator<Item = BlockPos> {
+    (min.z..=max.z)
+        .flat_map(move |z| (min.y..=max.y).flat_map(move |y| (min.x..=max.x).map(move |x| BlockPos::new(x, y, z))))
+}
+
+/// blocks on 3d line from `from` to `to` (both inclusive)
+/// 3d bresenham algorithm. adjacent blocks share face, edge or corner
+pub fn line(from: BlockPos, to: BlockPos) -> LineIter {
+    LineIter::new(from, to)
+}
+
+/// blocks whose distance from center is not greater than radius
+pub fn sphere(center: BlockPos, radius: f32) -> impl Iterator<Item = BlockPos> {
+    ellipsoid(center, Vec3::new(radius, radius, radius))
+}
+
+/// only surface blocks of sphere
+/// surface block is block in sphere which has at least one face neighbor outside of sphere
+pub fn hollow_sphere(center: BlockPos, radius: f32) -> impl Iterator<Item = BlockPos> {
+    hollow_ellipsoid(center, Vec3::new(radius, radius, radius))
+}
+
+/// blocks inside of ellipsoid with radii in each axis
+pub fn ellipsoid(center: BlockPos, radii: Vec3) -> impl Iterator<Item = BlockPos> {
+    let extent = radii_extent(radii);
+    cuboid(offset(center, -extent), offset(center, extent)).filter(move |pos| in_ellipsoid(center, radii, *pos))
+}
+
+/// only surface blocks of ellipsoid
+pub fn hollow_ellipsoid(center: BlockPos, radii: Vec3) -> impl Iterator<Item = BlockPos> {
+    ellipsoid(center, radii).filter(move |pos| {
+        [Dir::XPos, Dir::XNeg, Dir::YPos, Dir::YNeg, Dir::ZPos, Dir::ZNeg]
+            .iter()
+            .any(|dir| !in_ellipsoid(center, radii, pos.get_neighbor(*dir)))
+    })
+}
+
+/// cylinder starting from base and extending to dir with length blocks
+/// (base is included, so length 1 is disc)
+pub fn cylinder(base: BlockPos, dir: Dir, radius: f32, length: i32) -> impl Iterator<Item = BlockPos> {
+    let r = radius.max(0.0).floor() as i32;
+    let end = offset(base, dir_vec(dir) * (length - 1).max(0));
+    let axis = axis_vec(dir);
+    let disc = IVec3::new(r, r, r) * (IVec3::ONE - axis);
+
+    let min = min_pos(offset(base, -disc), offset(end, -disc));
+    let max = max_pos(offset(base, disc), offset(end, disc));
+
+    cuboid(min, max).filter(move |pos| {
+        if length <= 0 {
+            return false;
+        }
+        let d = IVec3::new(pos.x - base.x, pos.y - base.y, pos.z - base.z) * (IVec3::ONE - axis);
+        (d.x * d.x + d.y * d.y + d.z * d.z) as f32 <= radius * radius
+    })
+}
+
+/// blocks whose center is within thickness / 2 from plane
+/// plane is infinite, so only blocks in [min, max] are returned
+pub fn plane(
+    plane: Plane,
+    thickness: f32,
+    block_size: f32,
+    min: BlockPos,
+    max: BlockPos,
+) -> impl Iterator<Item = BlockPos> {
+    let half = thickness * 0.5;
+    cuboid(min, max).filter(move |pos| {
+        let center = pos.aabb(block_size).center();
+        plane.dist(center).abs() <= half
+    })
+}
+
+fn in_ellipsoid(center: BlockPos, radii: Vec3, pos: BlockPos) -> bool {
+    let d = Vec3::new(
+        (pos.x - center.x) as f32,
+        (pos.y - center.y) as f32,
+        (pos.z - center.z) as f32,
+    );
+
+    // zero radius axis allows only center line
+    let axis = |d: f32, r: f32| {
+        if r > 0.0 {
+            (d / r) * (d / r)
+        } else if d == 0.0 {
+            0.0
+        } else {
+            f32::INFINITY
+        }
+    };
+    axis(d.x, radii.x) + axis(d.y, radii.y) + axis(d.z, radii.z) <= 1.0
+}
+
+fn radii_extent(radii: Vec3) -> IVec3 {
+    IVec3::new(
+        radii.x.max(0.0).floor() as i32,
+        radii.y.max(0.0).floor() as i32,
+        radii.z.max(0.0).floor() as i32,
+    )
+}
+
+fn offset(pos: BlockPos, v: IVec3) -> BlockPos {
+    BlockPos::new(pos.x + v.x, pos.y + v.y, pos.z + v.z)
+}
+
+fn min_pos(a: BlockPos, b: BlockPos) -> BlockPos {
+    BlockPos::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z))
+}
+
+fn max_pos(a: BlockPos, b: BlockPos) -> BlockPos {
+    BlockPos::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z))
+}
+
+fn dir_vec(dir: Dir) -> IVec3 {
+    match dir {
+        Dir::XPos => IVec3::X,
+        Dir::XNeg => -IVec3::X,
+        Dir::YPos => IVec3::Y,
+        Dir::YNeg => -IVec3::Y,
+        Dir::ZPos => IVec3::Z,
+        Dir::ZNeg => -IVec3::Z,
+    }
+}
+
+fn axis_vec(dir: Dir) -> IVec3 {
+    dir_vec(dir).abs()
+}
+
+/// 3d bresenham line iterator
+/// reference: <https://www.geeksforgeeks.org/bresenhams-algorithm-for-3-d-line-drawing/>
+#[derive(Debug)]
+pub struct LineIter {
+    cur: IVec3,
+    step: IVec3,
+    delta: IVec3,
+    // index of driving axis (0: x, 1: y, 2: z)
+    driving: usize,
+    err1: i32,
+    err2: i32,
+    remain: i32,
+}
+
+impl LineIter {
+    pub fn new(from: BlockPos, to: BlockPos) -> Self {
+        let from = IVec3::new(from.x, from.y, from.z);
+        let to = IVec3::new(to.x, to.y, to.z);
+        let diff = to - from;
+        let delta = diff.abs();
+        let step = diff.signum();
+
+        let driving = if delta.x >= delta.y && delta.x >= delta.z {
+            0
+        } else if delta.y >= delta.z {
+            1
+        } else {
+            2
+        };
+
+        let (d, a, b) = Self::axes(delta, driving);
+
+        Self {
+            cur: from,
+            step,
+            delta,
+            driving,
+            err1: 2 * a - d,
+            err2: 2 * b - d,
+            remain: d + 1,
+        }
+    }
+
+    /// (driving, secondary1, secondary2)
+    fn axes(v: IVec3, driving: usize) -> (i32, i32, i32) {
+        match driving {
+            0 => (v.x, v.y, v.z),
+            1 => (v.y, v.x, v.z),
+            _ => (v.z, v.x, v.y),
+        }
+    }
+
+    fn advance(&mut self, axis: usize) {
+        match axis {
+            0 => self.cur.x += self.step.x,
+            1 => self.cur.y += self.step.y,
+            _ => self.cur.z += self.step.z,
+        }
+    }
+}
+
+impl Iterator for LineIter {
+    type Item = BlockPos;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remain <= 0 {
+            return None;
+        }
+        self.remain -= 1;
+
+        let result = BlockPos::new(self.cur.x, self.cur.y, self.cur.z);
+
+        if self.remain > 0 {
+            let (d, a, b) = Self::axes(self.delta, self.driving);
+            let (axis1, axis2) = match self.driving {
+                0 => (1, 2),
+                1 => (0, 2),
+                _ => (0, 1),
+            };
+
+            self.advance(self.driving);
+            if self.err1 >= 0 {
+                self.advance(axis1);
+                self.err1 -= 2 * d;
+            }
+            if self.err2 >= 0 {
+                self.advance(axis2);
+                self.err2 -= 2 * d;
+            }
+            self.err1 += 2 * a;
+            self.err2 += 2 * b;
+        }
+
+        Some(result)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remain = self.remain.max(0) as usize;
+        (remain, Some(remain))
+    }
+}
+
+impl ExactSizeIterator for LineIter {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cuboid() {
+        let blocks: Vec<_> = cuboid(BlockPos::new(0, 0, 0), BlockPos::new(1, 2, 3)).collect();
+        assert_eq!(blocks.len(), 24);
+        assert_eq!(blocks[0], BlockPos::new(0, 0, 0));
+        assert_eq!(blocks[1], BlockPos::new(1, 0, 0));
+        assert_eq!(blocks[23], BlockPos::new(1, 2, 3));
+
+        // min > max is empty
+        assert_eq!(cuboid(BlockPos::new(1, 0, 0), BlockPos::new(0, 0, 0)).count(), 0);
+    }
+
+    #[test]
+    fn test_line() {
+        let blocks: Vec<_> = line(BlockPos::new(0, 0, 0), BlockPos::new(3, 0, 0)).collect();
+        assert_eq!(
+            blocks,
+            vec![
+                BlockPos::new(0, 0, 0),
+                BlockPos::new(1, 0, 0),
+                BlockPos::new(2, 0, 0),
+                BlockPos::new(3, 0, 0)
+            ]
+        );
+
+        let blocks: Vec<_> = line(BlockPos::new(0, 0, 0), BlockPos::new(-3, 3, 3)).collect();
+        assert_eq!(blocks.len(), 4);
+        assert_eq!(blocks[3], BlockPos::new(-3, 3, 3));
+
+        // single block
+        assert_eq!(line(BlockPos::new(5, 5, 5), BlockPos::new(5, 5, 5)).count(), 1);
+    }
+
+    #[test]
+    fn test_line_is_connected() {
+        let from = BlockPos::new(-4, 2, 7);
+        let to = BlockPos::new(13, -5, 1);
+        let blocks: Vec<_> = line(from, to).collect();
+
+        assert_eq!(blocks.len(), 18);
+        assert_eq!(*blocks.first().unwrap(), from);
+        assert_eq!(*blocks.last().unwrap(), to);
+
+        for w in blocks.windows(2) {
+            assert!((w[0].x - w[1].x).abs() <= 1);
+            assert!((w[0].y - w[1].y).abs() <= 1);
+            assert!((w[0].z - w[1].z).abs() <= 1);
+        }
+    }
+
+    #[test]
+    fn test_sphere() {
+        let center = BlockPos::new(10, 10, 10);
+        assert_eq!(sphere(center, 0.0).count(), 1);
+        assert_eq!(sphere(center, 1.0).count(), 7);
+        assert_eq!(sphere(center, 1.5).count(), 19);
+        assert_eq!(sphere(center, 2.0).count(), 33);
+    }
+
+    #[test]
+    fn test_hollow_sphere() {
+        let center = BlockPos::new(0, 0, 0);
+        assert_eq!(hollow_sphere(center, 1.0).count(), 6);
+        assert!(hollow_sphere(center, 3.0).all(|pos| pos != center));
+
+        let solid = sphere(center, 5.0).count();
+        let hollow = hollow_sphere(center, 5.0).count();
+        assert!(hollow < solid);
+    }
+
+    #[test]
+    fn test_ellipsoid() {
+        let center = BlockPos::new(0, 0, 0);
+        assert_eq!(ellipsoid(center, Vec3::new(2.0, 1.0, 1.0)).count(), 9);
+        assert_eq!(ellipsoid(center, Vec3::new(3.0, 0.0, 0.0)).count(), 7);
+    }
+
+    #[test]
+    fn test_cylinder() {
+        let base = BlockPos::new(0, 0, 0);
+        let blocks: Vec<_> = cylinder(base, Dir::YPos, 1.0, 3).collect();
+        assert_eq!(blocks.len(), 15);
+        assert!(blocks.iter().all(|pos| pos.y >= 0 && pos.y <= 2));
+
+        let blocks: Vec<_> = cylinder(base, Dir::XNeg, 0.0, 4).collect();
+        assert_eq!(blocks.len(), 4);
+        assert!(blocks.iter().all(|pos| pos.x <= 0 && pos.x >= -3));
+
+        assert_eq!(cylinder(base, Dir::ZPos, 2.0, 0).count(), 0);
+    }
+
+    #[test]
+    fn test_plane() {
+        // y = 0.5 (center of blocks with y = 0)
+        let p = Plane::new(0.0, 1.0, 0.0, -0.5);
+        let blocks: Vec<_> = plane(p, 1.0, 1.0, BlockPos::new(-2, -2, -2), BlockPos::new(2, 2, 2)).collect();
+        assert_eq!(blocks.len(), 25);
+        assert!(blocks.iter().all(|pos| pos.y == 0));
+
+        let count = plane(p, 3.0, 1.0, BlockPos::new(-2, -2, -2), BlockPos::new(2, 2, 2)).count();
+        assert_eq!(count, 75);
+    }
+}
@@ -0,0 +1,405 @@
+use enumflags2::BitFlags;
+
+use crate::{shape, Aabb, BlockPos, Dir, Vec3};
+
+/// tolerance for touching faces
+/// aabb touching block face is not treated as overlapped
+const SKIN: f32 = 1.0e-4;
+
+/// first contact of moving aabb
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SweepHit {
+    /// time of impact in [0, 1], ratio of motion
+    pub time: f32,
+    /// normal of contact face (points from block to moving aabb)
+    pub normal: Dir,
+    /// block which is hit
+    pub block: BlockPos,
+}
+
+impl SweepHit {
+    /// remaining motion after impact, with motion into contact face removed
+    pub fn slide(&self, motion: Vec3) -> Vec3 {
+        let remain = motion * (1.0 - self.time);
+        remove_axis(remain, self.normal)
+    }
+}
+
+/// result of move_aabb
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MoveResult {
+    /// aabb after moved
+    pub aabb: Aabb,
+    /// motion actually applied
+    pub motion: Vec3,
+    /// normals of contact faces during move (empty if not collided)
+    pub contacts: BitFlags<Dir>,
+}
+
+/// find first solid block hit by aabb moving with motion
+/// blocks which already overlap aabb at start are ignored
+/// layers of blocks entered by leading faces of aabb are visited in order of time (voxel dda), so fast motion does
+/// not tunnel. cost grows linearly with distance times area of leading faces, and stops at first hit
+pub fn sweep_aabb(aabb: &Aabb, motion: Vec3, block_size: f32, is_solid: impl Fn(BlockPos) -> bool) -> Option<SweepHit> {
+    // next layer of blocks entered along each moving axis, and time of entering it
+    let mut layers = [0_i32; 3];
+    let mut times = [f32::INFINITY; 3];
+    for axis in 0..3 {
+        if motion[axis] > 0.0 {
+            layers[axis] = ((aabb.max[axis] - SKIN) / block_size).ceil() as i32;
+        } else if motion[axis] < 0.0 {
+            layers[axis] = ((aabb.min[axis] + SKIN) / block_size).floor() as i32 - 1;
+        } else {
+            continue;
+        }
+        times[axis] = layer_time(aabb, motion, block_size, axis, layers[axis]);
+    }
+
+    let mut first: Option<SweepHit> = None;
+    loop {
+        let time = times.iter().copied().fold(f32::INFINITY, f32::min);
+        if time > 1.0 || matches!(first, Some(hit) if time > hit.time) {
+            break;
+        }
+
+        // layers entered at same time are advanced together, so next time is after all of them
+        let entered = (0..3)
+            .filter(|axis| times[*axis] == time)
+            .map(|axis| (axis, layers[axis]))
+            .collect::<Vec<_>>();
+        for (axis, _) in &entered {
+            layers[*axis] += if motion[*axis] > 0.0 { 1 } else { -1 };
+            times[*axis] = layer_time(aabb, motion, block_size, *axis, layers[*axis]);
+        }
+        let next_time = times.iter().copied().fold(1.0, f32::min);
+
+        // blocks overlapped on other axes until next layer is entered
+        let from = motion * time.max(0.0);
+        let to = motion * next_time;
+        let swept = Aabb::new(aabb.min + from.min(to), aabb.max + from.max(to));
+        let (min, max) = block_range(&swept, block_size);
+
+        for (axis, layer) in entered {
+            let mut layer_min = min;
+            let mut layer_max = max;
+            set_axis(&mut layer_min, axis, layer);
+            set_axis(&mut layer_max, axis, layer);
+
+            for block in shape::cuboid(layer_min, layer_max) {
+                if !is_solid(block) {
+                    continue;
+                }
+
+                if let Some((time, normal)) = sweep_block(aabb, motion, &block.aabb(block_size)) {
+                    let closer = match first {
+                        Some(hit) => time < hit.time,
+                        None => true,
+                    };
+
+                    if closer {
+                        first = Some(SweepHit { time, normal, block });
+                    }
+                }
+            }
+        }
+    }
+
+    first
+}
+
+/// time when leading face of aabb moving along axis enters layer
+fn layer_time(aabb: &Aabb, motion: Vec3, block_size: f32, axis: usize, layer: i32) -> f32 {
+    if motion[axis] > 0.0 {
+        (layer as f32 * block_size - aabb.max[axis]) / motion[axis]
+    } else {
+        ((layer + 1) as f32 * block_size - aabb.min[axis]) / motion[axis]
+    }
+}
+
+/// move aabb with motion, resolving collision one axis at a time (y, x, z)
+/// blocked axis is stopped at contact face and other axes keep moving (sliding)
+/// each axis checks every block it passes, so fast motion does not tunnel
+pub fn move_aabb(aabb: &Aabb, motion: Vec3, block_size: f32, is_solid: impl Fn(BlockPos) -> bool) -> MoveResult {
+    let mut aabb = *aabb;
+    let mut applied = Vec3::ZERO;
+    let mut contacts = BitFlags::<Dir>::empty();
+
+    for axis in [1_usize, 0, 2] {
+        let delta = motion[axis];
+        if delta == 0.0 {
+            continue;
+        }
+
+        let allowed = clip_axis(&aabb, axis, delta, block_size, &is_solid);
+        if allowed != delta {
+            contacts |= contact_normal(axis, delta);
+        }
+
+        let mut offset = Vec3::ZERO;
+        offset[axis] = allowed;
+        aabb = Aabb::new(aabb.min + offset, aabb.max + offset);
+        applied[axis] = allowed;
+    }
+
+    MoveResult {
+        aabb,
+        motion: applied,
+        contacts,
+    }
+}
+
+/// how far aabb can move along axis (0: x, 1: y, 2: z) before hitting solid block
+fn clip_axis(aabb: &Aabb, axis: usize, delta: f32, block_size: f32, is_solid: &impl Fn(BlockPos) -> bool) -> f32 {
+    let (min, max) = block_range(aabb, block_size);
+
+    // block layers along axis which aabb enters while moving
+    let layers: Vec<i32> = if delta > 0.0 {
+        let from = ((aabb.max[axis] - SKIN) / block_size).ceil() as i32;
+        let to = ((aabb.max[axis] + delta) / block_size).ceil() as i32 - 1;
+        (from..=to).collect()
+    } else {
+        let from = ((aabb.min[axis] + SKIN) / block_size).floor() as i32 - 1;
+        let to = ((aabb.min[axis] + delta) / block_size).floor() as i32;
+        (to..=from).rev().collect()
+    };
+
+    for layer in layers {
+        let mut layer_min = min;
+        let mut layer_max = max;
+        set_axis(&mut layer_min, axis, layer);
+        set_axis(&mut layer_max, axis, layer);
+
+        if shape::cuboid(layer_min, layer_max).any(is_solid) {
+            let allowed = if delta > 0.0 {
+                layer as f32 * block_size - aabb.max[axis]
+            } else {
+                (layer + 1) as f32 * block_size - aabb.min[axis]
+            };
+
+            // never move backward when already touching
+            return if delta > 0.0 {
+                allowed.clamp(0.0, delta)
+            } else {
+                allowed.clamp(delta, 0.0)
+            };
+        }
+    }
+
+    delta
+}
+
+/// swept aabb vs static aabb
+/// returns (time of impact, normal of contact face)
+fn sweep_block(aabb: &Aabb, motion: Vec3, block: &Aabb) -> Option<(f32, Dir)> {
+    let mut entry = f32::NEG_INFINITY;
+    let mut exit = f32::INFINITY;
+    let mut normal = None;
+
+    for axis in 0..3 {
+        let d = motion[axis];
+        if d == 0.0 {
+            // should already overlap on this axis
+            if aabb.max[axis] - SKIN <= block.min[axis] || aabb.min[axis] + SKIN >= block.max[axis] {
+                return None;
+            }
+            continue;
+        }
+
+        let (axis_entry, axis_exit) = if d > 0.0 {
+            (
+                (block.min[axis] - aabb.max[axis]) / d,
+                (block.max[axis] - aabb.min[axis]) / d,
+            )
+        } else {
+            (
+                (block.max[axis] - aabb.min[axis]) / d,
+                (block.min[axis] - aabb.max[axis]) / d,
+            )
+        };
+
+        if axis_entry > entry {
+            entry = axis_entry;
+            normal = Some(contact_normal(axis, d));
+        }
+        exit = exit.min(axis_exit);
+    }
+
+    let normal = normal?;
+    if entry > exit || !(0.0..=1.0).contains(&entry) {
+        return None;
+    }
+
+    Some((entry, normal))
+}
+
+/// blocks overlapped by aabb (touching faces are excluded)
+fn block_range(aabb: &Aabb, block_size: f32) -> (BlockPos, BlockPos) {
+    let min = BlockPos::from_vec3(&(aabb.min + Vec3::splat(SKIN)), block_size);
+    let max = BlockPos::from_vec3(&(aabb.max - Vec3::splat(SKIN)), block_size);
+    (min, max)
+}
+
+/// normal of face hit when moving to delta along axis
+fn contact_normal(axis: usize, delta: f32) -> Dir {
+    match (axis, delta > 0.0) {
+        (0, true) => Dir::XNeg,
+        (0, false) => Dir::XPos,
+        (1, true) => Dir::YNeg,
+        (1, false) => Dir::YPos,
+        (_, true) => Dir::ZNeg,
+        (_, false) => Dir::ZPos,
+    }
+}
+
+fn remove_axis(mut v: Vec3, normal: Dir) -> Vec3 {
+    match normal {
+        Dir::XPos | Dir::XNeg => v.x = 0.0,
+        Dir::YPos | Dir::YNeg => v.y = 0.0,
+        Dir::ZPos | Dir::ZNeg => v.z = 0.0,
+    }
+    v
+}
+
+fn set_axis(pos: &mut BlockPos, axis: usize, val: i32) {
+    match axis {
+        0 => pos.x = val,
+        1 => pos.y = val,
+        _ => pos.z = val,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_abs_diff_eq;
+
+    use super::*;
+
+    fn floor_at(y: i32) -> impl Fn(BlockPos) -> bool {
+        move |pos| pos.y == y
+    }
+
+    fn player(x: f32, y: f32, z: f32) -> Aabb {
+        Aabb::new(Vec3::new(x, y, z), Vec3::new(x + 0.6, y + 1.8, z + 0.6))
+    }
+
+    #[test]
+    fn test_sweep_hit_floor() {
+        let aabb = player(0.2, 2.0, 0.2);
+        let hit = sweep_aabb(&aabb, Vec3::new(0.0, -5.0, 0.0), 1.0, floor_at(0)).unwrap();
+
+        assert_abs_diff_eq!(hit.time, 0.2);
+        assert_eq!(hit.normal, Dir::YPos);
+        assert_eq!(hit.block.y, 0);
+    }
+
+    #[test]
+    fn test_sweep_miss() {
+        let aabb = player(0.2, 2.0, 0.2);
+        assert!(sweep_aabb(&aabb, Vec3::new(0.0, 5.0, 0.0), 1.0, floor_at(0)).is_none());
+        assert!(sweep_aabb(&aabb, Vec3::new(0.0, -0.5, 0.0), 1.0, floor_at(0)).is_none());
+    }
+
+    #[test]
+    fn test_sweep_no_tunneling() {
+        let aabb = player(0.2, 2.0, 0.2);
+        let hit = sweep_aabb(&aabb, Vec3::new(0.0, -1000.0, 0.0), 1.0, floor_at(-500)).unwrap();
+        assert_eq!(hit.block.y, -500);
+        assert_eq!(hit.normal, Dir::YPos);
+    }
+
+    #[test]
+    fn test_sweep_wall() {
+        // wall at x == 3
+        let aabb = player(0.2, 0.0, 0.2);
+        let hit = sweep_aabb(&aabb, Vec3::new(10.0, 0.0, 2.0), 1.0, |pos| pos.x == 3).unwrap();
+        assert_eq!(hit.normal, Dir::XNeg);
+        assert_abs_diff_eq!(hit.time, 0.22);
+
+        let slide = hit.slide(Vec3::new(10.0, 0.0, 2.0));
+        assert_abs_diff_eq!(slide.x, 0.0);
+        assert_abs_diff_eq!(slide.z, 2.0 * 0.78, epsilon = 0.0001);
+    }
+
+    /// every block in swept area, which sweep_aabb should agree with
+    fn sweep_every_block(aabb: &Aabb, motion: Vec3, is_solid: impl Fn(BlockPos) -> bool) -> Option<f32> {
+        let moved = Aabb::new(aabb.min + motion, aabb.max + motion);
+        let swept = Aabb::new(aabb.min.min(moved.min), aabb.max.max(moved.max));
+        let (min, max) = block_range(&swept, 1.0);
+        shape::cuboid(min, max)
+            .filter(|block| is_solid(*block))
+            .filter_map(|block| sweep_block(aabb, motion, &block.aabb(1.0)))
+            .map(|(time, _)| time)
+            .fold(None, |first: Option<f32>, time| {
+                Some(first.map_or(time, |first| first.min(time)))
+            })
+    }
+
+    #[test]
+    fn test_sweep_equals_every_block() {
+        // sparse pillars
+        let is_solid = |pos: BlockPos| (pos.x * 7 + pos.y * 3 + pos.z * 5).rem_euclid(11) == 0;
+        let aabb = player(0.3, 0.1, 0.7);
+
+        for i in 0..200 {
+            let f = i as f32;
+            let motion = Vec3::new((f * 0.37).sin() * 6.0, (f * 0.61).cos() * 6.0, (f * 0.23).sin() * 6.0);
+            let hit = sweep_aabb(&aabb, motion, 1.0, is_solid).map(|hit| hit.time);
+            assert_eq!(hit, sweep_every_block(&aabb, motion, is_solid), "motion {:?}", motion);
+        }
+    }
+
+    #[test]
+    fn test_sweep_cost_is_linear() {
+        // swept box of this motion has a billion blocks
+        let count = std::cell::Cell::new(0);
+        let aabb = player(0.2, 0.0, 0.2);
+        let hit = sweep_aabb(&aabb, Vec3::new(1000.0, 1000.0, 1000.0), 1.0, |_| {
+            count.set(count.get() + 1);
+            false
+        });
+        assert!(hit.is_none());
+        assert!(count.get() < 20_000, "{} blocks tested", count.get());
+    }
+
+    #[test]
+    fn test_move_slide_on_floor() {
+        let aabb = player(0.2, 1.5, 0.2);
+        let result = move_aabb(&aabb, Vec3::new(3.0, -2.0, 0.0), 1.0, floor_at(0));
+
+        assert_abs_diff_eq!(result.aabb.min.y, 1.0);
+        assert_abs_diff_eq!(result.aabb.min.x, 3.2);
+        assert_abs_diff_eq!(result.motion.y, -0.5);
+        assert_eq!(result.contacts, Dir::YPos);
+
+        // standing on floor, move again
+        let result = move_aabb(&result.aabb, Vec3::new(0.0, -1.0, 1.0), 1.0, floor_at(0));
+        assert_abs_diff_eq!(result.motion.y, 0.0);
+        assert_abs_diff_eq!(result.motion.z, 1.0);
+        assert_eq!(result.contacts, Dir::YPos);
+    }
+
+    #[test]
+    fn test_move_no_tunneling() {
+        let aabb = player(0.2, 10.0, 0.2);
+        let result = move_aabb(&aabb, Vec3::new(0.0, -10_000.0, 0.0), 1.0, floor_at(-300));
+        assert_abs_diff_eq!(result.aabb.min.y, -299.0);
+        assert_eq!(result.contacts, Dir::YPos);
+    }
+
+    #[test]
+    fn test_move_wall() {
+        let aabb = player(0.0, 0.0, 0.0);
+        let result = move_aabb(&aabb, Vec3::new(-5.0, 0.0, 0.0), 0.5, |pos| pos.x == -4);
+        assert_abs_diff_eq!(result.aabb.min.x, -1.5);
+        assert_eq!(result.contacts, Dir::XPos);
+    }
+
+    #[test]
+    fn test_move_free() {
+        let aabb = player(0.0, 0.0, 0.0);
+        let motion = Vec3::new(1.0, 2.0, 3.0);
+        let result = move_aabb(&aabb, motion, 1.0, |_| false);
+        assert_eq!(result.motion, motion);
+        assert!(result.contacts.is_empty());
+    }
+}
@@ -1,4 +1,4 @@
-use voxrs_asset::{AssetHandle, WorldMaterialAsset};
+use voxrs_asset::{AssetHandle, CubemapAsset, FogMode, TextureAsset, WorldFog, WorldMaterialAsset};
 use voxrs_math::*;
 use voxrs_types::SafeCloner;
 
@@ -16,9 +16,130 @@ pub struct Camera {
     pub view_proj_mat: Mat4,
 }
 
+/// limits of chunk meshes kept in gpu. least recently used ones over limits are evicted
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ChunkCacheBudget {
+    pub max_count: usize,
+    pub max_bytes: usize,
+}
+
+impl Default for ChunkCacheBudget {
+    fn default() -> Self {
+        Self {
+            max_count: 4096,
+            max_bytes: 256 * 1024 * 1024,
+        }
+    }
+}
+
+/// sky and sun light of world
+#[derive(Clone, Debug, PartialEq)]
+pub struct Environment {
+    pub sky_color: Vec3,                           // sky at zenith
+    pub horizon_color: Vec3,                       // sky at horizon
+    pub sun_dir: Vec3,                             // direction sun light travels. normalized
+    pub sun_color: Vec3,                           // diffuse light from sun
+    pub sun_size: f32,                             // angular radius of sun disc in radians
+    pub ambient_color: Vec3,                       // light on every face regardless of direction
+    pub skybox: Option<AssetHandle<CubemapAsset>>, // drawn instead of gradient and sun disc
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self {
+            sky_color: Vec3::new(0.1, 0.2, 0.3),
+            horizon_color: Vec3::new(0.3, 0.4, 0.5),
+            sun_dir: Vec3::new(-0.3, -1.0, -0.5).normalize(),
+            sun_color: Vec3::new(0.7, 0.7, 0.7),
+            sun_size: 0.02,
+            ambient_color: Vec3::new(0.4, 0.4, 0.4),
+            skybox: None,
+        }
+    }
+}
+
+/// distance fog over chunks. chunks farther than end are fully hidden by color
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Fog {
+    pub color: Vec3,
+    pub start: f32, // distance from camera
+    pub end: f32,
+    pub mode: FogMode,
+}
+
+impl From<&WorldFog> for Fog {
+    fn from(fog: &WorldFog) -> Self {
+        Self {
+            color: Vec3::from(fog.color),
+            start: fog.start,
+            end: fog.end,
+            mode: fog.mode,
+        }
+    }
+}
+
+/// cascaded shadow map of sun light
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ShadowSettings {
+    pub enabled: bool,
+    pub cascade_count: usize, // 1 to 4
+    pub distance: f32,        // shadow is drawn up to this distance from camera. clamped to zfar
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            cascade_count: 3,
+            distance: 96.0,
+        }
+    }
+}
+
+/// post processing of hdr scene before ui is drawn
+#[derive(Clone, Debug, PartialEq)]
+pub struct PostProcess {
+    pub exposure: f32,                                // scene color is scaled by this before tone mapping
+    pub bloom: Option<Bloom>,                         // glow around bright pixels like emissive blocks
+    pub fxaa: bool,                                   // anti aliasing of edges after tone mapping
+    pub color_lut: Option<AssetHandle<TextureAsset>>, // color grading. n slices of n x n laid side by side
+}
+
+impl Default for PostProcess {
+    fn default() -> Self {
+        Self {
+            exposure: 1.0,
+            bloom: Some(Bloom::default()),
+            fxaa: true,
+            color_lut: None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Bloom {
+    pub threshold: f32, // pixels brighter than this glow
+    pub intensity: f32, // glow added to scene
+}
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Self {
+            threshold: 1.0,
+            intensity: 0.5,
+        }
+    }
+}
+
 pub struct Blueprint {
     pub camera: Camera,
+    pub environment: Environment,
+    pub shadow_settings: ShadowSettings,
+    pub fog: Option<Fog>,
+    pub post_process: PostProcess,
     pub block_size: f32,
+    pub greedy_meshing: bool, // merge coplanar faces of chunk into larger quads
+    pub chunk_cache_budget: ChunkCacheBudget,
     pub world_block_mat_handle: Option<AssetHandle<WorldMaterialAsset>>,
     pub chunks: Vec<SafeCloner<Chunk>>,
     pub dynamic_blocks: Vec<DynamicBlock>,
@@ -29,7 +150,13 @@ impl Blueprint {
     pub fn new() -> Self {
         Self {
             block_size: 1.0,
+            greedy_meshing: false,
+            chunk_cache_budget: ChunkCacheBudget::default(),
             camera: Camera::default(),
+            environment: Environment::default(),
+            shadow_settings: ShadowSettings::default(),
+            fog: None,
+            post_process: PostProcess::default(),
             world_block_mat_handle: None,
             chunks: Vec::new(),
             dynamic_blocks: Vec::new(),
@@ -41,6 +168,22 @@ impl Blueprint {
         self.camera = camera;
     }
 
+    pub fn set_environment(&mut self, environment: Environment) {
+        self.environment = environment;
+    }
+
+    pub fn set_shadow_settings(&mut self, settings: ShadowSettings) {
+        self.shadow_settings = settings;
+    }
+
+    pub fn set_fog(&mut self, fog: Fog) {
+        self.fog = Some(fog);
+    }
+
+    pub fn set_post_process(&mut self, post_process: PostProcess) {
+        self.post_process = post_process;
+    }
+
     pub fn set_world_mat(&mut self, handle: AssetHandle<WorldMaterialAsset>) {
         self.world_block_mat_handle = Some(handle);
     }
@@ -49,6 +192,14 @@ impl Blueprint {
         self.block_size = block_size;
     }
 
+    pub fn set_greedy_meshing(&mut self, greedy_meshing: bool) {
+        self.greedy_meshing = greedy_meshing;
+    }
+
+    pub fn set_chunk_cache_budget(&mut self, budget: ChunkCacheBudget) {
+        self.chunk_cache_budget = budget;
+    }
+
     pub fn add_chunk(&mut self, chunk: SafeCloner<Chunk>) {
         self.chunks.push(chunk);
     }
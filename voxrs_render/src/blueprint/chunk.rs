@@ -3,41 +3,120 @@ use std::sync::atomic::{AtomicU64, Ordering};
 use enumflags2::BitFlags;
 use voxrs_math::*;
 
-use super::{BlockMatIdx, ChunkId};
+use super::{BlockMatIdx, ChunkId, ChunkVersion};
 
 pub struct Chunk {
     pub id: ChunkId,
+    pub version: ChunkVersion,
     pub pos: Vec3,
     pub aabb: Aabb,
-    pub blocks: Vec<BlockMatIdx>, // 0 : empty
-    pub vis: Vec<BitFlags<Dir>>,
+    pub blocks: PaletteArray<BlockMatIdx>, // 0 : empty
+    pub vis: PaletteArray<BitFlags<Dir>>,
+    pub light: PaletteArray<u8>, // sky and block light (see LightChannel)
+    pub border: ChunkBorder,
 }
 
 impl Chunk {
-    pub fn new(pos: Vec3, aabb: Aabb, blocks: Vec<u8>, vis: Vec<BitFlags<Dir>>) -> Self {
+    /// chunk without light and border. they are filled by world
+    pub fn new(
+        id: ChunkId,
+        pos: Vec3,
+        aabb: Aabb,
+        blocks: PaletteArray<BlockMatIdx>,
+        vis: PaletteArray<BitFlags<Dir>>,
+    ) -> Self {
+        let light = PaletteArray::new(blocks.len(), 0);
         Self {
-            id: generate_chunk_id(),
+            id,
+            version: generate_chunk_version(),
             pos,
             aabb,
             blocks,
             vis,
+            light,
+            border: ChunkBorder::default(),
+        }
+    }
+
+    /// bytes used by this chunk, including blocks, vis, light and border
+    pub fn memory_usage(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + self.blocks.heap_usage()
+            + self.vis.heap_usage()
+            + self.light.heap_usage()
+            + self.border.heap_usage()
+    }
+}
+
+/// side of chunk with one block of neighbors on each side
+const PADDED_SIDE: usize = BLOCK_COUNT_IN_CHUNKSIDE + 2;
+
+/// blocks of neighbor chunks touching chunk, which faces at chunk border depend on
+/// positions are local to chunk, -1 to 16 for each axis. values of blocks in chunk are not used
+/// default border is empty and open to sky
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChunkBorder {
+    light: PaletteArray<u8>,    // sky and block light (see LightChannel)
+    opaque: PaletteArray<bool>, // opaque blocks darken corners of faces
+}
+
+impl Default for ChunkBorder {
+    fn default() -> Self {
+        let open_sky = LightChannel::Sky.set(0, MAX_LIGHT);
+        let count = PADDED_SIDE * PADDED_SIDE * PADDED_SIDE;
+        Self {
+            light: PaletteArray::new(count, open_sky),
+            opaque: PaletteArray::new(count, false),
         }
     }
 }
 
-fn generate_chunk_id() -> u64 {
+impl ChunkBorder {
+    pub fn get_light(&self, local_pos: BlockPos) -> u8 {
+        self.light.get(Self::index(local_pos))
+    }
+
+    pub fn set_light(&mut self, local_pos: BlockPos, light: u8) {
+        self.light.set(Self::index(local_pos), light);
+    }
+
+    pub fn is_opaque(&self, local_pos: BlockPos) -> bool {
+        self.opaque.get(Self::index(local_pos))
+    }
+
+    pub fn set_opaque(&mut self, local_pos: BlockPos, opaque: bool) {
+        self.opaque.set(Self::index(local_pos), opaque);
+    }
+
+    pub fn heap_usage(&self) -> usize {
+        self.light.heap_usage() + self.opaque.heap_usage()
+    }
+
+    fn index(local_pos: BlockPos) -> usize {
+        let side = PADDED_SIDE as i32;
+        let (x, y, z) = (local_pos.x + 1, local_pos.y + 1, local_pos.z + 1);
+        debug_assert!((0..side).contains(&x) && (0..side).contains(&y) && (0..side).contains(&z));
+        (x + y * side + z * side * side) as usize
+    }
+}
+
+fn generate_chunk_version() -> ChunkVersion {
     static COUNTER: AtomicU64 = AtomicU64::new(1);
     COUNTER.fetch_add(1, Ordering::Relaxed)
 }
 
+/// clone is modified by SafeCloner. so it has same id and new version
 impl Clone for Chunk {
     fn clone(&self) -> Self {
         Self {
-            id: generate_chunk_id(),
+            id: self.id,
+            version: generate_chunk_version(),
             pos: self.pos,
             aabb: self.aabb,
             blocks: self.blocks.clone(),
             vis: self.vis.clone(),
+            light: self.light.clone(),
+            border: self.border.clone(),
         }
     }
 }
@@ -49,41 +128,89 @@ mod tests {
     #[test]
     fn create_chunk() {
         let chunk = Chunk::new(
+            ChunkPos::new(1, 2, 3),
             Vec3::new(1.0, 2.0, 3.0),
             Aabb::new(Vec3::new(1.0, 2.0, 3.0), Vec3::new(11.0, 12.0, 13.0)),
-            Vec::new(),
-            Vec::new(),
+            PaletteArray::new(0, 0),
+            PaletteArray::new(0, BitFlags::empty()),
         );
-        assert_ne!(chunk.id, 0);
+        assert_eq!(chunk.id, ChunkPos::new(1, 2, 3));
+        assert_ne!(chunk.version, 0);
     }
 
     #[test]
-    fn clone_chunk_should_have_different_id() {
+    fn clone_chunk_should_have_same_id_and_newer_version() {
         let chunk = Chunk::new(
+            ChunkPos::new(1, 2, 3),
             Vec3::new(1.0, 2.0, 3.0),
             Aabb::new(Vec3::new(1.0, 2.0, 3.0), Vec3::new(11.0, 12.0, 13.0)),
-            Vec::new(),
-            Vec::new(),
+            PaletteArray::new(0, 0),
+            PaletteArray::new(0, BitFlags::empty()),
         );
         let clonned = chunk.clone();
 
-        assert_ne!(clonned.id, chunk.id);
+        assert_eq!(clonned.id, chunk.id);
+        assert!(clonned.version > chunk.version);
     }
 
     #[test]
     fn when_clonned_blocks_also_clonned() {
         let mut chunk = Chunk::new(
+            ChunkPos::new(1, 2, 3),
             Vec3::new(1.0, 2.0, 3.0),
             Aabb::new(Vec3::new(1.0, 2.0, 3.0), Vec3::new(11.0, 12.0, 13.0)),
-            Vec::new(),
-            Vec::new(),
+            PaletteArray::new(TOTAL_BLOCK_COUNTS_IN_CHUNK, 0),
+            PaletteArray::new(TOTAL_BLOCK_COUNTS_IN_CHUNK, BitFlags::empty()),
         );
         let clonned = chunk.clone();
 
-        chunk.blocks.push(1);
-        chunk.blocks.push(2);
-        chunk.blocks.push(3);
+        chunk.blocks.set(0, 1);
+        chunk.blocks.set(1, 2);
+        chunk.blocks.set(2, 3);
 
         assert_ne!(clonned.blocks, chunk.blocks);
     }
+
+    #[test]
+    fn border_is_open_sky_until_set() {
+        let mut border = ChunkBorder::default();
+        let corner = BlockPos::new(-1, 16, -1);
+        assert_eq!(LightChannel::Sky.get(border.get_light(corner)), MAX_LIGHT);
+
+        border.set_light(corner, 3);
+        assert_eq!(border.get_light(corner), 3);
+        assert_eq!(
+            LightChannel::Sky.get(border.get_light(BlockPos::new(16, 16, -1))),
+            MAX_LIGHT
+        );
+
+        assert!(!border.is_opaque(corner));
+        border.set_opaque(corner, true);
+        assert!(border.is_opaque(corner));
+        assert_eq!(border.get_light(corner), 3);
+    }
+
+    #[test]
+    fn uniform_chunk_memory_usage() {
+        let empty = Chunk::new(
+            ChunkPos::new(0, 0, 0),
+            Vec3::ZERO,
+            Aabb::new(Vec3::ZERO, Vec3::ONE),
+            PaletteArray::new(TOTAL_BLOCK_COUNTS_IN_CHUNK, 0),
+            PaletteArray::new(TOTAL_BLOCK_COUNTS_IN_CHUNK, BitFlags::empty()),
+        );
+        assert_eq!(empty.memory_usage(), std::mem::size_of::<Chunk>());
+
+        let mut blocks = PaletteArray::new(TOTAL_BLOCK_COUNTS_IN_CHUNK, 0);
+        blocks.set(0, 1);
+        let chunk = Chunk::new(
+            ChunkPos::new(0, 0, 0),
+            Vec3::ZERO,
+            Aabb::new(Vec3::ZERO, Vec3::ONE),
+            blocks,
+            PaletteArray::new(TOTAL_BLOCK_COUNTS_IN_CHUNK, BitFlags::empty()),
+        );
+        assert!(chunk.memory_usage() > empty.memory_usage());
+        assert!(chunk.memory_usage() < TOTAL_BLOCK_COUNTS_IN_CHUNK);
+    }
 }
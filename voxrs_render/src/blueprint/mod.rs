@@ -1,10 +1,19 @@
+use voxrs_math::ChunkPos;
+
 #[allow(clippy::module_inception)]
 mod blueprint;
+pub use blueprint::Bloom;
 pub use blueprint::Blueprint;
 pub use blueprint::Camera;
+pub use blueprint::ChunkCacheBudget;
+pub use blueprint::Environment;
+pub use blueprint::Fog;
+pub use blueprint::PostProcess;
+pub use blueprint::ShadowSettings;
 
 mod chunk;
 pub use chunk::Chunk;
+pub use chunk::ChunkBorder;
 
 mod dynamic_block;
 pub use dynamic_block::DynamicBlock;
@@ -14,7 +23,11 @@ pub type BlockMatIdx = u8;
 
 pub type BlockIdx = u16;
 
-pub type ChunkId = u64;
+/// stable id of chunk. position of chunk in world
+pub type ChunkId = ChunkPos;
+
+/// changed whenever chunk is modified. newer version is greater
+pub type ChunkVersion = u64;
 
 mod ui;
 pub use ui::Panel;
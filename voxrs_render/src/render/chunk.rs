@@ -1,29 +1,74 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc};
 
-use crate::blueprint::{self, BlockIdx, BlockMatIdx, ChunkId};
-use enumflags2::BitFlags;
-use voxrs_asset::{AssetHandle, AssetHash, MaterialAsset, WorldMaterialAsset};
+use crate::blueprint::{self, BlockMatIdx, ChunkCacheBudget, ChunkId};
+use voxrs_asset::{AssetHandle, AssetHash, AssetManager, MaterialAlpha, ShaderAsset, WorldMaterialAsset};
 use voxrs_math::*;
-use voxrs_rhi::DEPTH_FORMAT;
-use voxrs_types::SafeCloner;
+use voxrs_rhi::{Texture, DEPTH_FORMAT, HDR_FORMAT};
+use voxrs_types::{io::FileSystem, SafeCloner};
 
 use wgpu::util::DeviceExt;
 
-use super::{ChunkCache, CommonUniforms, ShaderHash};
+use super::{
+    render_graph::{FrameTargets, RenderGraph, ResourceId},
+    BuiltChunk, ChunkCache, ChunkCacheStats, ChunkMesher, ChunkTransforms, ChunkVertex, CommonUniforms,
+    DrawIndexedArgs, DrawList, DrawRun, GeometryAlloc, GeometryArena, IndirectBuffer, ShaderHash, ShadowMap,
+    TextureLayer, TransformSlot, TransparentQuads,
+};
+
+/// max bytes of chunk meshes uploaded per frame
+const UPLOAD_BUDGET_BYTES: usize = 4 * 1024 * 1024;
+
+/// blended quads are sorted again when eye moves farther than this (in blocks)
+const RESORT_DISTANCE: f32 = 0.25;
+
+const MATERIAL_ALPHAS: [MaterialAlpha; 3] = [MaterialAlpha::NoAlpha, MaterialAlpha::OneBit, MaterialAlpha::FullAlpha];
 
 pub struct ChunkRenderer {
     cache: ChunkCache,
     uniform_bind_group: wgpu::BindGroup,
-    uniform_local_bind_group_layout: wgpu::BindGroupLayout,
+    transform_bind_group_layout: wgpu::BindGroupLayout,
     diffuse_bind_group_layout: wgpu::BindGroupLayout,
+    diffuse_bind_groups: HashMap<BlockMatIdx, wgpu::BindGroup>,
+    alpha_cutoff_buffers: HashMap<MaterialAlpha, wgpu::Buffer>,
+    arena: GeometryArena,
+    transforms: ChunkTransforms,
+    texture_array_bind_group_layout: wgpu::BindGroupLayout,
+    texture_array: Option<TextureArray>,
     render_pipeline_layout: wgpu::PipelineLayout,
-    render_pipelines: HashMap<ShaderHash, wgpu::RenderPipeline>,
+    texture_array_pipeline_layout: wgpu::PipelineLayout,
+    render_pipelines: HashMap<(ShaderHash, MaterialAlpha), wgpu::RenderPipeline>,
+    shadow_pipeline: wgpu::RenderPipeline,
     current_world_material_hash: Option<AssetHash>,
-    vertex_buffer: wgpu::Buffer,
+    occlusion: Arc<BlockOcclusion>,
+    emission: Arc<BlockEmission>,
+    greedy_meshing: bool,
+    mesher: ChunkMesher,
+    cache_budget: ChunkCacheBudget,
+    cache_stats: ChunkCacheStats,
+    eye: Vec3,
+    multi_draw: bool, // each draw run is 1 multi draw indirect. else each part is drawn
+    indirect: IndirectBuffer,
+    draws: DrawList<DrawKey>,      // parts drawn in chunk pass
+    shadow_draws: DrawList<usize>, // shadow casters of each arena page
+    draws_first: u32,              // first command of draws in indirect buffer
+    shadow_draws_first: u32,       // first command of shadow_draws in indirect buffer
+}
+
+/// states to draw chunk part. consecutive parts with same key are drawn in 1 run
+#[derive(Copy, Clone, PartialEq)]
+struct DrawKey {
+    pipeline: (ShaderHash, MaterialAlpha),
+    mat_idx: Option<BlockMatIdx>, // None if texture array is used
+    page: usize,
 }
 
 impl ChunkRenderer {
-    pub fn new(device: &wgpu::Device, common_uniforms: &CommonUniforms) -> Self {
+    pub fn new<F: FileSystem>(
+        device: &wgpu::Device,
+        common_uniforms: &CommonUniforms,
+        shadow_map: &ShadowMap,
+        asset_manager: &mut AssetManager<F>,
+    ) -> Self {
         let uniform_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: Some("view projection bind group layout for chunk"),
             entries: &[
@@ -38,26 +83,58 @@ impl ChunkRenderer {
                     },
                     count: None,
                 },
+                // sun and ambient light
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                // distance fog
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
             ],
         });
 
         let uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("uniform_bind_group"),
             layout: &uniform_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: common_uniforms.get_view_proj_buffer(),
-            }],
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: common_uniforms.get_view_proj_buffer(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: common_uniforms.get_light_buffer(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: common_uniforms.get_fog_buffer(),
+                },
+            ],
         });
 
-        // chunk마다 설정할 uniform값들
-        let uniform_local_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            label: Some("local bind group layout for chunk"),
+        // world transforms of every chunk, indexed by instance index
+        let transform_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("transform bind group layout for chunk"),
             entries: &[wgpu::BindGroupLayoutEntry {
                 binding: 0,
                 visibility: wgpu::ShaderStage::VERTEX,
                 ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
+                    ty: wgpu::BufferBindingType::Storage { read_only: true },
                     has_dynamic_offset: false,
                     min_binding_size: None,
                 },
@@ -65,66 +142,104 @@ impl ChunkRenderer {
             }],
         });
 
-        let diffuse_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            label: Some("diffuse texture bind group layout for chunk"),
-            entries: &[
-                // texture
-                wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStage::FRAGMENT,
-                    ty: wgpu::BindingType::Texture {
-                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                        view_dimension: wgpu::TextureViewDimension::D2,
-                        multisampled: false,
-                    },
-                    count: None,
-                },
-                // sampler
-                wgpu::BindGroupLayoutEntry {
-                    binding: 1,
-                    visibility: wgpu::ShaderStage::FRAGMENT,
-                    ty: wgpu::BindingType::Sampler {
-                        filtering: true,
-                        comparison: false,
-                    },
-                    count: None,
-                },
-            ],
-        });
+        let diffuse_bind_group_layout = create_diffuse_bind_group_layout(
+            device,
+            wgpu::TextureViewDimension::D2,
+            "diffuse texture bind group layout for chunk",
+        );
+        let texture_array_bind_group_layout = create_diffuse_bind_group_layout(
+            device,
+            wgpu::TextureViewDimension::D2Array,
+            "texture array bind group layout for chunk",
+        );
 
         let render_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("chunk render system pipeline layout"),
             bind_group_layouts: &[
                 &uniform_bind_group_layout,
-                &uniform_local_bind_group_layout,
+                &transform_bind_group_layout,
                 &diffuse_bind_group_layout,
+                shadow_map.sample_bind_group_layout(),
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let texture_array_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("chunk render system texture array pipeline layout"),
+            bind_group_layouts: &[
+                &uniform_bind_group_layout,
+                &transform_bind_group_layout,
+                &texture_array_bind_group_layout,
+                shadow_map.sample_bind_group_layout(),
             ],
             push_constant_ranges: &[],
         });
 
-        let vertex_buffer = create_chunk_vertexbuffer(&device);
         let render_pipelines = HashMap::new();
+        let shadow_pipeline = create_shadow_pipeline(device, shadow_map, &transform_bind_group_layout, asset_manager);
+        let transforms = ChunkTransforms::new(device, &transform_bind_group_layout);
+
+        // fragments with lower alpha are discarded. padded to 16 bytes for uniform
+        let alpha_cutoff_buffers = MATERIAL_ALPHAS
+            .iter()
+            .map(|alpha| {
+                let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("alpha cutoff buffer"),
+                    contents: bytemuck::cast_slice(&[alpha_cutoff(*alpha), 0.0, 0.0, 0.0]),
+                    usage: wgpu::BufferUsage::UNIFORM,
+                });
+                (*alpha, buffer)
+            })
+            .collect();
 
         Self {
             cache: ChunkCache::new(),
             uniform_bind_group,
-            uniform_local_bind_group_layout,
+            transform_bind_group_layout,
             diffuse_bind_group_layout,
+            diffuse_bind_groups: HashMap::new(),
+            alpha_cutoff_buffers,
+            arena: GeometryArena::new(),
+            transforms,
+            texture_array_bind_group_layout,
+            texture_array: None,
             render_pipeline_layout,
+            texture_array_pipeline_layout,
             render_pipelines,
+            shadow_pipeline,
             current_world_material_hash: None,
-            vertex_buffer,
+            occlusion: Arc::new(BlockOcclusion::default()),
+            emission: Arc::new(BlockEmission::default()),
+            greedy_meshing: false,
+            mesher: ChunkMesher::new(),
+            cache_budget: ChunkCacheBudget::default(),
+            cache_stats: ChunkCacheStats::default(),
+            eye: Vec3::ZERO,
+            multi_draw: device.features().contains(wgpu::Features::MULTI_DRAW_INDIRECT),
+            indirect: IndirectBuffer::new(device),
+            draws: DrawList::new(),
+            shadow_draws: DrawList::new(),
+            draws_first: 0,
+            shadow_draws_first: 0,
         }
     }
 
+    /// eye : camera position. blended quads are sorted back to front from it
+    /// draws of chunks in blueprint are built for this frame
+    #[allow(clippy::too_many_arguments)]
     #[profiling::function]
     pub fn prepare(
         &mut self,
         chunks_bps: &[SafeCloner<blueprint::Chunk>],
         world_material: &AssetHandle<WorldMaterialAsset>,
         block_size: f32,
+        greedy_meshing: bool,
+        eye: Vec3,
         device: &wgpu::Device,
-    ) -> Vec<ChunkId> {
+        queue: &wgpu::Queue,
+    ) {
+        self.eye = eye;
+
         // prepare render pipeline
         if self.current_world_material_hash != Some(world_material.asset_hash()) {
             self.current_world_material_hash = Some(world_material.asset_hash());
@@ -132,49 +247,269 @@ impl ChunkRenderer {
             // clear previous render pipeline if world material is changed
             self.clear_render_pipeline();
 
+            // cached chunks refer to bind groups of previous materials
+            self.diffuse_bind_groups.clear();
+            self.cache.clear_all();
+
             // register new materials in world material
             let asset = world_material.get_asset();
             for material_handle in asset.material_handles.values() {
-                self.register_render_pipeline(device, material_handle);
+                let material = material_handle.get_asset();
+                let (vs, fs) = (&material.vertex_shader, &material.frag_shader);
+                self.register_render_pipeline(device, vs, fs, material.alpha, false);
+            }
+            self.occlusion = Arc::new(asset.block_occlusion());
+            self.emission = Arc::new(asset.block_emission());
+
+            // every material in 1 texture array if world material has it
+            let texture_array = asset
+                .texture_array
+                .as_ref()
+                .and_then(|shaders| match asset.build_texture_array(device, queue) {
+                    Ok(texture) => Some((shaders, texture)),
+                    Err(err) => {
+                        log::error!("texture array build error. draw with each material. err: {}", err);
+                        None
+                    }
+                })
+                .map(|(shaders, texture)| {
+                    // materials with same alpha are merged into 1 mesh
+                    let layers = asset
+                        .texture_layers()
+                        .into_iter()
+                        .map(|(mat_idx, layer)| {
+                            let alpha = asset.material_handles[&mat_idx].get_asset().alpha;
+                            (
+                                mat_idx,
+                                TextureLayer {
+                                    layer,
+                                    group: alpha as u8,
+                                },
+                            )
+                        })
+                        .collect();
+
+                    let (vs, fs) = (&shaders.vertex_shader, &shaders.frag_shader);
+                    let bind_groups = MATERIAL_ALPHAS
+                        .iter()
+                        .map(|alpha| {
+                            self.register_render_pipeline(device, vs, fs, *alpha, true);
+                            let layout = &self.texture_array_bind_group_layout;
+                            let alpha_cutoff = &self.alpha_cutoff_buffers[alpha];
+                            (
+                                *alpha,
+                                create_diffuse_bind_group(device, layout, &texture, alpha_cutoff),
+                            )
+                        })
+                        .collect();
+
+                    TextureArray {
+                        layers: Arc::new(layers),
+                        shader_hash: ShaderHash::from_hash(vs.asset_hash(), fs.asset_hash()),
+                        bind_groups,
+                        _texture: texture,
+                    }
+                });
+            self.texture_array = texture_array;
+        }
+
+        // rebuild every chunk mesh if meshing is changed
+        if self.greedy_meshing != greedy_meshing {
+            self.greedy_meshing = greedy_meshing;
+            self.cache.clear_all();
+        }
+
+        // upload meshes built on workers within budget
+        // meshes of versions which are no longer in blueprint are dropped
+        let current_versions = chunks_bps
+            .iter()
+            .map(|chunk_bp| (chunk_bp.id, chunk_bp.version))
+            .collect::<HashMap<_, _>>();
+        for built in self.mesher.receive(UPLOAD_BUDGET_BYTES) {
+            if built.greedy_meshing != self.greedy_meshing
+                || built.texture_array != self.texture_array.is_some()
+                || current_versions.get(&built.id()) != Some(&built.version())
+            {
+                continue;
             }
+
+            let chunks = self.upload_built(&built, block_size, world_material, device, queue);
+            let byte_size = built.byte_size();
+            self.cache.add(built.chunk_bp, chunks, byte_size);
         }
 
+        self.transforms.update_fades(queue);
+
         // convert to chunk ids
+        // changed chunks are requested to workers, and drawn with previous mesh until built
         let mut chunks_for_render = Vec::new();
 
         for chunk_bp in chunks_bps {
-            // check cached
-            if self.cache.get(&chunk_bp.id).is_none() {
-                let chunks = Chunk::from_bp(
-                    &chunk_bp,
-                    block_size,
-                    device,
-                    &self.diffuse_bind_group_layout,
-                    &self.uniform_local_bind_group_layout,
-                    world_material,
+            if !self.cache.check(&chunk_bp.id, chunk_bp.version) {
+                let texture_layers = self.texture_array.as_ref().map(|array| &array.layers);
+                self.mesher.request(
+                    chunk_bp,
+                    &self.occlusion,
+                    &self.emission,
+                    greedy_meshing,
+                    texture_layers,
                 );
+            }
 
-                let cloned_chunk_bp = SafeCloner::clone_read(chunk_bp);
-                self.cache.add(chunk_bp.id, cloned_chunk_bp, chunks);
+            if self.cache.get(&chunk_bp.id).is_none() {
+                continue;
             }
 
-            // add to used chunk for prevent remove when cache.clear_unused() called
+            // add to used chunk for prevent eviction in this frame
             self.cache.set_used(chunk_bp.id);
 
             chunks_for_render.push(chunk_bp.id);
         }
 
-        chunks_for_render
+        // sort blended quads of visible chunks again if eye moved
+        for chunk_id in &chunks_for_render {
+            for chunk in self.cache.get_mut(chunk_id).unwrap() {
+                if let Some(sort) = &mut chunk.sort {
+                    if let Some(indices) = sort.resort(eye) {
+                        self.arena.write_indices(&chunk.geometry, &indices, queue);
+                    }
+                }
+            }
+        }
+
+        self.build_draws(&chunks_for_render, device, queue);
     }
 
-    fn register_render_pipeline(&mut self, device: &wgpu::Device, material_handle: &AssetHandle<MaterialAsset>) {
-        let asset = material_handle.get_asset();
-        let vs_handle = &asset.vertex_shader;
-        let fs_handle = &asset.frag_shader;
+    /// opaque and alpha tested parts of chunks first, then blended parts from back to front
+    /// shadow casters are opaque and alpha tested parts
+    fn build_draws(&mut self, chunks_ids: &[ChunkId], device: &wgpu::Device, queue: &wgpu::Queue) {
+        let (mut blended, mut opaque): (Vec<_>, Vec<_>) = chunks_ids
+            .iter()
+            .flat_map(|chunk_id| self.cache.get(chunk_id).unwrap())
+            .filter(|chunk| !chunk.geometry.indices.is_empty())
+            .partition(|chunk| chunk.alpha == MaterialAlpha::FullAlpha);
+
+        // opaque parts are sorted by alpha, shader, material and arena page for fewer state changes
+        opaque.sort_unstable_by(|a, b| {
+            a.alpha
+                .cmp(&b.alpha)
+                .then(a.shader_hash.partial_cmp(&b.shader_hash).unwrap())
+                .then(a.mat_idx.cmp(&b.mat_idx))
+                .then(a.geometry.page.cmp(&b.geometry.page))
+        });
+
+        // farthest chunk first. quads in chunk are already sorted
+        blended.sort_by(|a, b| {
+            let dist_a = a.center.distance_squared(self.eye);
+            let dist_b = b.center.distance_squared(self.eye);
+            dist_b.partial_cmp(&dist_a).unwrap()
+        });
+
+        let texture_array = self.texture_array.is_some();
+        self.draws.clear();
+        for chunk in opaque.iter().chain(&blended) {
+            let key = DrawKey {
+                pipeline: (chunk.shader_hash, chunk.alpha),
+                mat_idx: if texture_array { None } else { Some(chunk.mat_idx) },
+                page: chunk.geometry.page,
+            };
+            self.draws.push(key, draw_args(chunk));
+        }
+
+        let mut casters = opaque;
+        casters.sort_by_key(|chunk| chunk.geometry.page);
+        self.shadow_draws.clear();
+        for chunk in casters {
+            self.shadow_draws.push(chunk.geometry.page, draw_args(chunk));
+        }
+
+        if self.multi_draw {
+            let lists = [self.draws.commands(), self.shadow_draws.commands()];
+            let firsts = self.indirect.write(&lists, device, queue);
+            self.draws_first = firsts[0];
+            self.shadow_draws_first = firsts[1];
+        }
+    }
+
+    /// gpu chunks of meshes built on worker, one for each material (or merged materials with texture array)
+    /// meshes are suballocated from arena, and every part shares 1 transform slot
+    fn upload_built(
+        &mut self,
+        built: &BuiltChunk,
+        block_size: f32,
+        world_material: &AssetHandle<WorldMaterialAsset>,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Vec<Chunk> {
+        let translate = Mat4::from_translation(built.chunk_bp.pos);
+        let scale = Mat4::from_scale(Vec3::new(block_size, block_size, block_size));
+        let world_transform = translate * scale;
+        let layout = &self.transform_bind_group_layout;
+
+        // chunk without previous mesh is new in view, so fades in instead of popping
+        let fade_in = self.cache.get(&built.id()).is_none();
+        let transform = Arc::new(self.transforms.alloc(world_transform, fade_in, device, queue, layout));
+
+        let world_mat = world_material.get_asset();
+        let mut chunks = Vec::new();
+
+        for (mat_idx, mesh) in &built.meshes {
+            let material_handle = world_mat.material_handles.get(mat_idx).unwrap();
+            let material = material_handle.get_asset();
+
+            // texture array has every material. mesh of merged materials is keyed by one of them
+            let shader_hash = match &self.texture_array {
+                Some(array) => array.shader_hash,
+                None => {
+                    let diffuse_bind_group_layout = &self.diffuse_bind_group_layout;
+                    let alpha_cutoff = &self.alpha_cutoff_buffers[&material.alpha];
+                    self.diffuse_bind_groups.entry(*mat_idx).or_insert_with(|| {
+                        let diffuse_asset = material.diffuse_tex.get_asset();
+                        let diffuse = diffuse_asset.texture.as_ref().unwrap();
+                        create_diffuse_bind_group(device, diffuse_bind_group_layout, diffuse, alpha_cutoff)
+                    });
+                    ShaderHash::from_material(material_handle)
+                }
+            };
+
+            // blended quads are sorted when drawn first
+            let sort = match material.alpha {
+                MaterialAlpha::FullAlpha => Some(TransparentSort {
+                    quads: TransparentQuads::from_mesh(mesh),
+                    origin: built.chunk_bp.pos,
+                    scale: block_size,
+                    sorted_eye: None,
+                }),
+                _ => None,
+            };
+
+            chunks.push(Chunk {
+                mat_idx: *mat_idx,
+                shader_hash,
+                alpha: material.alpha,
+                center: built.chunk_bp.aabb.center(),
+                geometry: self.arena.alloc(mesh, device, queue),
+                transform: transform.clone(),
+                sort,
+            });
+        }
+
+        chunks
+    }
 
+    /// texture_array : pipeline samples texture array instead of diffuse texture of material
+    /// full alpha is blended without writing depth. one bit is discarded in shader by alpha cutoff
+    fn register_render_pipeline(
+        &mut self,
+        device: &wgpu::Device,
+        vs_handle: &AssetHandle<ShaderAsset>,
+        fs_handle: &AssetHandle<ShaderAsset>,
+        alpha: MaterialAlpha,
+        texture_array: bool,
+    ) {
         let shader_hash = ShaderHash::from_hash(vs_handle.asset_hash(), fs_handle.asset_hash());
 
-        let pipeline = self.render_pipelines.get(&shader_hash);
+        let pipeline = self.render_pipelines.get(&(shader_hash, alpha));
         if pipeline.is_some() {
             return;
         }
@@ -185,9 +520,22 @@ impl ChunkRenderer {
         let vs_module = vs_asset.module.as_ref().unwrap();
         let fs_module = fs_asset.module.as_ref().unwrap();
 
+        let layout = if texture_array {
+            &self.texture_array_pipeline_layout
+        } else {
+            &self.render_pipeline_layout
+        };
+
+        let blended = alpha == MaterialAlpha::FullAlpha;
+        let blend = if blended {
+            Some(wgpu::BlendState::ALPHA_BLENDING)
+        } else {
+            None
+        };
+
         let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: Some("chunk render system render pipeline"),
-            layout: Some(&self.render_pipeline_layout),
+            layout: Some(layout),
             vertex: wgpu::VertexState {
                 module: &vs_module,
                 entry_point: "main",
@@ -204,7 +552,7 @@ impl ChunkRenderer {
             },
             depth_stencil: Some(wgpu::DepthStencilState {
                 format: DEPTH_FORMAT,
-                depth_write_enabled: true,
+                depth_write_enabled: !blended,
                 depth_compare: wgpu::CompareFunction::Less,
                 stencil: wgpu::StencilState::default(),
                 bias: wgpu::DepthBiasState {
@@ -222,195 +570,281 @@ impl ChunkRenderer {
                 module: &fs_module,
                 entry_point: "main",
                 targets: &[wgpu::ColorTargetState {
-                    format: wgpu::TextureFormat::Bgra8UnormSrgb,
-                    blend: None,
-                    // alpha_blend: wgpu::BlendState::REPLACE,
-                    // color_blend: wgpu::BlendState::REPLACE,
+                    format: HDR_FORMAT,
+                    blend,
                     write_mask: wgpu::ColorWrite::ALL,
                 }],
             }),
         });
 
-        self.render_pipelines.insert(shader_hash, render_pipeline);
+        self.render_pipelines.insert((shader_hash, alpha), render_pipeline);
     }
 
     fn clear_render_pipeline(&mut self) {
         self.render_pipelines.clear();
     }
 
+    /// draws built in prepare. states are set only when changed
     #[profiling::function]
-    pub fn render<'a>(&'a self, chunks_ids: &[ChunkId], render_pass: &mut wgpu::RenderPass<'a>) {
-        let mut prev_shaderhash: Option<ShaderHash> = None;
-
-        for chunk_id in chunks_ids {
-            let chunks = self.cache.get(chunk_id).unwrap();
-            for chunk in chunks {
-                if prev_shaderhash != Some(chunk.shader_hash) {
-                    prev_shaderhash = Some(chunk.shader_hash);
-
-                    let render_pipeline = self.render_pipelines.get(&chunk.shader_hash).unwrap();
-                    render_pass.set_pipeline(render_pipeline);
-                    render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
-                    render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-                }
+    pub fn render<'a>(&'a self, shadow_map: &'a ShadowMap, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+        render_pass.set_bind_group(1, self.transforms.bind_group(), &[]);
+        render_pass.set_bind_group(3, shadow_map.sample_bind_group(), &[]);
+
+        let mut prev_pipeline: Option<(ShaderHash, MaterialAlpha)> = None;
+        let mut prev_bind_group: Option<&wgpu::BindGroup> = None;
+        let mut prev_page: Option<usize> = None;
+
+        for run in self.draws.runs() {
+            let key = &run.key;
+            if prev_pipeline != Some(key.pipeline) {
+                prev_pipeline = Some(key.pipeline);
+
+                let render_pipeline = self.render_pipelines.get(&key.pipeline).unwrap();
+                render_pass.set_pipeline(render_pipeline);
+            }
+
+            // texture array is shared by materials with same alpha
+            let bind_group = match (&self.texture_array, key.mat_idx) {
+                (Some(array), _) => &array.bind_groups[&key.pipeline.1],
+                (None, Some(mat_idx)) => &self.diffuse_bind_groups[&mat_idx],
+                (None, None) => unreachable!("draws are built without texture array"),
+            };
+            if !prev_bind_group.map_or(false, |prev| std::ptr::eq(prev, bind_group)) {
+                prev_bind_group = Some(bind_group);
+                render_pass.set_bind_group(2, bind_group, &[]);
+            }
 
-                render_pass.set_index_buffer(chunk.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
-                render_pass.set_bind_group(1, &chunk.local_uniform_bind_group, &[]);
-                render_pass.set_bind_group(2, &chunk.diffuse_bind_group, &[]);
-                render_pass.draw_indexed(0..chunk.num_indices, 0, 0..1);
+            if prev_page != Some(key.page) {
+                prev_page = Some(key.page);
+                self.set_page(key.page, render_pass);
             }
+
+            self.draw_run(&self.draws, self.draws_first, run, render_pass);
         }
     }
 
-    pub fn clear(&mut self) {
-        self.cache.clear_unused();
+    /// depth of opaque and alpha tested parts seen from sun into cascade of shadow map
+    /// alpha tested parts cast shadow of whole quads
+    #[profiling::function]
+    pub fn render_shadow<'a>(
+        &'a self,
+        shadow_map: &'a ShadowMap,
+        cascade: usize,
+        render_pass: &mut wgpu::RenderPass<'a>,
+    ) {
+        render_pass.set_pipeline(&self.shadow_pipeline);
+        render_pass.set_bind_group(0, shadow_map.cascade_bind_group(cascade), &[]);
+        render_pass.set_bind_group(1, self.transforms.bind_group(), &[]);
+
+        for run in self.shadow_draws.runs() {
+            self.set_page(run.key, render_pass);
+            self.draw_run(&self.shadow_draws, self.shadow_draws_first, run, render_pass);
+        }
     }
-}
 
-#[repr(C)]
-#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
-pub struct ChunkVertex {
-    pub position: [f32; 3],
-    pub color: [f32; 3],
-    pub uv: [f32; 2],
-}
+    fn set_page<'a>(&'a self, page: usize, render_pass: &mut wgpu::RenderPass<'a>) {
+        let (vertex_buffer, index_buffer) = self.arena.page_buffers(page);
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+    }
+
+    /// 1 multi draw indirect if device supports it, else each draw of run
+    /// first : first command of list in indirect buffer
+    fn draw_run<'a, K>(
+        &'a self,
+        list: &DrawList<K>,
+        first: u32,
+        run: &DrawRun<K>,
+        render_pass: &mut wgpu::RenderPass<'a>,
+    ) {
+        if self.multi_draw {
+            self.indirect.draw_run(first, run, render_pass);
+            return;
+        }
 
-#[rustfmt::skip]
-pub const BLOCK_VERTICES: &[ChunkVertex] = &[
-    // +y
-    ChunkVertex { position: [0.0, 1.0, 1.0], color: [0., 1., 1.], uv: [0.0, 0.0] },
-    ChunkVertex { position: [1.0, 1.0, 1.0], color: [0., 1., 1.], uv: [1.0, 0.0] },
-    ChunkVertex { position: [0.0, 1.0, 0.0], color: [0., 1., 1.], uv: [0.0, 1.0] },
-    ChunkVertex { position: [1.0, 1.0, 0.0], color: [0., 1., 1.], uv: [1.0, 1.0] },
-
-    // -y
-    ChunkVertex { position: [0.0, 0.0, 0.0], color: [1., 1., 1.], uv: [0.0, 0.0] },
-    ChunkVertex { position: [1.0, 0.0, 0.0], color: [1., 1., 1.], uv: [1.0, 0.0] },
-    ChunkVertex { position: [0.0, 0.0, 1.0], color: [1., 1., 1.], uv: [0.0, 1.0] },
-    ChunkVertex { position: [1.0, 0.0, 1.0], color: [1., 1., 1.], uv: [1.0, 1.0] },
-
-    // +x
-    ChunkVertex { position: [1.0, 0.0, 0.0], color: [1., 1., 1.], uv: [0.0, 1.0] },
-    ChunkVertex { position: [1.0, 1.0, 0.0], color: [1., 1., 1.], uv: [0.0, 0.0] },
-    ChunkVertex { position: [1.0, 0.0, 1.0], color: [1., 1., 1.], uv: [1.0, 1.0] },
-    ChunkVertex { position: [1.0, 1.0, 1.0], color: [1., 1., 1.], uv: [1.0, 0.0] },
-    
-    // -x
-    ChunkVertex { position: [0.0, 1.0, 0.0], color: [1., 1., 1.], uv: [0.0, 0.0] },
-    ChunkVertex { position: [0.0, 0.0, 0.0], color: [1., 1., 1.], uv: [1.0, 0.0] },
-    ChunkVertex { position: [0.0, 1.0, 1.0], color: [1., 1., 1.], uv: [0.0, 1.0] },
-    ChunkVertex { position: [0.0, 0.0, 1.0], color: [1., 1., 1.], uv: [1.0, 1.0] },
-
-    // +z
-    ChunkVertex { position: [0.0, 1.0, 1.0], color: [1., 1., 1.], uv: [0.0, 0.0] },
-    ChunkVertex { position: [0.0, 0.0, 1.0], color: [1., 1., 1.], uv: [1.0, 0.0] },
-    ChunkVertex { position: [1.0, 1.0, 1.0], color: [1., 1., 1.], uv: [0.0, 1.0] },
-    ChunkVertex { position: [1.0, 0.0, 1.0], color: [1., 1., 1.], uv: [1.0, 1.0] },
-
-    // -z
-    ChunkVertex { position: [0.0, 0.0, 0.0], color: [1., 0., 1.], uv: [0.0, 0.0] },
-    ChunkVertex { position: [0.0, 1.0, 0.0], color: [1., 0., 1.], uv: [1.0, 0.0] },
-    ChunkVertex { position: [1.0, 0.0, 0.0], color: [1., 0., 1.], uv: [0.0, 1.0] },
-    ChunkVertex { position: [1.0, 1.0, 0.0], color: [1., 0., 1.], uv: [1.0, 1.0] },
-];
-
-#[rustfmt::skip]
-pub const CHUNK_INDICES: &[u32] = &[
-    // +y
-    0, 1, 2, 
-    2, 1, 3, 
-    
-    // -y
-    4, 5, 6, 
-    6, 5, 7, 
-    
-    // +x
-    8, 9, 10, 
-    10, 9, 11, 
-    
-    // -x
-    12, 13, 14, 
-    14, 13, 15, 
-    
-    // +z
-    16, 17, 18, 
-    18, 17, 19, 
-    
-    // -z
-    20, 21, 22, 
-    22, 21, 23,
-];
-
-fn block_indices_in_dir(vis: BitFlags<Dir>) -> Vec<u32> {
-    let mut indices = Vec::new();
-
-    for dir in vis.iter() {
-        match dir {
-            Dir::XPos => indices.extend(&[8, 9, 10, 10, 9, 11]),
-            Dir::XNeg => indices.extend(&[12, 13, 14, 14, 13, 15]),
-            Dir::YPos => indices.extend(&[0, 1, 2, 2, 1, 3]),
-            Dir::YNeg => indices.extend(&[4, 5, 6, 6, 5, 7]),
-            Dir::ZPos => indices.extend(&[16, 17, 18, 18, 17, 19]),
-            Dir::ZNeg => indices.extend(&[20, 21, 22, 22, 21, 23]),
+        let commands = &list.commands()[run.commands.start as usize..run.commands.end as usize];
+        for args in commands {
+            render_pass.draw_indexed(args.indices(), args.base_vertex, args.instances());
         }
     }
-    indices
-}
 
-pub fn create_chunk_vertexbuffer(device: &wgpu::Device) -> wgpu::Buffer {
-    let mut v = Vec::new() as Vec<ChunkVertex>;
-    v.reserve(BLOCK_VERTICES.len() * TOTAL_BLOCK_COUNTS_IN_CHUNK);
-
-    for z in 0..BLOCK_COUNT_IN_CHUNKSIDE {
-        for y in 0..BLOCK_COUNT_IN_CHUNKSIDE {
-            for x in 0..BLOCK_COUNT_IN_CHUNKSIDE {
-                let offset = Vec3::new(x as f32, y as f32, z as f32);
-                v.extend(BLOCK_VERTICES.iter().map(|v| {
-                    let new_position = offset + Vec3::new(v.position[0], v.position[1], v.position[2]);
-                    ChunkVertex {
-                        position: *new_position.as_ref(),
-                        ..*v
-                    }
-                }));
-            }
+    /// off-screen chunks are kept within budget
+    pub fn set_cache_budget(&mut self, budget: ChunkCacheBudget) {
+        self.cache_budget = budget;
+    }
+
+    /// cache stats of last frame
+    pub fn cache_stats(&self) -> ChunkCacheStats {
+        self.cache_stats
+    }
+
+    /// depth from sun into each shadow layer, then chunks lit and shadowed into frame
+    pub(crate) fn add_passes<'r>(
+        &'r self,
+        shadow_map: &'r ShadowMap,
+        shadow_layers: &[ResourceId],
+        targets: FrameTargets,
+        graph: &mut RenderGraph<'r>,
+    ) {
+        for (cascade, layer) in shadow_layers.iter().enumerate() {
+            graph
+                .add_pass("chunk shadow pass")
+                .depth(*layer, wgpu::LoadOp::Clear(1.0))
+                .record(move |pass, _| self.render_shadow(shadow_map, cascade, pass));
         }
+
+        let mut builder = graph
+            .add_pass("chunk pass")
+            .color(targets.color, wgpu::LoadOp::Load)
+            .depth(targets.depth, wgpu::LoadOp::Load);
+        for layer in shadow_layers {
+            builder = builder.read(*layer);
+        }
+        builder.record(move |pass, _| self.render(shadow_map, pass));
     }
 
-    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-        label: Some("chunk vetex buffer"),
-        contents: bytemuck::cast_slice(&v),
-        usage: wgpu::BufferUsage::VERTEX,
+    pub fn clear(&mut self) {
+        self.cache.evict(&self.cache_budget);
+        self.cache_stats = self.cache.take_stats();
+    }
+}
+
+fn create_diffuse_bind_group_layout(
+    device: &wgpu::Device,
+    view_dimension: wgpu::TextureViewDimension,
+    label: &str,
+) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some(label),
+        entries: &[
+            // texture
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStage::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            // sampler
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStage::FRAGMENT,
+                ty: wgpu::BindingType::Sampler {
+                    filtering: true,
+                    comparison: false,
+                },
+                count: None,
+            },
+            // alpha cutoff
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStage::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+        ],
     })
 }
 
-/// #Inputs
-/// block_indices: (idx, mat_idx)
-/// #Returns
-///  ().0 : index buffer
-///  ().1 : index count
-pub fn create_chunk_indexbuffer(
-    block_indices: &[BlockIdx],
+fn create_diffuse_bind_group(
     device: &wgpu::Device,
-    vis: &[BitFlags<Dir>],
-) -> (wgpu::Buffer, u32) {
-    let mut v = Vec::<u32>::new();
-    v.reserve(block_indices.len() * CHUNK_INDICES.len());
-    for &block_idx in block_indices {
-        let indices = block_indices_in_dir(vis[block_idx as usize]);
-        v.extend(
-            indices
-                .iter()
-                .map(|idx| *idx + (block_idx as usize * BLOCK_VERTICES.len()) as u32),
-        );
+    layout: &wgpu::BindGroupLayout,
+    diffuse: &Texture,
+    alpha_cutoff: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("diffuse_bind_group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&diffuse.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&diffuse.sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: alpha_cutoff,
+                    offset: 0,
+                    size: None,
+                }),
+            },
+        ],
+    })
+}
+
+/// fragments with lower alpha are discarded
+fn alpha_cutoff(alpha: MaterialAlpha) -> f32 {
+    match alpha {
+        MaterialAlpha::NoAlpha | MaterialAlpha::FullAlpha => 0.0,
+        MaterialAlpha::OneBit => 0.5,
     }
+}
 
-    let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-        label: Some("chunk index buffer"),
-        contents: bytemuck::cast_slice(&v),
-        usage: wgpu::BufferUsage::INDEX,
+/// depth only pipeline drawing chunks from sun
+/// depth is biased to avoid self shadowing of faces
+fn create_shadow_pipeline<F: FileSystem>(
+    device: &wgpu::Device,
+    shadow_map: &ShadowMap,
+    transform_bind_group_layout: &wgpu::BindGroupLayout,
+    asset_manager: &mut AssetManager<F>,
+) -> wgpu::RenderPipeline {
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("chunk shadow pipeline layout"),
+        bind_group_layouts: &[shadow_map.cascade_bind_group_layout(), transform_bind_group_layout],
+        push_constant_ranges: &[],
     });
 
-    (buffer, v.len() as u32)
+    let vs_handle = asset_manager.get::<ShaderAsset>(&"assets/shaders/shadow_shader.vert.spv".into());
+    let vs_asset = vs_handle.get_asset();
+    let vs_module = vs_asset.module.as_ref().unwrap();
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("chunk shadow pipeline"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &vs_module,
+            entry_point: "main",
+            buffers: &[create_chunk_vertexbuffer_desc()],
+        },
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Cw,
+            cull_mode: None,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            clamp_depth: false,
+            conservative: false,
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::LessEqual,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState {
+                constant: 2,
+                slope_scale: 2.0,
+                clamp: 0.0,
+            },
+        }),
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        fragment: None,
+    })
 }
 
 pub fn create_chunk_vertexbuffer_desc<'a>() -> wgpu::VertexBufferLayout<'a> {
@@ -433,108 +867,84 @@ pub fn create_chunk_vertexbuffer_desc<'a>() -> wgpu::VertexBufferLayout<'a> {
                 shader_location: 2,
                 format: wgpu::VertexFormat::Float32x2,
             },
+            wgpu::VertexAttribute {
+                offset: (std::mem::size_of::<[f32; 3]>() * 2 + std::mem::size_of::<[f32; 2]>()) as wgpu::BufferAddress,
+                shader_location: 3,
+                format: wgpu::VertexFormat::Uint32,
+            },
+            wgpu::VertexAttribute {
+                offset: (std::mem::size_of::<[f32; 3]>() * 2
+                    + std::mem::size_of::<[f32; 2]>()
+                    + std::mem::size_of::<u32>()) as wgpu::BufferAddress,
+                shader_location: 4,
+                format: wgpu::VertexFormat::Float32x3,
+            },
+            wgpu::VertexAttribute {
+                offset: (std::mem::size_of::<[f32; 3]>() * 3
+                    + std::mem::size_of::<[f32; 2]>()
+                    + std::mem::size_of::<u32>()) as wgpu::BufferAddress,
+                shader_location: 5,
+                format: wgpu::VertexFormat::Float32,
+            },
+            wgpu::VertexAttribute {
+                offset: (std::mem::size_of::<[f32; 3]>() * 3
+                    + std::mem::size_of::<[f32; 2]>()
+                    + std::mem::size_of::<u32>()
+                    + std::mem::size_of::<f32>()) as wgpu::BufferAddress,
+                shader_location: 6,
+                format: wgpu::VertexFormat::Float32x2,
+            },
         ],
     }
 }
 
-pub(crate) struct Chunk {
-    pub shader_hash: ShaderHash,
-    pub diffuse_bind_group: wgpu::BindGroup,
-    pub local_uniform_bind_group: wgpu::BindGroup,
-    pub index_buffer: wgpu::Buffer,
-    pub num_indices: u32,
+/// every material of world material in 1 texture array
+struct TextureArray {
+    layers: Arc<HashMap<BlockMatIdx, TextureLayer>>,
+    shader_hash: ShaderHash,
+    bind_groups: HashMap<MaterialAlpha, wgpu::BindGroup>, // alpha cutoff differs
+    _texture: Texture,
 }
 
-impl Chunk {
-    pub fn from_bp(
-        bp: &blueprint::Chunk,
-        block_size: f32,
-        device: &wgpu::Device,
-        diffuse_bind_group_layout: &wgpu::BindGroupLayout,
-        uniform_local_bind_group_layout: &wgpu::BindGroupLayout,
-        world_material: &AssetHandle<WorldMaterialAsset>,
-    ) -> Vec<Self> {
-        let mut chunks = Vec::new();
-
-        // sort by material id
-        // Vec : block location, material index
-        let mat_blocks = {
-            let mut mat_blocks: HashMap<BlockMatIdx, Vec<BlockIdx>> = HashMap::new();
-            for (idx, mat_idx) in bp.blocks.iter().enumerate() {
-                if *mat_idx == 0 {
-                    continue;
-                }
+/// blended quads of chunk part sorted back to front
+struct TransparentSort {
+    quads: TransparentQuads,
+    origin: Vec3, // chunk position
+    scale: f32,   // block size
+    sorted_eye: Option<Vec3>,
+}
 
-                if let Some(blocks) = mat_blocks.get_mut(mat_idx) {
-                    blocks.push(idx as BlockIdx);
-                } else {
-                    let blocks = vec![idx as BlockIdx];
-                    mat_blocks.insert(*mat_idx, blocks);
-                }
+impl TransparentSort {
+    /// indices sorted from eye if it moved enough since last sort
+    fn resort(&mut self, eye: Vec3) -> Option<Vec<u32>> {
+        let local_eye = (eye - self.origin) / self.scale;
+        if let Some(sorted_eye) = self.sorted_eye {
+            if sorted_eye.distance_squared(local_eye) < RESORT_DISTANCE * RESORT_DISTANCE {
+                return None;
             }
-            mat_blocks
-        };
-
-        let world_mat = world_material.get_asset();
-
-        for (k, v) in mat_blocks {
-            let material_handle = world_mat.material_handles.get(&k).unwrap();
-            let material = material_handle.get_asset();
-
-            let diffuse_asset = material.diffuse_tex.get_asset();
-            let diffuse = diffuse_asset.texture.as_ref().unwrap();
-
-            let diffuse_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-                label: Some("diffuse_bind_group"),
-                layout: diffuse_bind_group_layout,
-                entries: &[
-                    wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: wgpu::BindingResource::TextureView(&diffuse.view),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 1,
-                        resource: wgpu::BindingResource::Sampler(&diffuse.sampler),
-                    },
-                ],
-            });
-
-            // local uniform buffer
-            let translate = Mat4::from_translation(bp.pos);
-            let scale = Mat4::from_scale(Vec3::new(block_size, block_size, block_size));
-            let world_transform = translate * scale;
-
-            let local_uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("view_proj buffer"),
-                contents: bytemuck::cast_slice(world_transform.as_ref()),
-                usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
-            });
-
-            let local_uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-                label: Some("local_uniform_bind_group"),
-                layout: uniform_local_bind_group_layout,
-                entries: &[wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-                        buffer: &local_uniform_buffer,
-                        offset: 0,
-                        size: None,
-                    }),
-                }],
-            });
-
-            let (index_buffer, num_indices) = create_chunk_indexbuffer(&v, device, &bp.vis);
-            let shader_hash = ShaderHash::from_material(material_handle);
-            let chunk = Self {
-                shader_hash,
-                diffuse_bind_group,
-                local_uniform_bind_group,
-                index_buffer,
-                num_indices,
-            };
-            chunks.push(chunk);
         }
 
-        chunks
+        self.sorted_eye = Some(local_eye);
+        Some(self.quads.sorted_indices(local_eye))
     }
 }
+
+/// 1 instance of part at its transform slot
+fn draw_args(chunk: &Chunk) -> DrawIndexedArgs {
+    DrawIndexedArgs::new(
+        chunk.geometry.indices.clone(),
+        chunk.geometry.vertices.start as i32,
+        chunk.transform.index,
+    )
+}
+
+/// part of chunk with 1 material
+pub(crate) struct Chunk {
+    pub mat_idx: BlockMatIdx,
+    pub shader_hash: ShaderHash,
+    pub alpha: MaterialAlpha, // full alpha is drawn after others
+    pub center: Vec3,         // world position for sorting blended parts
+    pub geometry: GeometryAlloc,
+    pub transform: Arc<TransformSlot>, // shared by parts of same chunk
+    sort: Option<TransparentSort>,
+}
@@ -1,106 +1,299 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 
 use voxrs_types::SafeCloner;
 
-use crate::blueprint::{self, ChunkId};
+use crate::blueprint::{self, ChunkCacheBudget, ChunkId, ChunkVersion};
 
 use super::chunk::Chunk;
 
 /// should store with safe cloner for being noticed that safe cloner is changed
-type CachedValue = (Vec<Chunk>, SafeCloner<blueprint::Chunk>);
+struct CachedValue {
+    chunks: Vec<Chunk>,
+    chunk_bp: SafeCloner<blueprint::Chunk>,
+    byte_size: usize,
+    last_used: u64, // frame
+}
+
+/// counts of cache usage since last take_stats, and resident size
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct ChunkCacheStats {
+    pub hits: usize,           // mesh of current version is cached
+    pub misses: usize,         // mesh is not cached, or cached mesh is older version
+    pub remeshes: usize,       // cached mesh is replaced with newer version
+    pub evicted: usize,        // least recently used meshes removed over budget
+    pub resident_count: usize, // cached chunks
+    pub resident_bytes: usize, // gpu buffer bytes of cached chunks
+}
 
+/// gpu chunks by chunk id
+/// chunks out of view are kept until budget is exceeded, then least recently used ones are evicted
 pub(crate) struct ChunkCache {
     cached: HashMap<ChunkId, CachedValue>,
-    used: HashSet<ChunkId>,
+    frame: u64,
+    resident_bytes: usize,
+    stats: ChunkCacheStats,
 }
 
 impl ChunkCache {
     pub fn new() -> Self {
         Self {
             cached: HashMap::new(),
-            used: HashSet::new(),
+            frame: 0,
+            resident_bytes: 0,
+            stats: ChunkCacheStats::default(),
         }
     }
 
-    /// add val vec with key to cache
+    /// add chunks with id of chunk_bp to cache
+    /// byte_size : gpu buffer bytes of chunks
     /// return : replaced flag
-    /// if new key, insert value withkey and return false
-    /// if key exists, exchange val and return true
-    pub fn add(&mut self, key: ChunkId, chunk_bp: SafeCloner<blueprint::Chunk>, chunks: Vec<Chunk>) -> bool {
-        let prev = self.cached.insert(key, (chunks, chunk_bp));
-        self.set_used(key);
-        matches!(prev, Some(_))
+    /// if new id, insert chunks and return false
+    /// if id exists, replace chunks of previous version and return true
+    pub fn add(&mut self, chunk_bp: SafeCloner<blueprint::Chunk>, chunks: Vec<Chunk>, byte_size: usize) -> bool {
+        let key = chunk_bp.id;
+        let value = CachedValue {
+            chunks,
+            chunk_bp,
+            byte_size,
+            last_used: self.frame,
+        };
+
+        self.resident_bytes += byte_size;
+        let prev = self.cached.insert(key, value);
+
+        let replaced = match prev {
+            Some(prev) => {
+                self.resident_bytes -= prev.byte_size;
+                true
+            }
+            None => false,
+        };
+        if replaced {
+            self.stats.remeshes += 1;
+        }
+        replaced
     }
 
-    /// refresh used and return alreay used
+    /// version of cached chunk
+    pub fn get_version(&self, key: &ChunkId) -> Option<ChunkVersion> {
+        self.cached.get(key).map(|value| value.chunk_bp.version)
+    }
+
+    /// whether cached chunk is same version. counted as hit or miss
+    pub fn check(&mut self, key: &ChunkId, version: ChunkVersion) -> bool {
+        let is_current = self.get_version(key) == Some(version);
+        if is_current {
+            self.stats.hits += 1;
+        } else {
+            self.stats.misses += 1;
+        }
+        is_current
+    }
+
+    /// refresh used frame and return already used in this frame
     ///
     /// return true if already used
-    /// retgurn false if new key
+    /// return false if first use in this frame, or not cached
     pub fn set_used(&mut self, key: ChunkId) -> bool {
-        !self.used.insert(key)
+        match self.cached.get_mut(&key) {
+            Some(value) => {
+                let already_used = value.last_used == self.frame;
+                value.last_used = self.frame;
+                already_used
+            }
+            None => false,
+        }
     }
 
-    // pub fn try_iter(&self, key: &K) -> Option<impl Iterator<Item = &V>> {
-    //     self.set_used(*key);
+    /// cached chunks. may be older version than blueprint
+    pub fn get(&self, key: &ChunkId) -> Option<&Vec<Chunk>> {
+        self.cached.get(key).map(|value| &value.chunks)
+    }
 
-    //     let value = self.cached.get(key);
-    //     match value {
-    //         Some(v) => Some(v.iter()),
-    //         None => None,
-    //     }
-    // }
+    pub fn get_mut(&mut self, key: &ChunkId) -> Option<&mut Vec<Chunk>> {
+        self.cached.get_mut(key).map(|value| &mut value.chunks)
+    }
 
-    pub fn get(&self, key: &ChunkId) -> Option<&Vec<Chunk>> {
-        self.cached.get(key).map(|(vec, _)| vec)
+    pub fn clear_all(&mut self) {
+        self.cached.clear();
+        self.resident_bytes = 0;
     }
 
-    pub fn clear_unused(&mut self) -> usize {
-        let remove_count;
-        {
-            let cached: HashSet<_> = self.cached.iter().map(|(k, _)| *k).collect();
-            let removed: Vec<_> = cached.difference(&self.used).collect();
-            remove_count = removed.len();
+    /// end of frame
+    /// evict least recently used chunks until cache fits in budget. chunks used in this frame are kept
+    /// return evicted count
+    pub fn evict(&mut self, budget: &ChunkCacheBudget) -> usize {
+        let mut remove_count = 0;
 
-            for &k in &removed {
-                self.cached.remove(k);
+        if self.cached.len() > budget.max_count || self.resident_bytes > budget.max_bytes {
+            let mut candidates = self
+                .cached
+                .iter()
+                .filter(|(_, value)| value.last_used != self.frame)
+                .map(|(key, value)| (value.last_used, *key))
+                .collect::<Vec<_>>();
+            candidates.sort_unstable_by_key(|(last_used, _)| *last_used);
+
+            for (_, key) in candidates {
+                if self.cached.len() <= budget.max_count && self.resident_bytes <= budget.max_bytes {
+                    break;
+                }
+
+                let value = self.cached.remove(&key).unwrap();
+                self.resident_bytes -= value.byte_size;
+                remove_count += 1;
             }
         }
 
-        self.used.clear();
+        self.frame += 1;
+        self.stats.evicted += remove_count;
         remove_count
     }
+
+    /// gpu buffer bytes of cached chunks
+    pub fn resident_bytes(&self) -> usize {
+        self.resident_bytes
+    }
+
+    pub fn resident_count(&self) -> usize {
+        self.cached.len()
+    }
+
+    /// stats since last call
+    pub fn take_stats(&mut self) -> ChunkCacheStats {
+        let mut stats = std::mem::take(&mut self.stats);
+        stats.resident_count = self.resident_count();
+        stats.resident_bytes = self.resident_bytes;
+        stats
+    }
 }
 
 #[cfg(test)]
 mod cache_tests {
-    use voxrs_math::{Aabb, Vec3};
+    use enumflags2::BitFlags;
+    use voxrs_math::{Aabb, ChunkPos, PaletteArray, Vec3};
 
     use super::*;
 
-    #[test]
-    fn clear_unused() {
-        let mut cache = ChunkCache::new();
-        assert_eq!(cache.set_used(1), false);
+    fn create_chunk_bp(id: ChunkId) -> SafeCloner<blueprint::Chunk> {
+        SafeCloner::new(blueprint::Chunk::new(
+            id,
+            Vec3::ZERO,
+            Aabb::unit(),
+            PaletteArray::new(0, 0),
+            PaletteArray::new(0, BitFlags::empty()),
+        ))
+    }
 
-        let bp_chunk_1 = SafeCloner::new(blueprint::Chunk::new(Vec3::ZERO, Aabb::unit(), Vec::new(), Vec::new()));
+    fn count_budget(max_count: usize) -> ChunkCacheBudget {
+        ChunkCacheBudget {
+            max_count,
+            max_bytes: usize::MAX,
+        }
+    }
 
-        let chunk_1 = Vec::new();
-        cache.add(1, bp_chunk_1, chunk_1);
+    #[test]
+    fn evict_unused_over_budget() {
+        let id_1 = ChunkPos::new(1, 0, 0);
+        let id_2 = ChunkPos::new(2, 0, 0);
 
-        let bp_chunk_2 = SafeCloner::new(blueprint::Chunk::new(Vec3::ZERO, Aabb::unit(), Vec::new(), Vec::new()));
+        let mut cache = ChunkCache::new();
+        assert!(!cache.set_used(id_1));
 
-        let chunk_2 = Vec::new();
-        cache.add(2, bp_chunk_2, chunk_2);
+        cache.add(create_chunk_bp(id_1), Vec::new(), 0);
+        cache.add(create_chunk_bp(id_2), Vec::new(), 0);
 
-        let removed = cache.clear_unused();
+        // used chunks are kept over budget
+        let removed = cache.evict(&count_budget(1));
         assert_eq!(removed, 0);
 
-        cache.set_used(2);
+        // unused chunk is kept in budget
+        cache.set_used(id_2);
+        let removed = cache.evict(&count_budget(2));
+        assert_eq!(removed, 0);
+        assert!(cache.get(&id_1).is_some());
 
-        let removed = cache.clear_unused();
+        cache.set_used(id_2);
+        let removed = cache.evict(&count_budget(1));
         assert_eq!(removed, 1);
 
-        assert!(cache.get(&1).is_none());
-        assert!(cache.get(&2).is_some());
+        assert!(cache.get(&id_1).is_none());
+        assert!(cache.get(&id_2).is_some());
+        assert_eq!(cache.take_stats().evicted, 1);
+    }
+
+    #[test]
+    fn evict_least_recently_used() {
+        let ids = (0..4).map(|x| ChunkPos::new(x, 0, 0)).collect::<Vec<_>>();
+
+        let mut cache = ChunkCache::new();
+        let budget = ChunkCacheBudget {
+            max_count: usize::MAX,
+            max_bytes: 250,
+        };
+
+        // 1 chunk of 100 bytes per frame
+        for id in &ids {
+            cache.add(create_chunk_bp(*id), Vec::new(), 100);
+            cache.evict(&budget);
+            assert!(cache.resident_bytes() <= 250);
+        }
+
+        // oldest ones are evicted
+        assert!(cache.get(&ids[0]).is_none());
+        assert!(cache.get(&ids[1]).is_none());
+        assert!(cache.get(&ids[2]).is_some());
+        assert!(cache.get(&ids[3]).is_some());
+
+        // using chunk makes it recent
+        cache.set_used(ids[2]);
+        cache.evict(&budget);
+        cache.add(create_chunk_bp(ids[0]), Vec::new(), 100);
+        cache.evict(&budget);
+        assert!(cache.get(&ids[2]).is_some());
+        assert!(cache.get(&ids[3]).is_none());
+
+        let stats = cache.take_stats();
+        assert_eq!(stats.evicted, 3);
+        assert_eq!(stats.resident_count, 2);
+        assert_eq!(stats.resident_bytes, 200);
+    }
+
+    #[test]
+    fn remesh_only_changed_version() {
+        let mut chunk_bp_1 = create_chunk_bp(ChunkPos::new(1, 0, 0));
+        let chunk_bp_2 = create_chunk_bp(ChunkPos::new(2, 0, 0));
+
+        let mut cache = ChunkCache::new();
+        assert!(!cache.check(&chunk_bp_1.id, chunk_bp_1.version));
+        assert!(!cache.add(SafeCloner::clone_read(&chunk_bp_1), Vec::new(), 10));
+        assert!(!cache.add(SafeCloner::clone_read(&chunk_bp_2), Vec::new(), 10));
+        cache.take_stats();
+
+        // modify chunk 1. cached one is not changed
+        let prev_version = chunk_bp_1.version;
+        chunk_bp_1.blocks = PaletteArray::new(1, 1);
+        assert!(chunk_bp_1.version > prev_version);
+        assert_eq!(cache.get_version(&chunk_bp_1.id), Some(prev_version));
+
+        assert!(!cache.check(&chunk_bp_1.id, chunk_bp_1.version));
+        assert!(cache.check(&chunk_bp_2.id, chunk_bp_2.version));
+
+        // previous mesh is kept until remeshed
+        assert!(cache.get(&chunk_bp_1.id).is_some());
+        assert!(cache.add(SafeCloner::clone_read(&chunk_bp_1), Vec::new(), 30));
+        assert!(cache.check(&chunk_bp_1.id, chunk_bp_1.version));
+
+        assert_eq!(
+            cache.take_stats(),
+            ChunkCacheStats {
+                hits: 2,
+                misses: 1,
+                remeshes: 1,
+                evicted: 0,
+                resident_count: 2,
+                resident_bytes: 40,
+            }
+        );
     }
 }
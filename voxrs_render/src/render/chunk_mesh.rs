@@ -0,0 +1,825 @@
+use std::collections::{BTreeMap, HashMap};
+
+use enumflags2::BitFlags;
+use voxrs_math::*;
+
+use crate::blueprint::{self, BlockIdx, BlockMatIdx};
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ChunkVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 3], // face color darkened by ambient occlusion
+    pub uv: [f32; 2],
+    pub layer: u32,       // layer in texture array of world material
+    pub normal: [f32; 3], // toward outside of face
+    pub emission: f32,    // light emitted by material. 0 to 1
+    pub light: [f32; 2],  // sky and block light in front of face. 0 to 1
+}
+
+/// 4 vertices per face. +y, -y, +x, -x, +z, -z
+/// each face is 2 triangles (0, 1, 2), (2, 1, 3)
+#[rustfmt::skip]
+pub const BLOCK_VERTICES: &[ChunkVertex] = &[
+    // +y
+    ChunkVertex { position: [0.0, 1.0, 1.0], color: [0., 1., 1.], uv: [0.0, 0.0], layer: 0, normal: [0., 1., 0.], emission: 0.0, light: [0.0, 0.0] },
+    ChunkVertex { position: [1.0, 1.0, 1.0], color: [0., 1., 1.], uv: [1.0, 0.0], layer: 0, normal: [0., 1., 0.], emission: 0.0, light: [0.0, 0.0] },
+    ChunkVertex { position: [0.0, 1.0, 0.0], color: [0., 1., 1.], uv: [0.0, 1.0], layer: 0, normal: [0., 1., 0.], emission: 0.0, light: [0.0, 0.0] },
+    ChunkVertex { position: [1.0, 1.0, 0.0], color: [0., 1., 1.], uv: [1.0, 1.0], layer: 0, normal: [0., 1., 0.], emission: 0.0, light: [0.0, 0.0] },
+
+    // -y
+    ChunkVertex { position: [0.0, 0.0, 0.0], color: [1., 1., 1.], uv: [0.0, 0.0], layer: 0, normal: [0., -1., 0.], emission: 0.0, light: [0.0, 0.0] },
+    ChunkVertex { position: [1.0, 0.0, 0.0], color: [1., 1., 1.], uv: [1.0, 0.0], layer: 0, normal: [0., -1., 0.], emission: 0.0, light: [0.0, 0.0] },
+    ChunkVertex { position: [0.0, 0.0, 1.0], color: [1., 1., 1.], uv: [0.0, 1.0], layer: 0, normal: [0., -1., 0.], emission: 0.0, light: [0.0, 0.0] },
+    ChunkVertex { position: [1.0, 0.0, 1.0], color: [1., 1., 1.], uv: [1.0, 1.0], layer: 0, normal: [0., -1., 0.], emission: 0.0, light: [0.0, 0.0] },
+
+    // +x
+    ChunkVertex { position: [1.0, 0.0, 0.0], color: [1., 1., 1.], uv: [0.0, 1.0], layer: 0, normal: [1., 0., 0.], emission: 0.0, light: [0.0, 0.0] },
+    ChunkVertex { position: [1.0, 1.0, 0.0], color: [1., 1., 1.], uv: [0.0, 0.0], layer: 0, normal: [1., 0., 0.], emission: 0.0, light: [0.0, 0.0] },
+    ChunkVertex { position: [1.0, 0.0, 1.0], color: [1., 1., 1.], uv: [1.0, 1.0], layer: 0, normal: [1., 0., 0.], emission: 0.0, light: [0.0, 0.0] },
+    ChunkVertex { position: [1.0, 1.0, 1.0], color: [1., 1., 1.], uv: [1.0, 0.0], layer: 0, normal: [1., 0., 0.], emission: 0.0, light: [0.0, 0.0] },
+
+    // -x
+    ChunkVertex { position: [0.0, 1.0, 0.0], color: [1., 1., 1.], uv: [0.0, 0.0], layer: 0, normal: [-1., 0., 0.], emission: 0.0, light: [0.0, 0.0] },
+    ChunkVertex { position: [0.0, 0.0, 0.0], color: [1., 1., 1.], uv: [1.0, 0.0], layer: 0, normal: [-1., 0., 0.], emission: 0.0, light: [0.0, 0.0] },
+    ChunkVertex { position: [0.0, 1.0, 1.0], color: [1., 1., 1.], uv: [0.0, 1.0], layer: 0, normal: [-1., 0., 0.], emission: 0.0, light: [0.0, 0.0] },
+    ChunkVertex { position: [0.0, 0.0, 1.0], color: [1., 1., 1.], uv: [1.0, 1.0], layer: 0, normal: [-1., 0., 0.], emission: 0.0, light: [0.0, 0.0] },
+
+    // +z
+    ChunkVertex { position: [0.0, 1.0, 1.0], color: [1., 1., 1.], uv: [0.0, 0.0], layer: 0, normal: [0., 0., 1.], emission: 0.0, light: [0.0, 0.0] },
+    ChunkVertex { position: [0.0, 0.0, 1.0], color: [1., 1., 1.], uv: [1.0, 0.0], layer: 0, normal: [0., 0., 1.], emission: 0.0, light: [0.0, 0.0] },
+    ChunkVertex { position: [1.0, 1.0, 1.0], color: [1., 1., 1.], uv: [0.0, 1.0], layer: 0, normal: [0., 0., 1.], emission: 0.0, light: [0.0, 0.0] },
+    ChunkVertex { position: [1.0, 0.0, 1.0], color: [1., 1., 1.], uv: [1.0, 1.0], layer: 0, normal: [0., 0., 1.], emission: 0.0, light: [0.0, 0.0] },
+
+    // -z
+    ChunkVertex { position: [0.0, 0.0, 0.0], color: [1., 0., 1.], uv: [0.0, 0.0], layer: 0, normal: [0., 0., -1.], emission: 0.0, light: [0.0, 0.0] },
+    ChunkVertex { position: [0.0, 1.0, 0.0], color: [1., 0., 1.], uv: [1.0, 0.0], layer: 0, normal: [0., 0., -1.], emission: 0.0, light: [0.0, 0.0] },
+    ChunkVertex { position: [1.0, 0.0, 0.0], color: [1., 0., 1.], uv: [0.0, 1.0], layer: 0, normal: [0., 0., -1.], emission: 0.0, light: [0.0, 0.0] },
+    ChunkVertex { position: [1.0, 1.0, 0.0], color: [1., 0., 1.], uv: [1.0, 1.0], layer: 0, normal: [0., 0., -1.], emission: 0.0, light: [0.0, 0.0] },
+];
+
+/// brightness of vertex by ao level. 0 : corner fully occluded, 3 : not occluded
+const AO_BRIGHTNESS: [f32; 4] = [0.4, 0.6, 0.8, 1.0];
+
+/// vertices and indices of visible faces in chunk local space
+#[derive(Default)]
+pub struct ChunkMesh {
+    pub vertices: Vec<ChunkVertex>,
+    pub indices: Vec<u32>,
+}
+
+impl ChunkMesh {
+    /// faces of blocks (block_indices) which are visible in vis
+    /// is_opaque : block at local position darkens corners next to it. position can be out of chunk
+    /// light : packed light of block at local position, which lights faces toward it. position can be out of chunk
+    pub fn build(
+        block_indices: &[BlockIdx],
+        vis: &PaletteArray<BitFlags<Dir>>,
+        is_opaque: impl Fn(BlockPos) -> bool,
+        light: impl Fn(BlockPos) -> u8,
+    ) -> Self {
+        let mut mesh = Self::default();
+
+        for &block_idx in block_indices {
+            let block_pos = BlockPos::from_chunk_pos(ChunkPos::new(0, 0, 0), block_idx as usize);
+            for dir in vis.get(block_idx as usize).iter() {
+                let ao = face_ao(block_pos, dir, &is_opaque);
+                mesh.push_quad(dir, block_pos, [1, 1, 1], &ao, light(block_pos.get_neighbor(dir)));
+            }
+        }
+
+        mesh
+    }
+
+    /// same faces as build, but coplanar faces with same ao and light are merged into larger quads
+    /// uv of merged quad is tiled per block. faces with ao gradient are not merged
+    pub fn build_greedy(
+        block_indices: &[BlockIdx],
+        vis: &PaletteArray<BitFlags<Dir>>,
+        is_opaque: impl Fn(BlockPos) -> bool,
+        light: impl Fn(BlockPos) -> u8,
+    ) -> Self {
+        let mut mesh = Self::default();
+        let side = BLOCK_COUNT_IN_CHUNKSIDE as i32;
+
+        for dir in BitFlags::<Dir>::all().iter() {
+            // ao and light of visible faces toward dir. None if no face
+            let mut faces = vec![None; TOTAL_BLOCK_COUNTS_IN_CHUNK];
+            for &block_idx in block_indices {
+                if vis.get(block_idx as usize).contains(dir) {
+                    let block_pos = BlockPos::from_chunk_pos(ChunkPos::new(0, 0, 0), block_idx as usize);
+                    let face = (face_ao(block_pos, dir, &is_opaque), light(block_pos.get_neighbor(dir)));
+                    faces[block_idx as usize] = Some(face);
+                }
+            }
+
+            let (normal_axis, u_axis, v_axis) = face_axes(dir);
+            let block_pos_at = |layer: i32, u: i32, v: i32| {
+                let mut pos = [0; 3];
+                pos[normal_axis] = layer;
+                pos[u_axis] = u;
+                pos[v_axis] = v;
+                BlockPos::new(pos[0], pos[1], pos[2])
+            };
+            let is_mergeable = |ao: &[usize; 4]| ao.iter().all(|a| *a == ao[0]);
+
+            for layer in 0..side {
+                for v in 0..side {
+                    for u in 0..side {
+                        let block_pos = block_pos_at(layer, u, v);
+                        let face = match faces[block_pos.get_block_index()] {
+                            Some(face) => face,
+                            None => continue,
+                        };
+                        let (ao, face_light) = face;
+
+                        let mut width = 1;
+                        let mut height = 1;
+                        if is_mergeable(&ao) {
+                            let same =
+                                |u: i32, v: i32| faces[block_pos_at(layer, u, v).get_block_index()] == Some(face);
+                            while u + width < side && same(u + width, v) {
+                                width += 1;
+                            }
+                            while v + height < side && (u..u + width).all(|u| same(u, v + height)) {
+                                height += 1;
+                            }
+                        }
+
+                        for v in v..v + height {
+                            for u in u..u + width {
+                                faces[block_pos_at(layer, u, v).get_block_index()] = None;
+                            }
+                        }
+
+                        let mut size = [1; 3];
+                        size[u_axis] = width;
+                        size[v_axis] = height;
+                        mesh.push_quad(dir, block_pos, size, &ao, face_light);
+                    }
+                }
+            }
+        }
+
+        mesh
+    }
+
+    /// bytes of vertices and indices
+    pub fn byte_size(&self) -> usize {
+        self.vertices.len() * std::mem::size_of::<ChunkVertex>() + self.indices.len() * std::mem::size_of::<u32>()
+    }
+
+    /// texture layer of every vertex
+    pub fn set_layer(&mut self, layer: u32) {
+        for v in &mut self.vertices {
+            v.layer = layer;
+        }
+    }
+
+    /// emission of every vertex
+    pub fn set_emission(&mut self, emission: f32) {
+        for v in &mut self.vertices {
+            v.emission = emission;
+        }
+    }
+
+    /// merge other mesh into this
+    pub fn append(&mut self, other: ChunkMesh) {
+        let base = self.vertices.len() as u32;
+        self.vertices.extend(other.vertices);
+        self.indices.extend(other.indices.iter().map(|idx| base + idx));
+    }
+
+    /// 2 triangles for each quad
+    pub fn quad_count(&self) -> usize {
+        self.indices.len() / 6
+    }
+
+    /// quad of face toward dir, covering size blocks from block_pos. light is packed light in front of face
+    fn push_quad(&mut self, dir: Dir, block_pos: BlockPos, size: [i32; 3], ao: &[usize; 4], light: u8) {
+        let face = face_vertices(dir);
+        let light = [
+            LightChannel::Sky.get(light) as f32 / MAX_LIGHT as f32,
+            LightChannel::Block.get(light) as f32 / MAX_LIGHT as f32,
+        ];
+        let offset = [block_pos.x as f32, block_pos.y as f32, block_pos.z as f32];
+        let size = [size[0] as f32, size[1] as f32, size[2] as f32];
+        let uv_size = [size[uv_axis(dir, 0)], size[uv_axis(dir, 1)]];
+
+        let base = self.vertices.len() as u32;
+        self.vertices.extend(face.iter().zip(ao.iter()).map(|(v, ao)| {
+            let brightness = AO_BRIGHTNESS[*ao];
+            ChunkVertex {
+                position: [
+                    v.position[0] * size[0] + offset[0],
+                    v.position[1] * size[1] + offset[1],
+                    v.position[2] * size[2] + offset[2],
+                ],
+                color: [
+                    v.color[0] * brightness,
+                    v.color[1] * brightness,
+                    v.color[2] * brightness,
+                ],
+                uv: [v.uv[0] * uv_size[0], v.uv[1] * uv_size[1]],
+                layer: 0,
+                normal: v.normal,
+                emission: 0.0,
+                light,
+            }
+        }));
+        self.indices.extend(quad_indices(ao).iter().map(|idx| base + idx));
+    }
+}
+
+/// quads of blended mesh, drawn back to front
+pub struct TransparentQuads {
+    centers: Vec<Vec3>, // chunk local space
+    indices: Vec<u32>,  // 6 for each quad
+}
+
+impl TransparentQuads {
+    pub fn from_mesh(mesh: &ChunkMesh) -> Self {
+        let centers = mesh
+            .indices
+            .chunks(6)
+            .map(|quad| {
+                // 4 vertices of quad are pushed together
+                let base = *quad.iter().min().unwrap() as usize;
+                let sum = mesh.vertices[base..base + 4]
+                    .iter()
+                    .fold(Vec3::ZERO, |sum, v| sum + Vec3::from(v.position));
+                sum / 4.0
+            })
+            .collect();
+
+        Self {
+            centers,
+            indices: mesh.indices.clone(),
+        }
+    }
+
+    /// indices of quads sorted from farthest to nearest from eye in chunk local space
+    pub fn sorted_indices(&self, eye: Vec3) -> Vec<u32> {
+        let mut order = (0..self.centers.len()).collect::<Vec<_>>();
+        order.sort_by(|a, b| {
+            let dist_a = self.centers[*a].distance_squared(eye);
+            let dist_b = self.centers[*b].distance_squared(eye);
+            dist_b.partial_cmp(&dist_a).unwrap()
+        });
+
+        order
+            .into_iter()
+            .flat_map(|quad| self.indices[quad * 6..quad * 6 + 6].iter().copied())
+            .collect()
+    }
+}
+
+/// normal axis and 2 axes on face toward dir (0: x, 1: y, 2: z)
+fn face_axes(dir: Dir) -> (usize, usize, usize) {
+    match dir {
+        Dir::XPos | Dir::XNeg => (0, 1, 2),
+        Dir::YPos | Dir::YNeg => (1, 0, 2),
+        Dir::ZPos | Dir::ZNeg => (2, 0, 1),
+    }
+}
+
+/// axis along which uv component (0: u, 1: v) of face changes
+fn uv_axis(dir: Dir, uv: usize) -> usize {
+    let face = face_vertices(dir);
+    let (_, u_axis, v_axis) = face_axes(dir);
+    let follows = |axis: usize| {
+        face.iter().all(|v| v.uv[uv] == v.position[axis]) || face.iter().all(|v| v.uv[uv] == 1.0 - v.position[axis])
+    };
+
+    if follows(u_axis) {
+        u_axis
+    } else {
+        v_axis
+    }
+}
+
+/// ao of 4 vertices of face
+fn face_ao(block_pos: BlockPos, dir: Dir, is_opaque: impl Fn(BlockPos) -> bool) -> [usize; 4] {
+    let mut ao = [0; 4];
+    for (ao, v) in ao.iter_mut().zip(face_vertices(dir)) {
+        *ao = vertex_ao(block_pos, dir, v.position, &is_opaque);
+    }
+    ao
+}
+
+/// layer of material in texture array
+/// meshes of materials in same group are merged
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct TextureLayer {
+    pub layer: u32,
+    pub group: u8,
+}
+
+/// meshes of chunk for each material
+/// blocks out of chunk are read from border of chunk, so corners and faces at chunk border match neighbors
+/// emission of material is scaled to 0 to 1
+/// with texture_layers, meshes of materials in same group are merged into 1 mesh
+/// with layer of each material, keyed by lowest material of them
+pub fn build_chunk_meshes(
+    bp: &blueprint::Chunk,
+    occlusion: &BlockOcclusion,
+    emission: &BlockEmission,
+    greedy_meshing: bool,
+    texture_layers: Option<&HashMap<BlockMatIdx, TextureLayer>>,
+) -> Vec<(BlockMatIdx, ChunkMesh)> {
+    // block indices of each material
+    let mut mat_blocks: BTreeMap<BlockMatIdx, Vec<BlockIdx>> = BTreeMap::new();
+    for (idx, mat_idx) in bp.blocks.iter().enumerate() {
+        if mat_idx != 0 {
+            mat_blocks.entry(mat_idx).or_default().push(idx as BlockIdx);
+        }
+    }
+
+    let is_opaque = |block_pos: BlockPos| {
+        if is_in_chunk(block_pos) {
+            occlusion.get(bp.blocks.get(block_pos.get_block_index())) == Occlusion::Opaque
+        } else {
+            bp.border.is_opaque(block_pos)
+        }
+    };
+    let light = |block_pos: BlockPos| {
+        if is_in_chunk(block_pos) {
+            bp.light.get(block_pos.get_block_index())
+        } else {
+            bp.border.get_light(block_pos)
+        }
+    };
+
+    let meshes = mat_blocks.into_iter().map(|(mat_idx, block_indices)| {
+        let mut mesh = if greedy_meshing {
+            ChunkMesh::build_greedy(&block_indices, &bp.vis, is_opaque, light)
+        } else {
+            ChunkMesh::build(&block_indices, &bp.vis, is_opaque, light)
+        };
+        mesh.set_emission(emission.get(mat_idx) as f32 / MAX_LIGHT as f32);
+        (mat_idx, mesh)
+    });
+
+    let texture_layers = match texture_layers {
+        Some(texture_layers) => texture_layers,
+        None => return meshes.collect(),
+    };
+
+    let mut merged: Vec<(BlockMatIdx, ChunkMesh)> = Vec::new();
+    let mut merged_groups = Vec::new();
+    for (mat_idx, mut mesh) in meshes {
+        let texture_layer = texture_layers.get(&mat_idx).copied().unwrap_or_default();
+        mesh.set_layer(texture_layer.layer);
+
+        match merged_groups.iter().position(|group| *group == texture_layer.group) {
+            Some(idx) => merged[idx].1.append(mesh),
+            None => {
+                merged.push((mat_idx, mesh));
+                merged_groups.push(texture_layer.group);
+            }
+        }
+    }
+    merged
+}
+
+/// local position is in chunk
+fn is_in_chunk(block_pos: BlockPos) -> bool {
+    let side = BLOCK_COUNT_IN_CHUNKSIDE as i32;
+    (0..side).contains(&block_pos.x) && (0..side).contains(&block_pos.y) && (0..side).contains(&block_pos.z)
+}
+
+fn face_vertices(dir: Dir) -> &'static [ChunkVertex] {
+    let face = match dir {
+        Dir::YPos => 0,
+        Dir::YNeg => 1,
+        Dir::XPos => 2,
+        Dir::XNeg => 3,
+        Dir::ZPos => 4,
+        Dir::ZNeg => 5,
+    };
+    &BLOCK_VERTICES[face * 4..face * 4 + 4]
+}
+
+/// split quad along brighter diagonal
+/// otherwise interpolated ao looks different when same corners are rotated
+fn quad_indices(ao: &[usize; 4]) -> [u32; 6] {
+    if ao[0] + ao[3] > ao[1] + ao[2] {
+        [0, 1, 3, 0, 3, 2]
+    } else {
+        [0, 1, 2, 2, 1, 3]
+    }
+}
+
+/// ao level of face vertex at corner (0.0 or 1.0 for each axis in block)
+/// from 2 side blocks and 1 corner block in front of face
+fn vertex_ao(block_pos: BlockPos, dir: Dir, corner: [f32; 3], is_opaque: impl Fn(BlockPos) -> bool) -> usize {
+    let front = block_pos.get_neighbor(dir);
+    let front = [front.x, front.y, front.z];
+    let (normal_axis, _, _) = face_axes(dir);
+
+    // step from front block toward corner along 2 axes of face
+    let mut steps = (0..3).filter(|axis| *axis != normal_axis).map(|axis| {
+        let mut step = [0; 3];
+        step[axis] = if corner[axis] > 0.5 { 1 } else { -1 };
+        step
+    });
+    let step1 = steps.next().unwrap();
+    let step2 = steps.next().unwrap();
+
+    let at = |step: [i32; 3]| {
+        is_opaque(BlockPos::new(
+            front[0] + step[0],
+            front[1] + step[1],
+            front[2] + step[2],
+        ))
+    };
+
+    let side1 = at(step1);
+    let side2 = at(step2);
+    if side1 && side2 {
+        return 0;
+    }
+
+    let corner = at([step1[0] + step2[0], step1[1] + step2[1], step1[2] + step2[2]]);
+    3 - (side1 as usize + side2 as usize + corner as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    fn sky_lit(_: BlockPos) -> u8 {
+        LightChannel::Sky.set(0, MAX_LIGHT)
+    }
+
+    fn single_block_vis(block_pos: BlockPos, vis: BitFlags<Dir>) -> PaletteArray<BitFlags<Dir>> {
+        let mut arr = PaletteArray::new(TOTAL_BLOCK_COUNTS_IN_CHUNK, BitFlags::empty());
+        arr.set(block_pos.get_block_index(), vis);
+        arr
+    }
+
+    fn build_top_face(opaques: &[BlockPos]) -> ChunkMesh {
+        let block_pos = BlockPos::new(1, 1, 1);
+        let vis = single_block_vis(block_pos, Dir::YPos.into());
+        let opaques = opaques.iter().copied().collect::<HashSet<_>>();
+        ChunkMesh::build(
+            &[block_pos.get_block_index() as BlockIdx],
+            &vis,
+            |pos| opaques.contains(&pos),
+            sky_lit,
+        )
+    }
+
+    fn brightness(mesh: &ChunkMesh) -> Vec<f32> {
+        mesh.vertices.iter().map(|v| v.color[0].max(v.color[1])).collect()
+    }
+
+    #[test]
+    fn test_block_without_neighbors() {
+        let block_pos = BlockPos::new(3, 4, 5);
+        let vis = single_block_vis(block_pos, BitFlags::all());
+        let mesh = ChunkMesh::build(&[block_pos.get_block_index() as BlockIdx], &vis, |_| false, sky_lit);
+
+        assert_eq!(mesh.vertices.len(), 24);
+        assert_eq!(mesh.indices.len(), 36);
+        let face_vertices = BitFlags::<Dir>::all().iter().flat_map(face_vertices);
+        for (v, face_v) in mesh.vertices.iter().zip(face_vertices) {
+            assert_eq!(v.color, face_v.color);
+            assert_eq!(v.position[0], face_v.position[0] + 3.0);
+        }
+    }
+
+    #[test]
+    fn test_face_normals() {
+        let blocks = floor();
+        let (block_indices, vis) = blocks_with_vis(&blocks);
+        let greedy = ChunkMesh::build_greedy(&block_indices, &vis, |pos| blocks.contains(&pos), sky_lit);
+
+        for quad in greedy.vertices.chunks(4) {
+            let normal = Vec3::from(quad[0].normal);
+            assert_eq!(normal.length(), 1.0);
+
+            // quad lies on plane perpendicular to normal
+            let position = Vec3::from(quad[0].position);
+            for v in quad {
+                assert_eq!(v.normal, quad[0].normal);
+                assert_eq!((Vec3::from(v.position) - position).dot(normal), 0.0);
+            }
+
+            // and faces out of floor (0..16, 0..1, 0..16)
+            let center = Vec3::new(8.0, 0.5, 8.0);
+            assert!((position - center).dot(normal) > 0.0);
+        }
+    }
+
+    #[test]
+    fn test_hidden_faces_are_skipped() {
+        let block_pos = BlockPos::new(0, 0, 0);
+        let vis = single_block_vis(block_pos, Dir::XPos | Dir::ZNeg);
+        let mesh = ChunkMesh::build(&[0], &vis, |_| false, sky_lit);
+
+        assert_eq!(mesh.vertices.len(), 8);
+        assert_eq!(mesh.indices.len(), 12);
+        assert_eq!(mesh.indices[6..], [4, 5, 6, 6, 5, 7]);
+    }
+
+    #[test]
+    fn test_corner_ao() {
+        // +y face of (1, 1, 1). vertices (0, 1, 1), (1, 1, 1), (0, 1, 0), (1, 1, 0)
+        let mesh = build_top_face(&[]);
+        assert_eq!(brightness(&mesh), [1.0, 1.0, 1.0, 1.0]);
+
+        // corner block only
+        let mesh = build_top_face(&[BlockPos::new(0, 2, 0)]);
+        assert_eq!(brightness(&mesh), [1.0, 1.0, 0.8, 1.0]);
+
+        // one side block darkens 2 vertices
+        let mesh = build_top_face(&[BlockPos::new(0, 2, 1)]);
+        assert_eq!(brightness(&mesh), [0.8, 1.0, 0.8, 1.0]);
+
+        // 2 sides occlude corner fully
+        let mesh = build_top_face(&[BlockPos::new(0, 2, 1), BlockPos::new(1, 2, 0)]);
+        assert_eq!(brightness(&mesh)[2], 0.4);
+
+        // blocks out of chunk are used too
+        let block_pos = BlockPos::new(0, 0, 0);
+        let vis = single_block_vis(block_pos, Dir::XNeg.into());
+        let mesh = ChunkMesh::build(&[0], &vis, |pos| pos == BlockPos::new(-1, 1, 0), sky_lit);
+        assert_eq!(brightness(&mesh), [0.8, 1.0, 0.8, 1.0]);
+    }
+
+    #[test]
+    fn test_quad_flip() {
+        // darkening vertex 0 or 3 keeps diagonal 1-2
+        let mesh = build_top_face(&[BlockPos::new(0, 2, 2)]);
+        assert_eq!(mesh.indices, [0, 1, 2, 2, 1, 3]);
+
+        // darkening vertex 1 or 2 splits along diagonal 0-3
+        let mesh = build_top_face(&[BlockPos::new(2, 2, 2)]);
+        assert_eq!(mesh.indices, [0, 1, 3, 0, 3, 2]);
+
+        let mesh = build_top_face(&[BlockPos::new(0, 2, 0)]);
+        assert_eq!(mesh.indices, [0, 1, 3, 0, 3, 2]);
+    }
+
+    /// blocks and their vis. faces to blocks out of chunk are visible
+    fn blocks_with_vis(blocks: &HashSet<BlockPos>) -> (Vec<BlockIdx>, PaletteArray<BitFlags<Dir>>) {
+        let mut vis = PaletteArray::new(TOTAL_BLOCK_COUNTS_IN_CHUNK, BitFlags::empty());
+        for block_pos in blocks {
+            let dirs = BitFlags::<Dir>::all()
+                .iter()
+                .filter(|dir| !blocks.contains(&block_pos.get_neighbor(*dir)))
+                .collect::<BitFlags<Dir>>();
+            vis.set(block_pos.get_block_index(), dirs);
+        }
+
+        let block_indices = blocks.iter().map(|pos| pos.get_block_index() as BlockIdx).collect();
+        (block_indices, vis)
+    }
+
+    fn floor() -> HashSet<BlockPos> {
+        let mut blocks = HashSet::new();
+        for z in 0..16 {
+            for x in 0..16 {
+                blocks.insert(BlockPos::new(x, 0, z));
+            }
+        }
+        blocks
+    }
+
+    /// blocks covered by quad
+    fn quad_area(mesh: &ChunkMesh, quad: usize) -> f32 {
+        let vertices = &mesh.vertices[quad * 4..quad * 4 + 4];
+        (0..3)
+            .map(|axis| {
+                let min = vertices.iter().map(|v| v.position[axis]).fold(f32::MAX, f32::min);
+                let max = vertices.iter().map(|v| v.position[axis]).fold(f32::MIN, f32::max);
+                max - min
+            })
+            .filter(|len| *len > 0.0)
+            .product()
+    }
+
+    #[test]
+    fn test_greedy_quad_counts() {
+        let blocks = floor();
+        let (block_indices, vis) = blocks_with_vis(&blocks);
+        let is_opaque = |pos| blocks.contains(&pos);
+
+        let mesh = ChunkMesh::build(&block_indices, &vis, is_opaque, sky_lit);
+        let greedy = ChunkMesh::build_greedy(&block_indices, &vis, is_opaque, sky_lit);
+
+        // top, bottom and 4 sides
+        assert_eq!(mesh.quad_count(), 256 + 256 + 4 * 16);
+        assert_eq!(greedy.quad_count(), 6);
+        assert_eq!(greedy.indices.len(), 6 * 6);
+        assert_eq!(greedy.vertices.len(), 6 * 4);
+    }
+
+    #[test]
+    fn test_greedy_tiled_uv() {
+        let blocks = floor();
+        let (block_indices, vis) = blocks_with_vis(&blocks);
+        let greedy = ChunkMesh::build_greedy(&block_indices, &vis, |pos| blocks.contains(&pos), sky_lit);
+
+        for quad in 0..greedy.quad_count() {
+            let vertices = &greedy.vertices[quad * 4..quad * 4 + 4];
+            let max_u = vertices.iter().map(|v| v.uv[0]).fold(0.0, f32::max);
+            let max_v = vertices.iter().map(|v| v.uv[1]).fold(0.0, f32::max);
+
+            // one tile per block
+            assert_eq!(max_u * max_v, quad_area(&greedy, quad));
+        }
+    }
+
+    #[test]
+    fn test_greedy_keeps_ao() {
+        // pillar on floor makes ao gradient around it
+        let mut blocks = floor();
+        blocks.insert(BlockPos::new(5, 1, 5));
+        blocks.insert(BlockPos::new(5, 2, 5));
+        let (block_indices, vis) = blocks_with_vis(&blocks);
+        let is_opaque = |pos| blocks.contains(&pos);
+
+        let mesh = ChunkMesh::build(&block_indices, &vis, is_opaque, sky_lit);
+        let greedy = ChunkMesh::build_greedy(&block_indices, &vis, is_opaque, sky_lit);
+        assert!(greedy.quad_count() < mesh.quad_count() / 10);
+
+        // same area is covered
+        let area: f32 = (0..greedy.quad_count()).map(|quad| quad_area(&greedy, quad)).sum();
+        assert_eq!(area, mesh.quad_count() as f32);
+
+        // darkened corners are kept
+        let dark_vertices = |mesh: &ChunkMesh| {
+            let mut positions = mesh
+                .vertices
+                .iter()
+                .filter(|v| v.color.iter().cloned().fold(0.0, f32::max) < 1.0)
+                .map(|v| v.position.map(|p| p as i32))
+                .collect::<Vec<_>>();
+            positions.sort_unstable();
+            positions.dedup();
+            positions
+        };
+        assert_eq!(dark_vertices(&greedy), dark_vertices(&mesh));
+    }
+
+    #[test]
+    fn test_face_light() {
+        // light in front of +y face of (1, 1, 1)
+        let block_pos = BlockPos::new(1, 1, 1);
+        let vis = single_block_vis(block_pos, Dir::YPos | Dir::YNeg);
+        let lit = LightChannel::Block.set(LightChannel::Sky.set(0, MAX_LIGHT), 6);
+        let mesh = ChunkMesh::build(
+            &[block_pos.get_block_index() as BlockIdx],
+            &vis,
+            |_| false,
+            |pos| {
+                if pos == BlockPos::new(1, 2, 1) {
+                    lit
+                } else {
+                    0
+                }
+            },
+        );
+
+        assert!(mesh.vertices[..4].iter().all(|v| v.light == [1.0, 0.4]));
+        assert!(mesh.vertices[4..].iter().all(|v| v.light == [0.0, 0.0]));
+    }
+
+    #[test]
+    fn test_greedy_splits_light() {
+        // sky light only over half of floor
+        let blocks = floor();
+        let (block_indices, vis) = blocks_with_vis(&blocks);
+        let light = |pos: BlockPos| if pos.x < 8 { sky_lit(pos) } else { 0 };
+        let greedy = ChunkMesh::build_greedy(&block_indices, &vis, |pos| blocks.contains(&pos), light);
+
+        // top and bottom are split, and +z and -z sides too
+        assert_eq!(greedy.quad_count(), 10);
+        for quad in greedy.vertices.chunks(4) {
+            let max_x = quad.iter().map(|v| v.position[0]).fold(0.0, f32::max);
+            let sky = if max_x <= 8.0 { 1.0 } else { 0.0 };
+            assert!(quad.iter().all(|v| v.light[0] == sky));
+        }
+    }
+
+    #[test]
+    fn test_border_light() {
+        // +x face at chunk border is lit by neighbor chunk
+        let mut blocks = PaletteArray::new(TOTAL_BLOCK_COUNTS_IN_CHUNK, 0);
+        let mut vis = PaletteArray::new(TOTAL_BLOCK_COUNTS_IN_CHUNK, BitFlags::empty());
+        let idx = BlockPos::new(15, 0, 0).get_block_index();
+        blocks.set(idx, 1);
+        vis.set(idx, Dir::XPos | Dir::XNeg);
+        let mut bp = blueprint::Chunk::new(ChunkPos::new(0, 0, 0), Vec3::ZERO, Aabb::unit(), blocks, vis);
+        bp.light
+            .set(BlockPos::new(14, 0, 0).get_block_index(), LightChannel::Block.set(0, 3));
+        bp.border
+            .set_light(BlockPos::new(16, 0, 0), LightChannel::Block.set(0, MAX_LIGHT));
+
+        let meshes = build_chunk_meshes(&bp, &BlockOcclusion::default(), &BlockEmission::default(), false, None);
+        let mesh = &meshes[0].1;
+        assert!(mesh.vertices[..4].iter().all(|v| v.light == [0.0, 1.0]));
+        assert!(mesh.vertices[4..].iter().all(|v| v.light == [0.0, 0.2]));
+    }
+
+    #[test]
+    fn test_border_ao() {
+        // +y face of (15, 0, 0). vertices (15, 1, 1), (16, 1, 1), (15, 1, 0), (16, 1, 0)
+        let mut blocks = PaletteArray::new(TOTAL_BLOCK_COUNTS_IN_CHUNK, 0);
+        let mut vis = PaletteArray::new(TOTAL_BLOCK_COUNTS_IN_CHUNK, BitFlags::empty());
+        let idx = BlockPos::new(15, 0, 0).get_block_index();
+        blocks.set(idx, 1);
+        vis.set(idx, Dir::YPos.into());
+        let mut bp = blueprint::Chunk::new(ChunkPos::new(0, 0, 0), Vec3::ZERO, Aabb::unit(), blocks, vis);
+        let occlusion = BlockOcclusion::default();
+        let emission = BlockEmission::default();
+
+        let meshes = build_chunk_meshes(&bp, &occlusion, &emission, false, None);
+        assert_eq!(brightness(&meshes[0].1), [1.0, 1.0, 1.0, 1.0]);
+
+        // block at x = 16 in neighbor chunk occludes side of face
+        bp.border.set_opaque(BlockPos::new(16, 1, 0), true);
+        let meshes = build_chunk_meshes(&bp, &occlusion, &emission, false, None);
+        assert_eq!(brightness(&meshes[0].1), [1.0, 0.8, 1.0, 0.8]);
+    }
+
+    #[test]
+    fn test_texture_layers_merge_materials() {
+        // single blocks of material 1, 2 and 3
+        let mut blocks = PaletteArray::new(TOTAL_BLOCK_COUNTS_IN_CHUNK, 0);
+        let mut vis = PaletteArray::new(TOTAL_BLOCK_COUNTS_IN_CHUNK, BitFlags::empty());
+        for (x, mat_idx) in [(0, 1), (2, 2), (4, 3)] {
+            let idx = BlockPos::new(x, 0, 0).get_block_index();
+            blocks.set(idx, mat_idx);
+            vis.set(idx, BitFlags::all());
+        }
+        let bp = blueprint::Chunk::new(ChunkPos::new(0, 0, 0), Vec3::ZERO, Aabb::unit(), blocks, vis);
+        let occlusion = BlockOcclusion::default();
+        let mut emission = BlockEmission::default();
+        emission.set(3, MAX_LIGHT);
+
+        let meshes = build_chunk_meshes(&bp, &occlusion, &emission, false, None);
+        assert_eq!(meshes.len(), 3);
+
+        // 1 and 2 in same group
+        let layers = [(1, 0, 0), (2, 1, 0), (3, 2, 1)]
+            .iter()
+            .map(|(mat_idx, layer, group)| {
+                (
+                    *mat_idx,
+                    TextureLayer {
+                        layer: *layer,
+                        group: *group,
+                    },
+                )
+            })
+            .collect::<HashMap<_, _>>();
+        let meshes = build_chunk_meshes(&bp, &occlusion, &emission, false, Some(&layers));
+        assert_eq!(meshes.len(), 2);
+
+        let (mat_idx, merged) = &meshes[0];
+        assert_eq!(*mat_idx, 1);
+        assert_eq!(merged.quad_count(), 12);
+        assert!(merged.vertices[..24].iter().all(|v| v.layer == 0));
+        assert!(merged.vertices[24..].iter().all(|v| v.layer == 1));
+        assert_eq!(merged.indices[36..42], [24, 25, 26, 26, 25, 27]);
+        assert!(merged.vertices.iter().all(|v| v.emission == 0.0));
+
+        let (mat_idx, other) = &meshes[1];
+        assert_eq!(*mat_idx, 3);
+        assert!(other.vertices.iter().all(|v| v.layer == 2 && v.emission == 1.0));
+    }
+
+    #[test]
+    fn test_transparent_quads_back_to_front() {
+        // +x faces of 3 blocks along x
+        let mut vis = PaletteArray::new(TOTAL_BLOCK_COUNTS_IN_CHUNK, BitFlags::empty());
+        let block_indices = (0..3)
+            .map(|x| {
+                let idx = BlockPos::new(x, 0, 0).get_block_index();
+                vis.set(idx, Dir::XPos.into());
+                idx as BlockIdx
+            })
+            .collect::<Vec<_>>();
+        let mesh = ChunkMesh::build(&block_indices, &vis, |_| false, sky_lit);
+        let quads = TransparentQuads::from_mesh(&mesh);
+
+        // farthest quad first
+        let first_vertex = |indices: &[u32]| indices.chunks(6).map(|quad| quad[0] / 4).collect::<Vec<_>>();
+        let indices = quads.sorted_indices(Vec3::new(10.0, 0.5, 0.5));
+        assert_eq!(first_vertex(&indices), [0, 1, 2]);
+        let indices = quads.sorted_indices(Vec3::new(-10.0, 0.5, 0.5));
+        assert_eq!(first_vertex(&indices), [2, 1, 0]);
+
+        // same triangles
+        let mut sorted = quads.sorted_indices(Vec3::ZERO);
+        let mut original = mesh.indices.clone();
+        sorted.sort_unstable();
+        original.sort_unstable();
+        assert_eq!(sorted, original);
+    }
+}
@@ -0,0 +1,203 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use crossbeam_channel::{Receiver, Sender};
+use voxrs_math::{BlockEmission, BlockOcclusion};
+use voxrs_types::SafeCloner;
+
+use crate::blueprint::{self, BlockMatIdx, ChunkId, ChunkVersion};
+
+use super::chunk_mesh::{build_chunk_meshes, ChunkMesh, TextureLayer};
+
+/// meshes of chunk built on worker, not uploaded to gpu yet
+pub(crate) struct BuiltChunk {
+    pub chunk_bp: SafeCloner<blueprint::Chunk>,
+    pub greedy_meshing: bool,
+    pub texture_array: bool, // meshes are merged with texture layers
+    pub meshes: Vec<(BlockMatIdx, ChunkMesh)>,
+}
+
+impl BuiltChunk {
+    pub fn id(&self) -> ChunkId {
+        self.chunk_bp.id
+    }
+
+    pub fn version(&self) -> ChunkVersion {
+        self.chunk_bp.version
+    }
+
+    pub fn byte_size(&self) -> usize {
+        self.meshes.iter().map(|(_, mesh)| mesh.byte_size()).sum()
+    }
+}
+
+/// builds chunk meshes on rayon workers
+pub(crate) struct ChunkMesher {
+    pending: HashSet<(ChunkId, ChunkVersion)>,
+    sender: Sender<BuiltChunk>,
+    receiver: Receiver<BuiltChunk>,
+}
+
+impl ChunkMesher {
+    pub fn new() -> Self {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+
+        Self {
+            pending: HashSet::new(),
+            sender,
+            receiver,
+        }
+    }
+
+    pub fn is_pending(&self, id: ChunkId, version: ChunkVersion) -> bool {
+        self.pending.contains(&(id, version))
+    }
+
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// build meshes of chunk on background worker
+    /// do nothing if same version of chunk is already building
+    /// texture_layers : layer of each material if world material has texture array
+    pub fn request(
+        &mut self,
+        chunk_bp: &SafeCloner<blueprint::Chunk>,
+        occlusion: &Arc<BlockOcclusion>,
+        emission: &Arc<BlockEmission>,
+        greedy_meshing: bool,
+        texture_layers: Option<&Arc<HashMap<BlockMatIdx, TextureLayer>>>,
+    ) {
+        if !self.pending.insert((chunk_bp.id, chunk_bp.version)) {
+            return;
+        }
+
+        let chunk_bp = SafeCloner::clone_read(chunk_bp);
+        let occlusion = occlusion.clone();
+        let emission = emission.clone();
+        let texture_layers = texture_layers.cloned();
+        let sender = self.sender.clone();
+        rayon::spawn(move || {
+            let meshes = build_chunk_meshes(
+                &chunk_bp,
+                &occlusion,
+                &emission,
+                greedy_meshing,
+                texture_layers.as_deref(),
+            );
+            let _ = sender.send(BuiltChunk {
+                chunk_bp,
+                greedy_meshing,
+                texture_array: texture_layers.is_some(),
+                meshes,
+            });
+        });
+    }
+
+    /// built chunks until their size reaches byte_budget
+    /// at least 1 chunk is returned if any is built, so large chunk is not blocked forever
+    pub fn receive(&mut self, byte_budget: usize) -> Vec<BuiltChunk> {
+        let mut built = Vec::new();
+        let mut bytes = 0;
+
+        while bytes < byte_budget || built.is_empty() {
+            let chunk = match self.receiver.try_recv() {
+                Ok(chunk) => chunk,
+                Err(_) => break,
+            };
+
+            self.pending.remove(&(chunk.id(), chunk.version()));
+            bytes += chunk.byte_size();
+            built.push(chunk);
+        }
+
+        built
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use enumflags2::BitFlags;
+    use voxrs_math::*;
+
+    use super::*;
+    use crate::render::ChunkVertex;
+
+    fn create_chunk_bp(id: ChunkId) -> SafeCloner<blueprint::Chunk> {
+        let mut blocks = PaletteArray::new(TOTAL_BLOCK_COUNTS_IN_CHUNK, 0);
+        blocks.set(0, 1);
+        blocks.set(1, 2);
+        let mut vis = PaletteArray::new(TOTAL_BLOCK_COUNTS_IN_CHUNK, BitFlags::empty());
+        vis.set(0, BitFlags::all());
+        vis.set(1, BitFlags::all());
+
+        SafeCloner::new(blueprint::Chunk::new(id, Vec3::ZERO, Aabb::unit(), blocks, vis))
+    }
+
+    /// receive until count chunks are built
+    fn receive_all(mesher: &mut ChunkMesher, byte_budget: usize, count: usize) -> Vec<Vec<BuiltChunk>> {
+        let mut frames = Vec::new();
+        let mut received = 0;
+        while received < count {
+            let built = mesher.receive(byte_budget);
+            received += built.len();
+            if !built.is_empty() {
+                frames.push(built);
+            }
+            std::thread::yield_now();
+        }
+        frames
+    }
+
+    #[test]
+    fn test_build_on_worker() {
+        let mut mesher = ChunkMesher::new();
+        let occlusion = Arc::new(BlockOcclusion::default());
+        let emission = Arc::new(BlockEmission::default());
+        let mut chunk_bp = create_chunk_bp(ChunkPos::new(0, 0, 0));
+
+        mesher.request(&chunk_bp, &occlusion, &emission, false, None);
+        mesher.request(&chunk_bp, &occlusion, &emission, false, None);
+        assert!(mesher.is_pending(chunk_bp.id, chunk_bp.version));
+        assert_eq!(mesher.pending_count(), 1);
+
+        let frames = receive_all(&mut mesher, usize::MAX, 1);
+        let built = &frames[0][0];
+        assert_eq!(built.id(), chunk_bp.id);
+        assert_eq!(built.version(), chunk_bp.version);
+        assert_eq!(built.meshes.len(), 2);
+
+        // 2 single blocks with every face visible
+        let vertex_size = std::mem::size_of::<ChunkVertex>();
+        assert_eq!(built.byte_size(), 2 * (24 * vertex_size + 36 * 4));
+        assert!(!mesher.is_pending(chunk_bp.id, chunk_bp.version));
+
+        // modified chunk is built again
+        chunk_bp.blocks.set(2, 1);
+        mesher.request(&chunk_bp, &occlusion, &emission, false, None);
+        let frames = receive_all(&mut mesher, usize::MAX, 1);
+        assert_eq!(frames[0][0].version(), chunk_bp.version);
+        assert_eq!(frames[0][0].meshes.len(), 2);
+    }
+
+    #[test]
+    fn test_receive_budget() {
+        let mut mesher = ChunkMesher::new();
+        let occlusion = Arc::new(BlockOcclusion::default());
+        let emission = Arc::new(BlockEmission::default());
+        let chunk_bps = (0..4)
+            .map(|x| create_chunk_bp(ChunkPos::new(x, 0, 0)))
+            .collect::<Vec<_>>();
+        for chunk_bp in &chunk_bps {
+            mesher.request(chunk_bp, &occlusion, &emission, true, None);
+        }
+
+        // 1 chunk per frame with small budget
+        let frames = receive_all(&mut mesher, 1, chunk_bps.len());
+        assert_eq!(frames.len(), chunk_bps.len());
+        assert!(frames.iter().all(|built| built.len() == 1));
+        assert_eq!(mesher.pending_count(), 0);
+    }
+}
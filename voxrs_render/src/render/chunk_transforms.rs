@@ -0,0 +1,283 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use crossbeam_channel::{Receiver, Sender};
+use voxrs_math::Mat4;
+
+/// initial slot count of transform buffer. doubled when full
+const INITIAL_CAPACITY: u32 = 1024;
+
+/// seconds for newly added chunk to fade in
+const FADE_IN_SECS: f32 = 0.4;
+
+/// data of each slot in storage buffer. same layout in std430
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct ChunkInstance {
+    world: [f32; 16],
+    fade: [f32; 4], // x : 0 (invisible) to 1 (fully drawn)
+}
+
+impl ChunkInstance {
+    fn new(transform: Mat4, fade: f32) -> Self {
+        Self {
+            world: transform.to_cols_array(),
+            fade: [fade, 0.0, 0.0, 0.0],
+        }
+    }
+}
+
+/// index of transform in storage buffer. drawn as instance index
+/// returned to allocator when dropped
+pub(crate) struct TransformSlot {
+    pub index: u32,
+    freed: Sender<u32>,
+}
+
+impl Drop for TransformSlot {
+    fn drop(&mut self) {
+        let _ = self.freed.send(self.index);
+    }
+}
+
+/// free list of transform slots
+pub(crate) struct SlotAllocator {
+    next: u32,
+    free: Vec<u32>,
+    freed_sender: Sender<u32>,
+    freed_receiver: Receiver<u32>,
+}
+
+impl SlotAllocator {
+    pub fn new() -> Self {
+        let (freed_sender, freed_receiver) = crossbeam_channel::unbounded();
+        Self {
+            next: 0,
+            free: Vec::new(),
+            freed_sender,
+            freed_receiver,
+        }
+    }
+
+    /// slots ever allocated. buffer should hold this many transforms
+    pub fn high_water(&self) -> u32 {
+        self.next
+    }
+
+    pub fn used_count(&mut self) -> u32 {
+        self.reclaim();
+        self.next - self.free.len() as u32
+    }
+
+    /// freed slot is reused first
+    pub fn alloc(&mut self) -> TransformSlot {
+        self.reclaim();
+
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                self.next += 1;
+                self.next - 1
+            }
+        };
+
+        TransformSlot {
+            index,
+            freed: self.freed_sender.clone(),
+        }
+    }
+
+    fn reclaim(&mut self) {
+        self.free.extend(self.freed_receiver.try_iter());
+    }
+}
+
+/// slots fading in and when they started
+pub(crate) struct FadeTracker {
+    started: HashMap<u32, Instant>,
+}
+
+impl FadeTracker {
+    pub fn new() -> Self {
+        Self {
+            started: HashMap::new(),
+        }
+    }
+
+    pub fn start(&mut self, index: u32, now: Instant) {
+        self.started.insert(index, now);
+    }
+
+    pub fn stop(&mut self, index: u32) {
+        self.started.remove(&index);
+    }
+
+    /// fades of slots at now. finished slots are returned with 1 and removed
+    pub fn update(&mut self, now: Instant) -> Vec<(u32, f32)> {
+        let fades = self
+            .started
+            .iter()
+            .map(|(index, started)| (*index, fade_at(now.saturating_duration_since(*started))))
+            .collect::<Vec<_>>();
+        self.started
+            .retain(|_, started| fade_at(now.saturating_duration_since(*started)) < 1.0);
+        fades
+    }
+}
+
+fn fade_at(elapsed: Duration) -> f32 {
+    (elapsed.as_secs_f32() / FADE_IN_SECS).min(1.0)
+}
+
+/// world transforms and fades of chunks in storage buffer, indexed by instance index in shader
+pub(crate) struct ChunkTransforms {
+    allocator: SlotAllocator,
+    instances: Vec<ChunkInstance>, // cpu copy for growing buffer
+    fades: FadeTracker,
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl ChunkTransforms {
+    pub fn new(device: &wgpu::Device, layout: &wgpu::BindGroupLayout) -> Self {
+        let (buffer, bind_group) = create_transform_buffer(device, layout, INITIAL_CAPACITY);
+        Self {
+            allocator: SlotAllocator::new(),
+            instances: vec![ChunkInstance::new(Mat4::IDENTITY, 1.0); INITIAL_CAPACITY as usize],
+            fades: FadeTracker::new(),
+            buffer,
+            bind_group,
+        }
+    }
+
+    /// store transform in free slot. fade_in : slot starts invisible and fades in
+    /// buffer and bind group are recreated if buffer is full
+    pub fn alloc(
+        &mut self,
+        transform: Mat4,
+        fade_in: bool,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layout: &wgpu::BindGroupLayout,
+    ) -> TransformSlot {
+        let slot = self.allocator.alloc();
+        let index = slot.index as usize;
+
+        if fade_in {
+            self.fades.start(slot.index, Instant::now());
+        } else {
+            self.fades.stop(slot.index);
+        }
+        let instance = ChunkInstance::new(transform, if fade_in { 0.0 } else { 1.0 });
+
+        if index >= self.instances.len() {
+            let capacity = (self.instances.len() * 2).max(index + 1);
+            self.instances.resize(capacity, ChunkInstance::new(Mat4::IDENTITY, 1.0));
+            self.instances[index] = instance;
+
+            let (buffer, bind_group) = create_transform_buffer(device, layout, capacity as u32);
+            queue.write_buffer(&buffer, 0, bytemuck::cast_slice(&self.instances));
+            self.buffer = buffer;
+            self.bind_group = bind_group;
+        } else {
+            self.instances[index] = instance;
+            let offset = (index * std::mem::size_of::<ChunkInstance>()) as u64;
+            queue.write_buffer(&self.buffer, offset, bytemuck::bytes_of(&instance));
+        }
+
+        slot
+    }
+
+    /// advance fades of slots fading in
+    pub fn update_fades(&mut self, queue: &wgpu::Queue) {
+        for (index, fade) in self.fades.update(Instant::now()) {
+            let instance = &mut self.instances[index as usize];
+            instance.fade[0] = fade;
+
+            let offset = (index as usize * std::mem::size_of::<ChunkInstance>()) as u64;
+            queue.write_buffer(&self.buffer, offset, bytemuck::bytes_of(instance));
+        }
+    }
+
+    pub fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_group
+    }
+}
+
+fn create_transform_buffer(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    capacity: u32,
+) -> (wgpu::Buffer, wgpu::BindGroup) {
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("chunk transform buffer"),
+        size: capacity as u64 * std::mem::size_of::<ChunkInstance>() as u64,
+        usage: wgpu::BufferUsage::STORAGE | wgpu::BufferUsage::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("chunk transform bind group"),
+        layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                buffer: &buffer,
+                offset: 0,
+                size: None,
+            }),
+        }],
+    });
+
+    (buffer, bind_group)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slot_reused_after_drop() {
+        let mut allocator = SlotAllocator::new();
+        let a = allocator.alloc();
+        let b = allocator.alloc();
+        assert_eq!((a.index, b.index), (0, 1));
+
+        drop(a);
+        assert_eq!(allocator.used_count(), 1);
+
+        let c = allocator.alloc();
+        assert_eq!(c.index, 0);
+        assert_eq!(allocator.high_water(), 2);
+
+        let d = allocator.alloc();
+        assert_eq!(d.index, 2);
+        assert_eq!(allocator.used_count(), 3);
+    }
+
+    #[test]
+    fn test_fade_in() {
+        let start = Instant::now();
+        let mut fades = FadeTracker::new();
+        fades.start(0, start);
+        fades.start(1, start + Duration::from_secs_f32(FADE_IN_SECS / 2.0));
+
+        let mut update = fades.update(start + Duration::from_secs_f32(FADE_IN_SECS / 2.0));
+        update.sort_by_key(|(index, _)| *index);
+        assert_eq!(update, vec![(0, 0.5), (1, 0.0)]);
+
+        // finished slot gets full fade once, then is not updated
+        let update = fades.update(start + Duration::from_secs_f32(FADE_IN_SECS));
+        assert!(update.contains(&(0, 1.0)));
+        let update = fades.update(start + Duration::from_secs_f32(FADE_IN_SECS * 2.0));
+        assert_eq!(update, vec![(1, 1.0)]);
+        assert!(fades.update(start + Duration::from_secs(10)).is_empty());
+
+        // reused slot stops fading
+        fades.start(2, start);
+        fades.stop(2);
+        assert!(fades.update(start).is_empty());
+    }
+}
@@ -1,17 +1,41 @@
 use std::num::NonZeroU64;
 
+use voxrs_asset::FogMode;
 use voxrs_math::*;
 
+use crate::blueprint::{Environment, Fog};
+
 pub struct CommonUniforms {
     view_proj_mat: Mat4,
     screen_to_ndc_mat: Mat4,
     buffer: wgpu::Buffer,
 }
 
+/// directional sun light with ambient term. vec3s are padded to vec4 for std140
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct LightUniform {
+    sun_dir: [f32; 4],
+    sun_color: [f32; 4],
+    ambient_color: [f32; 4],
+}
+
+/// distance fog. mode 0 : no fog, 1 : linear, 2 : exp, 3 : exp2
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct FogUniform {
+    color: [f32; 4],
+    params: [f32; 4], // start, end, density, mode
+}
+
 const MATRIX_SIZE: wgpu::BufferAddress = std::mem::size_of::<Mat4>() as wgpu::BufferAddress;
 const VIEW_PROJ_OFFSET: wgpu::BufferAddress = aligned_offset(0);
 const SCREEN_TO_NDC_OFFSET: wgpu::BufferAddress = aligned_offset(VIEW_PROJ_OFFSET + MATRIX_SIZE);
-const TOTAL_SIZE: wgpu::BufferAddress = SCREEN_TO_NDC_OFFSET + MATRIX_SIZE;
+const LIGHT_SIZE: wgpu::BufferAddress = std::mem::size_of::<LightUniform>() as wgpu::BufferAddress;
+const LIGHT_OFFSET: wgpu::BufferAddress = aligned_offset(SCREEN_TO_NDC_OFFSET + MATRIX_SIZE);
+const FOG_SIZE: wgpu::BufferAddress = std::mem::size_of::<FogUniform>() as wgpu::BufferAddress;
+const FOG_OFFSET: wgpu::BufferAddress = aligned_offset(LIGHT_OFFSET + LIGHT_SIZE);
+const TOTAL_SIZE: wgpu::BufferAddress = FOG_OFFSET + FOG_SIZE;
 
 // each buffer should be aligned with wgpu::BIND_BUFFER_ALIGNMENT
 const fn aligned_offset(offset: wgpu::BufferAddress) -> wgpu::BufferAddress {
@@ -70,6 +94,40 @@ impl CommonUniforms {
         )
     }
 
+    pub fn set_light(&mut self, environment: &Environment, queue: &wgpu::Queue) {
+        let light = LightUniform {
+            sun_dir: environment.sun_dir.extend(0.0).to_array(),
+            sun_color: environment.sun_color.extend(1.0).to_array(),
+            ambient_color: environment.ambient_color.extend(1.0).to_array(),
+        };
+
+        queue.write_buffer(&self.buffer, LIGHT_OFFSET, bytemuck::bytes_of(&light));
+    }
+
+    /// exp modes get density which makes fog about 98% thick at end
+    pub fn set_fog(&mut self, fog: Option<&Fog>, queue: &wgpu::Queue) {
+        let uniform = match fog {
+            Some(fog) => {
+                let range = (fog.end - fog.start).max(f32::EPSILON);
+                let (density, mode) = match fog.mode {
+                    FogMode::Linear => (0.0, 1.0),
+                    FogMode::Exp => (4.0 / range, 2.0),
+                    FogMode::Exp2 => (2.0 / range, 3.0),
+                };
+                FogUniform {
+                    color: fog.color.extend(1.0).to_array(),
+                    params: [fog.start, fog.end, density, mode],
+                }
+            }
+            None => FogUniform {
+                color: [0.0; 4],
+                params: [0.0; 4],
+            },
+        };
+
+        queue.write_buffer(&self.buffer, FOG_OFFSET, bytemuck::bytes_of(&uniform));
+    }
+
     pub fn get_view_proj_buffer(&self) -> wgpu::BindingResource<'_> {
         wgpu::BindingResource::Buffer(wgpu::BufferBinding {
             buffer: &self.buffer,
@@ -85,4 +143,20 @@ impl CommonUniforms {
             size: NonZeroU64::new(MATRIX_SIZE),
         })
     }
+
+    pub fn get_light_buffer(&self) -> wgpu::BindingResource<'_> {
+        wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+            buffer: &self.buffer,
+            offset: LIGHT_OFFSET,
+            size: NonZeroU64::new(LIGHT_SIZE),
+        })
+    }
+
+    pub fn get_fog_buffer(&self) -> wgpu::BindingResource<'_> {
+        wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+            buffer: &self.buffer,
+            offset: FOG_OFFSET,
+            size: NonZeroU64::new(FOG_SIZE),
+        })
+    }
 }
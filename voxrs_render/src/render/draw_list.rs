@@ -0,0 +1,186 @@
+use std::ops::Range;
+
+/// initial command count of indirect buffer. doubled when full
+const INITIAL_CAPACITY: usize = 1024;
+
+/// arguments of 1 indexed draw. same layout as indirect draw command
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct DrawIndexedArgs {
+    pub index_count: u32,
+    pub instance_count: u32,
+    pub first_index: u32,
+    pub base_vertex: i32,
+    pub first_instance: u32,
+}
+
+impl DrawIndexedArgs {
+    /// 1 instance of indices
+    pub fn new(indices: Range<u32>, base_vertex: i32, instance: u32) -> Self {
+        Self {
+            index_count: indices.end - indices.start,
+            instance_count: 1,
+            first_index: indices.start,
+            base_vertex,
+            first_instance: instance,
+        }
+    }
+
+    pub fn indices(&self) -> Range<u32> {
+        self.first_index..self.first_index + self.index_count
+    }
+
+    pub fn instances(&self) -> Range<u32> {
+        self.first_instance..self.first_instance + self.instance_count
+    }
+}
+
+/// consecutive draws with same states (key)
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct DrawRun<K> {
+    pub key: K,
+    pub commands: Range<u32>, // in commands of draw list
+}
+
+/// draws in order, grouped into runs of same key
+/// each run is drawn with 1 multi draw if device supports it
+pub(crate) struct DrawList<K> {
+    runs: Vec<DrawRun<K>>,
+    commands: Vec<DrawIndexedArgs>,
+}
+
+impl<K: PartialEq> DrawList<K> {
+    pub fn new() -> Self {
+        Self {
+            runs: Vec::new(),
+            commands: Vec::new(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.runs.clear();
+        self.commands.clear();
+    }
+
+    /// last run is continued if it has same key
+    pub fn push(&mut self, key: K, args: DrawIndexedArgs) {
+        let index = self.commands.len() as u32;
+        self.commands.push(args);
+
+        match self.runs.last_mut() {
+            Some(run) if run.key == key => run.commands.end = index + 1,
+            _ => self.runs.push(DrawRun {
+                key,
+                commands: index..index + 1,
+            }),
+        }
+    }
+
+    pub fn runs(&self) -> &[DrawRun<K>] {
+        &self.runs
+    }
+
+    pub fn commands(&self) -> &[DrawIndexedArgs] {
+        &self.commands
+    }
+}
+
+/// draw commands of frame for multi draw indirect
+pub(crate) struct IndirectBuffer {
+    buffer: wgpu::Buffer,
+    capacity: usize,
+}
+
+impl IndirectBuffer {
+    pub fn new(device: &wgpu::Device) -> Self {
+        Self {
+            buffer: create_indirect_buffer(device, INITIAL_CAPACITY),
+            capacity: INITIAL_CAPACITY,
+        }
+    }
+
+    /// commands of every list in order. returns first command index of each list
+    /// buffer is recreated if it is full
+    pub fn write(&mut self, lists: &[&[DrawIndexedArgs]], device: &wgpu::Device, queue: &wgpu::Queue) -> Vec<u32> {
+        let count = lists.iter().map(|commands| commands.len()).sum::<usize>();
+        if count > self.capacity {
+            self.capacity = (self.capacity * 2).max(count);
+            self.buffer = create_indirect_buffer(device, self.capacity);
+        }
+
+        let mut firsts = Vec::with_capacity(lists.len());
+        let mut first = 0;
+        for commands in lists {
+            firsts.push(first as u32);
+            if !commands.is_empty() {
+                let offset = (first * std::mem::size_of::<DrawIndexedArgs>()) as u64;
+                queue.write_buffer(&self.buffer, offset, bytemuck::cast_slice(commands));
+            }
+            first += commands.len();
+        }
+
+        firsts
+    }
+
+    /// draw commands of run. first is first command index of its list
+    pub fn draw_run<'a, K>(&'a self, first: u32, run: &DrawRun<K>, render_pass: &mut wgpu::RenderPass<'a>) {
+        let offset = (first + run.commands.start) as u64 * std::mem::size_of::<DrawIndexedArgs>() as u64;
+        let count = run.commands.end - run.commands.start;
+        render_pass.multi_draw_indexed_indirect(&self.buffer, offset, count);
+    }
+}
+
+fn create_indirect_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("chunk indirect buffer"),
+        size: (capacity * std::mem::size_of::<DrawIndexedArgs>()) as u64,
+        usage: wgpu::BufferUsage::INDIRECT | wgpu::BufferUsage::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(first_index: u32) -> DrawIndexedArgs {
+        DrawIndexedArgs::new(first_index..first_index + 6, 0, first_index)
+    }
+
+    #[test]
+    fn test_args_layout() {
+        // 5 x 4 bytes, tightly packed in indirect buffer
+        assert_eq!(std::mem::size_of::<DrawIndexedArgs>(), 20);
+
+        let args = DrawIndexedArgs::new(12..30, -4, 7);
+        assert_eq!(args.indices(), 12..30);
+        assert_eq!(args.instances(), 7..8);
+        assert_eq!(args.base_vertex, -4);
+    }
+
+    #[test]
+    fn test_runs() {
+        let mut list = DrawList::new();
+        list.push(0, args(0));
+        list.push(0, args(6));
+        list.push(1, args(12));
+        list.push(0, args(18));
+        list.push(0, args(24));
+
+        // order is kept, so same key after other key starts new run
+        assert_eq!(
+            list.runs(),
+            &[
+                DrawRun { key: 0, commands: 0..2 },
+                DrawRun { key: 1, commands: 2..3 },
+                DrawRun { key: 0, commands: 3..5 },
+            ]
+        );
+        assert_eq!(list.commands().len(), 5);
+        assert_eq!(list.commands()[2], args(12));
+
+        list.clear();
+        assert!(list.runs().is_empty());
+        assert!(list.commands().is_empty());
+    }
+}
@@ -4,10 +4,13 @@ use crate::blueprint::{self, BlockIdx, DynamicBlock};
 use voxrs_asset::{AssetHandle, ShaderAsset};
 use voxrs_math::*;
 
-use voxrs_rhi::{DynamicBuffer, DEPTH_FORMAT};
+use voxrs_rhi::{DynamicBuffer, DEPTH_FORMAT, HDR_FORMAT};
 use wgpu::util::DeviceExt;
 
-use super::{CommonUniforms, ShaderHash};
+use super::{
+    render_graph::{FrameTargets, RenderGraph},
+    CommonUniforms, ShaderHash,
+};
 
 pub struct DynamicBlockRenderer {
     uniform_bind_group: wgpu::BindGroup,
@@ -233,7 +236,7 @@ impl DynamicBlockRenderer {
                 module: &fs_module,
                 entry_point: "main",
                 targets: &[wgpu::ColorTargetState {
-                    format: wgpu::TextureFormat::Bgra8UnormSrgb,
+                    format: HDR_FORMAT,
                     blend: Some(wgpu::BlendState::ALPHA_BLENDING),
                     write_mask: wgpu::ColorWrite::ALL,
                 }],
@@ -273,6 +276,19 @@ impl DynamicBlockRenderer {
         }
     }
 
+    pub(crate) fn add_pass<'r>(
+        &'r self,
+        blocks: &'r HashMap<ShaderHash, Vec<Block>>,
+        targets: FrameTargets,
+        graph: &mut RenderGraph<'r>,
+    ) {
+        graph
+            .add_pass("dynamic block pass")
+            .color(targets.color, wgpu::LoadOp::Load)
+            .depth(targets.depth, wgpu::LoadOp::Load)
+            .record(move |pass, _| self.render(blocks, pass));
+    }
+
     pub fn clear(&mut self) {
         self.vertex_buffer.clear();
     }
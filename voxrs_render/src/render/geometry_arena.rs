@@ -0,0 +1,299 @@
+use std::ops::Range;
+
+use crossbeam_channel::{Receiver, Sender};
+
+use super::{ChunkMesh, ChunkVertex};
+
+/// vertices and indices in each page of arena (if mesh is not larger)
+const PAGE_VERTEX_COUNT: u32 = 256 * 1024;
+const PAGE_INDEX_COUNT: u32 = 384 * 1024;
+
+/// first fit allocator of ranges in buffer
+/// freed range is merged with neighbor free ranges
+pub(crate) struct RangeAllocator {
+    size: u32,
+    free: Vec<Range<u32>>, // sorted, never adjacent
+}
+
+impl RangeAllocator {
+    pub fn new(size: u32) -> Self {
+        let mut free = Vec::new();
+        if size > 0 {
+            free.push(0..size);
+        }
+        Self { size, free }
+    }
+
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    pub fn free_size(&self) -> u32 {
+        self.free.iter().map(|range| range.end - range.start).sum()
+    }
+
+    /// None if no free range is large enough
+    pub fn alloc(&mut self, size: u32) -> Option<Range<u32>> {
+        if size == 0 {
+            return Some(0..0);
+        }
+
+        let idx = self.free.iter().position(|range| range.end - range.start >= size)?;
+        let start = self.free[idx].start;
+        self.free[idx].start += size;
+        if self.free[idx].start == self.free[idx].end {
+            self.free.remove(idx);
+        }
+
+        Some(start..start + size)
+    }
+
+    pub fn free(&mut self, range: Range<u32>) {
+        if range.start == range.end {
+            return;
+        }
+
+        debug_assert!(range.end <= self.size);
+        let idx = self.free.partition_point(|free| free.start < range.start);
+        debug_assert!(idx == 0 || self.free[idx - 1].end <= range.start);
+        debug_assert!(idx == self.free.len() || range.end <= self.free[idx].start);
+
+        let merge_prev = idx > 0 && self.free[idx - 1].end == range.start;
+        let merge_next = idx < self.free.len() && self.free[idx].start == range.end;
+        match (merge_prev, merge_next) {
+            (true, true) => {
+                self.free[idx - 1].end = self.free[idx].end;
+                self.free.remove(idx);
+            }
+            (true, false) => self.free[idx - 1].end = range.end,
+            (false, true) => self.free[idx].start = range.start,
+            (false, false) => self.free.insert(idx, range),
+        }
+    }
+}
+
+/// ranges of mesh in page of arena
+/// returned to allocator when dropped
+pub(crate) struct GeometryAlloc {
+    pub page: usize,
+    pub vertices: Range<u32>,
+    pub indices: Range<u32>,
+    freed: Sender<(usize, Range<u32>, Range<u32>)>,
+}
+
+impl Drop for GeometryAlloc {
+    fn drop(&mut self) {
+        let _ = self
+            .freed
+            .send((self.page, self.vertices.clone(), self.indices.clone()));
+    }
+}
+
+/// vertex and index ranges of pages
+pub(crate) struct GeometryAllocator {
+    pages: Vec<(RangeAllocator, RangeAllocator)>,
+    freed_sender: Sender<(usize, Range<u32>, Range<u32>)>,
+    freed_receiver: Receiver<(usize, Range<u32>, Range<u32>)>,
+}
+
+impl GeometryAllocator {
+    pub fn new() -> Self {
+        let (freed_sender, freed_receiver) = crossbeam_channel::unbounded();
+        Self {
+            pages: Vec::new(),
+            freed_sender,
+            freed_receiver,
+        }
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// (vertex count, index count) of page
+    pub fn page_size(&self, page: usize) -> (u32, u32) {
+        let (vertices, indices) = &self.pages[page];
+        (vertices.size(), indices.size())
+    }
+
+    /// (vertex count, index count) in use
+    pub fn used_size(&self) -> (u32, u32) {
+        self.pages.iter().fold((0, 0), |(v, i), (vertices, indices)| {
+            (
+                v + vertices.size() - vertices.free_size(),
+                i + indices.size() - indices.free_size(),
+            )
+        })
+    }
+
+    /// ranges in first page with enough space. new page is added if no page has space
+    pub fn alloc(&mut self, vertex_count: u32, index_count: u32) -> GeometryAlloc {
+        self.reclaim();
+
+        for (page, (vertices, indices)) in self.pages.iter_mut().enumerate() {
+            if let Some(vertex_range) = vertices.alloc(vertex_count) {
+                if let Some(index_range) = indices.alloc(index_count) {
+                    return self.make_alloc(page, vertex_range, index_range);
+                }
+                vertices.free(vertex_range);
+            }
+        }
+
+        let mut vertices = RangeAllocator::new(vertex_count.max(PAGE_VERTEX_COUNT));
+        let mut indices = RangeAllocator::new(index_count.max(PAGE_INDEX_COUNT));
+        let vertex_range = vertices.alloc(vertex_count).unwrap();
+        let index_range = indices.alloc(index_count).unwrap();
+        self.pages.push((vertices, indices));
+
+        self.make_alloc(self.pages.len() - 1, vertex_range, index_range)
+    }
+
+    /// return dropped allocations to pages
+    fn reclaim(&mut self) {
+        while let Ok((page, vertex_range, index_range)) = self.freed_receiver.try_recv() {
+            let (vertices, indices) = &mut self.pages[page];
+            vertices.free(vertex_range);
+            indices.free(index_range);
+        }
+    }
+
+    fn make_alloc(&self, page: usize, vertices: Range<u32>, indices: Range<u32>) -> GeometryAlloc {
+        GeometryAlloc {
+            page,
+            vertices,
+            indices,
+            freed: self.freed_sender.clone(),
+        }
+    }
+}
+
+/// vertices and indices of chunks suballocated from a few large buffers
+pub(crate) struct GeometryArena {
+    allocator: GeometryAllocator,
+    pages: Vec<(wgpu::Buffer, wgpu::Buffer)>, // vertex, index
+}
+
+impl GeometryArena {
+    pub fn new() -> Self {
+        Self {
+            allocator: GeometryAllocator::new(),
+            pages: Vec::new(),
+        }
+    }
+
+    /// upload mesh to arena
+    pub fn alloc(&mut self, mesh: &ChunkMesh, device: &wgpu::Device, queue: &wgpu::Queue) -> GeometryAlloc {
+        let alloc = self
+            .allocator
+            .alloc(mesh.vertices.len() as u32, mesh.indices.len() as u32);
+
+        while self.pages.len() < self.allocator.page_count() {
+            let (vertex_count, index_count) = self.allocator.page_size(self.pages.len());
+            let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("chunk arena vertex buffer"),
+                size: vertex_count as u64 * std::mem::size_of::<ChunkVertex>() as u64,
+                usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
+                mapped_at_creation: false,
+            });
+            let index_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("chunk arena index buffer"),
+                size: index_count as u64 * std::mem::size_of::<u32>() as u64,
+                usage: wgpu::BufferUsage::INDEX | wgpu::BufferUsage::COPY_DST,
+                mapped_at_creation: false,
+            });
+            self.pages.push((vertex_buffer, index_buffer));
+        }
+
+        let (vertex_buffer, index_buffer) = &self.pages[alloc.page];
+        if !mesh.vertices.is_empty() {
+            let offset = alloc.vertices.start as u64 * std::mem::size_of::<ChunkVertex>() as u64;
+            queue.write_buffer(vertex_buffer, offset, bytemuck::cast_slice(&mesh.vertices));
+        }
+        if !mesh.indices.is_empty() {
+            let offset = alloc.indices.start as u64 * std::mem::size_of::<u32>() as u64;
+            queue.write_buffer(index_buffer, offset, bytemuck::cast_slice(&mesh.indices));
+        }
+
+        alloc
+    }
+
+    /// overwrite indices of allocation. count of indices should be same
+    pub fn write_indices(&self, alloc: &GeometryAlloc, indices: &[u32], queue: &wgpu::Queue) {
+        debug_assert_eq!(indices.len() as u32, alloc.indices.end - alloc.indices.start);
+        if indices.is_empty() {
+            return;
+        }
+
+        let (_, index_buffer) = &self.pages[alloc.page];
+        let offset = alloc.indices.start as u64 * std::mem::size_of::<u32>() as u64;
+        queue.write_buffer(index_buffer, offset, bytemuck::cast_slice(indices));
+    }
+
+    /// (vertex buffer, index buffer) of page
+    pub fn page_buffers(&self, page: usize) -> (&wgpu::Buffer, &wgpu::Buffer) {
+        let (vertex_buffer, index_buffer) = &self.pages[page];
+        (vertex_buffer, index_buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_range_alloc_and_merge() {
+        let mut allocator = RangeAllocator::new(100);
+        let a = allocator.alloc(10).unwrap();
+        let b = allocator.alloc(20).unwrap();
+        let c = allocator.alloc(30).unwrap();
+        assert_eq!((a.clone(), b.clone(), c.clone()), (0..10, 10..30, 30..60));
+        assert_eq!(allocator.free_size(), 40);
+        assert!(allocator.alloc(41).is_none());
+
+        // hole between a and c is reused first
+        allocator.free(b);
+        assert_eq!(allocator.alloc(15), Some(10..25));
+
+        // freed neighbors are merged into one range
+        allocator.free(10..25);
+        allocator.free(a);
+        allocator.free(c);
+        assert_eq!(allocator.free_size(), 100);
+        assert_eq!(allocator.alloc(100), Some(0..100));
+    }
+
+    #[test]
+    fn test_geometry_alloc_reused_after_drop() {
+        let mut allocator = GeometryAllocator::new();
+        let a = allocator.alloc(100, 150);
+        let b = allocator.alloc(100, 150);
+        assert_eq!((a.page, a.vertices.clone(), a.indices.clone()), (0, 0..100, 0..150));
+        assert_eq!((b.page, b.vertices.clone(), b.indices.clone()), (0, 100..200, 150..300));
+        assert_eq!(allocator.used_size(), (200, 300));
+
+        drop(a);
+        let c = allocator.alloc(50, 60);
+        assert_eq!((c.page, c.vertices.clone(), c.indices.clone()), (0, 0..50, 0..60));
+        assert_eq!(allocator.used_size(), (150, 210));
+        assert_eq!(allocator.page_count(), 1);
+    }
+
+    #[test]
+    fn test_geometry_alloc_new_page() {
+        let mut allocator = GeometryAllocator::new();
+        let a = allocator.alloc(PAGE_VERTEX_COUNT - 10, 10);
+
+        // vertices don't fit in first page
+        let b = allocator.alloc(20, 10);
+        assert_eq!((a.page, b.page), (0, 1));
+
+        // mesh larger than page gets its own page
+        let c = allocator.alloc(PAGE_VERTEX_COUNT * 2, PAGE_INDEX_COUNT * 2);
+        assert_eq!(c.page, 2);
+        assert_eq!(allocator.page_size(2), (PAGE_VERTEX_COUNT * 2, PAGE_INDEX_COUNT * 2));
+
+        // small one fits in first page again
+        let d = allocator.alloc(10, 10);
+        assert_eq!(d.page, 0);
+    }
+}
@@ -1,6 +1,12 @@
 mod chunk;
 use chunk::ChunkRenderer;
 
+mod chunk_mesh;
+use chunk_mesh::{ChunkMesh, ChunkVertex, TextureLayer, TransparentQuads};
+
+mod chunk_mesher;
+use chunk_mesher::{BuiltChunk, ChunkMesher};
+
 mod commands;
 pub use commands::Command;
 
@@ -15,7 +21,27 @@ mod shader_hash;
 use shader_hash::ShaderHash;
 
 mod chunk_cache;
-use chunk_cache::ChunkCache;
+use chunk_cache::{ChunkCache, ChunkCacheStats};
+
+mod chunk_transforms;
+use chunk_transforms::{ChunkTransforms, TransformSlot};
+
+mod draw_list;
+use draw_list::{DrawIndexedArgs, DrawList, DrawRun, IndirectBuffer};
+
+mod geometry_arena;
+use geometry_arena::{GeometryAlloc, GeometryArena};
+
+mod shadow;
+use shadow::ShadowMap;
+
+mod sky;
+use sky::SkyRenderer;
+
+mod post_process;
+use post_process::PostProcessRenderer;
+
+mod render_graph;
 
 mod common_uniforms;
 use common_uniforms::CommonUniforms;
@@ -0,0 +1,534 @@
+use voxrs_asset::{AssetBuildResult, AssetHash, AssetManager, ShaderAsset};
+use voxrs_rhi::HDR_FORMAT;
+use voxrs_types::io::FileSystem;
+
+use crate::blueprint::PostProcess;
+
+use super::render_graph::{RenderGraph, ResourceId, TextureDesc, TexturePool};
+
+/// same as swap chain
+const LDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Bgra8UnormSrgb;
+
+/// hdr scene to swap chain. bloom, tone mapping, color grading and fxaa with full screen triangles
+pub struct PostProcessRenderer {
+    uniform_buffer: wgpu::Buffer,
+    uniform_bind_group: wgpu::BindGroup,
+    blur_horizontal: BlurDirection,
+    blur_vertical: BlurDirection,
+    lut_bind_group_layout: wgpu::BindGroupLayout,
+    neutral_lut: NeutralLut,
+    bright_pipeline: wgpu::RenderPipeline,
+    blur_pipeline: wgpu::RenderPipeline,
+    tone_map_pipeline: wgpu::RenderPipeline,
+    fxaa_pipeline: wgpu::RenderPipeline,
+    lut_hash: Option<AssetHash>, // color lut of lut_bind_group
+    lut_bind_group: Option<wgpu::BindGroup>,
+    lut_error: Option<AssetHash>, // color lut whose failed load or build is logged
+    size: (u32, u32),
+}
+
+struct BlurDirection {
+    bind_group: wgpu::BindGroup,
+    _buffer: wgpu::Buffer,
+}
+
+/// bound when color grading is off. never sampled
+struct NeutralLut {
+    bind_group: wgpu::BindGroup,
+    _texture: wgpu::Texture,
+}
+
+/// shared by all post passes
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct PostUniform {
+    params: [f32; 4], // exposure, bloom threshold, bloom intensity, color lut enabled
+    texel: [f32; 4],  // xy : texel size of frame, zw : texel size of bloom
+}
+
+impl PostUniform {
+    fn new(settings: &PostProcess, width: u32, height: u32, lut_enabled: bool) -> Self {
+        let (bloom_width, bloom_height) = bloom_size(width, height);
+        let (threshold, intensity) = settings
+            .bloom
+            .map_or((0.0, 0.0), |bloom| (bloom.threshold, bloom.intensity));
+        Self {
+            params: [
+                settings.exposure,
+                threshold,
+                intensity,
+                if lut_enabled { 1.0 } else { 0.0 },
+            ],
+            texel: [
+                1.0 / width as f32,
+                1.0 / height as f32,
+                1.0 / bloom_width as f32,
+                1.0 / bloom_height as f32,
+            ],
+        }
+    }
+}
+
+/// bloom is blurred at half resolution
+fn bloom_size(width: u32, height: u32) -> (u32, u32) {
+    ((width / 2).max(1), (height / 2).max(1))
+}
+
+impl PostProcessRenderer {
+    pub fn new<F: FileSystem>(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        texture_pool: &TexturePool,
+        asset_manager: &mut AssetManager<F>,
+    ) -> Self {
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("post process uniform buffer"),
+            size: std::mem::size_of::<PostUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let uniform_bind_group_layout = create_uniform_bind_group_layout(device, "post process");
+        let uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("post process uniform bind group"),
+            layout: &uniform_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        // blur directions never change
+        let blur_bind_group_layout = create_uniform_bind_group_layout(device, "bloom blur");
+        let blur_horizontal = BlurDirection::new([1.0, 0.0], &blur_bind_group_layout, device, queue);
+        let blur_vertical = BlurDirection::new([0.0, 1.0], &blur_bind_group_layout, device, queue);
+
+        let lut_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("color lut bind group layout"),
+            entries: &[
+                // lut texture
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                // sampler
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler {
+                        filtering: true,
+                        comparison: false,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let neutral_lut = NeutralLut::new(device, &lut_bind_group_layout);
+
+        let bright_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("bloom bright pipeline layout"),
+            bind_group_layouts: &[texture_pool.sampled_bind_group_layout(1), &uniform_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let blur_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("bloom blur pipeline layout"),
+            bind_group_layouts: &[
+                texture_pool.sampled_bind_group_layout(1),
+                &uniform_bind_group_layout,
+                &blur_bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+        let tone_map_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("tone map pipeline layout"),
+            bind_group_layouts: &[
+                texture_pool.sampled_bind_group_layout(2),
+                &uniform_bind_group_layout,
+                &lut_bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+        let fxaa_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("fxaa pipeline layout"),
+            bind_group_layouts: &[texture_pool.sampled_bind_group_layout(1), &uniform_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let bright_pipeline = create_post_pipeline(
+            device,
+            &bright_layout,
+            "assets/shaders/bloom_bright_shader.frag.spv",
+            HDR_FORMAT,
+            asset_manager,
+        );
+        let blur_pipeline = create_post_pipeline(
+            device,
+            &blur_layout,
+            "assets/shaders/bloom_blur_shader.frag.spv",
+            HDR_FORMAT,
+            asset_manager,
+        );
+        let tone_map_pipeline = create_post_pipeline(
+            device,
+            &tone_map_layout,
+            "assets/shaders/tone_map_shader.frag.spv",
+            LDR_FORMAT,
+            asset_manager,
+        );
+        let fxaa_pipeline = create_post_pipeline(
+            device,
+            &fxaa_layout,
+            "assets/shaders/fxaa_shader.frag.spv",
+            LDR_FORMAT,
+            asset_manager,
+        );
+
+        Self {
+            uniform_buffer,
+            uniform_bind_group,
+            blur_horizontal,
+            blur_vertical,
+            lut_bind_group_layout,
+            neutral_lut,
+            bright_pipeline,
+            blur_pipeline,
+            tone_map_pipeline,
+            fxaa_pipeline,
+            lut_hash: None,
+            lut_bind_group: None,
+            lut_error: None,
+            size: (1, 1),
+        }
+    }
+
+    /// changed color lut is used from frame it is loaded and built. until then previous lut is kept
+    /// never blocks on loading lut
+    #[profiling::function]
+    pub fn prepare(
+        &mut self,
+        settings: &PostProcess,
+        width: u32,
+        height: u32,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        self.size = (width, height);
+
+        let hash = settings.color_lut.as_ref().map(|handle| handle.asset_hash());
+        if hash != self.lut_hash {
+            match &settings.color_lut {
+                None => {
+                    self.lut_hash = None;
+                    self.lut_bind_group = None;
+                }
+                Some(handle) if handle.is_failed() => self.fail_lut(hash),
+                Some(handle) if handle.is_loaded() => {
+                    let asset = handle.get_asset();
+                    match &asset.texture {
+                        AssetBuildResult::Ok(texture) => {
+                            // lut is sampled at texel centers, so sampler of texture can be used
+                            self.lut_bind_group = Some(device.create_bind_group(&wgpu::BindGroupDescriptor {
+                                label: Some("color lut bind group"),
+                                layout: &self.lut_bind_group_layout,
+                                entries: &[
+                                    wgpu::BindGroupEntry {
+                                        binding: 0,
+                                        resource: wgpu::BindingResource::TextureView(&texture.view),
+                                    },
+                                    wgpu::BindGroupEntry {
+                                        binding: 1,
+                                        resource: wgpu::BindingResource::Sampler(&texture.sampler),
+                                    },
+                                ],
+                            }));
+                            self.lut_hash = hash;
+                        }
+                        AssetBuildResult::NotBuilt => {}
+                        AssetBuildResult::Err(_) => self.fail_lut(hash),
+                    }
+                }
+                Some(_) => {}
+            }
+        }
+
+        let uniform = PostUniform::new(settings, width, height, self.lut_bind_group.is_some());
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
+
+    /// lut is failed to load or build. draw without color grading, and log once per lut
+    fn fail_lut(&mut self, hash: Option<AssetHash>) {
+        if self.lut_error != hash {
+            log::error!("color lut is not loaded or built. draw without color grading");
+            self.lut_error = hash;
+        }
+        self.lut_bind_group = None;
+    }
+
+    /// reads hdr scene and writes frame. should be added before ui so ui is not post processed
+    pub(crate) fn add_passes<'r>(
+        &'r self,
+        settings: &PostProcess,
+        scene: ResourceId,
+        frame: ResourceId,
+        graph: &mut RenderGraph<'r>,
+    ) {
+        let (width, height) = self.size;
+        let clear = wgpu::LoadOp::Clear(wgpu::Color::BLACK);
+
+        // without bloom, scene is bound again and bloom intensity is 0
+        let bloom = match settings.bloom {
+            Some(_) => {
+                let (bloom_width, bloom_height) = bloom_size(width, height);
+                let desc = TextureDesc {
+                    width: bloom_width,
+                    height: bloom_height,
+                    format: HDR_FORMAT,
+                    usage: wgpu::TextureUsage::RENDER_ATTACHMENT | wgpu::TextureUsage::SAMPLED,
+                };
+                let bright = graph.create_texture("bloom bright", desc);
+                let half_blurred = graph.create_texture("bloom blur", desc);
+                let bloom = graph.create_texture("bloom", desc);
+
+                graph
+                    .add_pass("bloom bright pass")
+                    .sample(scene)
+                    .color(bright, clear)
+                    .record(move |pass, ctx| self.draw(&self.bright_pipeline, ctx.sampled_bind_group(), None, pass));
+                graph
+                    .add_pass("bloom horizontal blur pass")
+                    .sample(bright)
+                    .color(half_blurred, clear)
+                    .record(move |pass, ctx| {
+                        let dir = &self.blur_horizontal.bind_group;
+                        self.draw(&self.blur_pipeline, ctx.sampled_bind_group(), Some(dir), pass)
+                    });
+                graph
+                    .add_pass("bloom vertical blur pass")
+                    .sample(half_blurred)
+                    .color(bloom, clear)
+                    .record(move |pass, ctx| {
+                        let dir = &self.blur_vertical.bind_group;
+                        self.draw(&self.blur_pipeline, ctx.sampled_bind_group(), Some(dir), pass)
+                    });
+                bloom
+            }
+            None => scene,
+        };
+
+        let ldr = if settings.fxaa {
+            graph.create_texture(
+                "ldr",
+                TextureDesc {
+                    width,
+                    height,
+                    format: LDR_FORMAT,
+                    usage: wgpu::TextureUsage::RENDER_ATTACHMENT | wgpu::TextureUsage::SAMPLED,
+                },
+            )
+        } else {
+            frame
+        };
+
+        let lut = self.lut_bind_group.as_ref().unwrap_or(&self.neutral_lut.bind_group);
+        graph
+            .add_pass("tone map pass")
+            .sample(scene)
+            .sample(bloom)
+            .color(ldr, clear)
+            .record(move |pass, ctx| self.draw(&self.tone_map_pipeline, ctx.sampled_bind_group(), Some(lut), pass));
+
+        if settings.fxaa {
+            graph
+                .add_pass("fxaa pass")
+                .sample(ldr)
+                .color(frame, clear)
+                .record(move |pass, ctx| self.draw(&self.fxaa_pipeline, ctx.sampled_bind_group(), None, pass));
+        }
+    }
+
+    fn draw<'a>(
+        &'a self,
+        pipeline: &'a wgpu::RenderPipeline,
+        sampled: &'a wgpu::BindGroup,
+        extra: Option<&'a wgpu::BindGroup>,
+        render_pass: &mut wgpu::RenderPass<'a>,
+    ) {
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, sampled, &[]);
+        render_pass.set_bind_group(1, &self.uniform_bind_group, &[]);
+        if let Some(extra) = extra {
+            render_pass.set_bind_group(2, extra, &[]);
+        }
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+fn create_uniform_bind_group_layout(device: &wgpu::Device, label: &str) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some(&format!("{} uniform bind group layout", label)),
+        entries: &[wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStage::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }],
+    })
+}
+
+impl BlurDirection {
+    /// dir never changes
+    fn new(dir: [f32; 2], layout: &wgpu::BindGroupLayout, device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("bloom blur uniform buffer"),
+            size: std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+        queue.write_buffer(&buffer, 0, bytemuck::cast_slice(&[dir[0], dir[1], 0.0, 0.0]));
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("bloom blur bind group"),
+            layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+
+        Self {
+            bind_group,
+            _buffer: buffer,
+        }
+    }
+}
+
+impl NeutralLut {
+    /// 1x1 placeholder. every bind group of pipeline layout should be set even if shader skips it
+    fn new(device: &wgpu::Device, layout: &wgpu::BindGroupLayout) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("neutral color lut"),
+            size: wgpu::Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsage::SAMPLED,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor::default());
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("neutral color lut bind group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+
+        Self {
+            bind_group,
+            _texture: texture,
+        }
+    }
+}
+
+/// post passes have no depth attachment
+fn create_post_pipeline<F: FileSystem>(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    frag_shader: &str,
+    format: wgpu::TextureFormat,
+    asset_manager: &mut AssetManager<F>,
+) -> wgpu::RenderPipeline {
+    let vs_handle = asset_manager.get::<ShaderAsset>(&"assets/shaders/post_shader.vert.spv".into());
+    let fs_handle = asset_manager.get::<ShaderAsset>(&frag_shader.into());
+    let vs_asset = vs_handle.get_asset();
+    let fs_asset = fs_handle.get_asset();
+    let vs_module = vs_asset.module.as_ref().unwrap();
+    let fs_module = fs_asset.module.as_ref().unwrap();
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("post process pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: &vs_module,
+            entry_point: "main",
+            buffers: &[],
+        },
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Cw,
+            cull_mode: None,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            clamp_depth: false,
+            conservative: false,
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &fs_module,
+            entry_point: "main",
+            targets: &[wgpu::ColorTargetState {
+                format,
+                blend: None,
+                write_mask: wgpu::ColorWrite::ALL,
+            }],
+        }),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blueprint::Bloom;
+
+    #[test]
+    fn test_post_uniform() {
+        let settings = PostProcess {
+            exposure: 2.0,
+            bloom: Some(Bloom {
+                threshold: 0.8,
+                intensity: 0.3,
+            }),
+            ..Default::default()
+        };
+        let uniform = PostUniform::new(&settings, 800, 601, true);
+        assert_eq!(uniform.params, [2.0, 0.8, 0.3, 1.0]);
+        assert_eq!(uniform.texel, [1.0 / 800.0, 1.0 / 601.0, 1.0 / 400.0, 1.0 / 300.0]);
+
+        // bloom off adds nothing
+        let settings = PostProcess {
+            bloom: None,
+            ..Default::default()
+        };
+        let uniform = PostUniform::new(&settings, 1, 1, false);
+        assert_eq!(uniform.params, [1.0, 0.0, 0.0, 0.0]);
+        assert_eq!(uniform.texel, [1.0, 1.0, 1.0, 1.0]);
+    }
+}
@@ -0,0 +1,550 @@
+use std::collections::{BTreeSet, HashMap};
+use std::marker::PhantomData;
+
+use super::TexturePool;
+
+/// texture used by passes of render graph
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct ResourceId(usize);
+
+/// transient texture allocated by graph. textures with same desc share memory when their lifetimes don't overlap
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct TextureDesc {
+    pub width: u32,
+    pub height: u32,
+    pub format: wgpu::TextureFormat,
+    pub usage: wgpu::TextureUsage,
+}
+
+/// color and depth targets which scene passes draw into
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct FrameTargets {
+    pub color: ResourceId,
+    pub depth: ResourceId,
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum GraphError {
+    UnwrittenTexture { pass: &'static str, texture: &'static str }, // transient is read before any pass writes it
+}
+
+impl std::fmt::Display for GraphError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GraphError::UnwrittenTexture { pass, texture } => {
+                write!(f, "pass {} reads texture {} before any pass writes it", pass, texture)
+            }
+        }
+    }
+}
+
+enum ResourceKind {
+    Imported, // owned outside graph. passes writing it are never culled
+    Transient(TextureDesc),
+}
+
+struct ResourceNode {
+    name: &'static str,
+    kind: ResourceKind,
+}
+
+/// scope of pass while it records. bind groups borrowed for whole frame outlive the pass
+pub(crate) struct PassContext<'p, 'r: 'p> {
+    sampled: Option<&'p wgpu::BindGroup>,
+    _scope: PhantomData<&'p &'r ()>,
+}
+
+impl<'p, 'r> PassContext<'p, 'r> {
+    /// textures declared with sample in order, then linear clamp sampler
+    /// layout is sampled_bind_group_layout of texture pool
+    pub fn sampled_bind_group(&self) -> &'p wgpu::BindGroup {
+        self.sampled.expect("pass samples no texture")
+    }
+}
+
+type RecordFn<'r> = Box<dyn for<'p> FnOnce(&mut wgpu::RenderPass<'p>, &PassContext<'p, 'r>) + 'r>;
+
+struct PassNode<'r> {
+    name: &'static str,
+    colors: Vec<(ResourceId, wgpu::LoadOp<wgpu::Color>)>,
+    depth: Option<(ResourceId, wgpu::LoadOp<f32>)>,
+    reads: Vec<ResourceId>,
+    sampled: Vec<ResourceId>,
+    record: RecordFn<'r>,
+}
+
+impl<'r> PassNode<'r> {
+    fn writes(&self) -> impl Iterator<Item = ResourceId> + '_ {
+        self.colors
+            .iter()
+            .map(|(id, _)| *id)
+            .chain(self.depth.map(|(id, _)| id))
+    }
+}
+
+/// passes of 1 frame with textures they draw into and read
+/// passes run in order they are added. pass reads what the last pass added before it wrote
+pub(crate) struct RenderGraph<'r> {
+    resources: Vec<ResourceNode>,
+    imported_views: HashMap<ResourceId, &'r wgpu::TextureView>,
+    passes: Vec<PassNode<'r>>,
+}
+
+/// declares attachments and reads of pass. pass is added to graph with its record function
+pub(crate) struct PassBuilder<'g, 'r> {
+    graph: &'g mut RenderGraph<'r>,
+    name: &'static str,
+    colors: Vec<(ResourceId, wgpu::LoadOp<wgpu::Color>)>,
+    depth: Option<(ResourceId, wgpu::LoadOp<f32>)>,
+    reads: Vec<ResourceId>,
+    sampled: Vec<ResourceId>,
+}
+
+impl<'g, 'r> PassBuilder<'g, 'r> {
+    pub fn color(mut self, id: ResourceId, load: wgpu::LoadOp<wgpu::Color>) -> Self {
+        self.colors.push((id, load));
+        self
+    }
+
+    pub fn depth(mut self, id: ResourceId, load: wgpu::LoadOp<f32>) -> Self {
+        self.depth = Some((id, load));
+        self
+    }
+
+    /// texture sampled by pass through its own bind groups. pass reads last write added before it
+    pub fn read(mut self, id: ResourceId) -> Self {
+        self.reads.push(id);
+        self
+    }
+
+    /// texture read through sampled bind group which graph creates for pass
+    pub fn sample(mut self, id: ResourceId) -> Self {
+        self.reads.push(id);
+        self.sampled.push(id);
+        self
+    }
+
+    pub fn record(self, record: impl for<'p> FnOnce(&mut wgpu::RenderPass<'p>, &PassContext<'p, 'r>) + 'r) {
+        self.graph.passes.push(PassNode {
+            name: self.name,
+            colors: self.colors,
+            depth: self.depth,
+            reads: self.reads,
+            sampled: self.sampled,
+            record: Box::new(record),
+        });
+    }
+}
+
+/// order of passes to run and memory of transient textures
+#[derive(Debug)]
+pub(crate) struct CompiledGraph {
+    order: Vec<usize>,                 // indices of passes. culled passes are left out
+    slots: HashMap<ResourceId, usize>, // slot of texture pool for each used transient
+    slot_descs: Vec<TextureDesc>,
+}
+
+impl CompiledGraph {
+    pub fn texture_descs(&self) -> &[TextureDesc] {
+        &self.slot_descs
+    }
+}
+
+impl<'r> RenderGraph<'r> {
+    pub fn new() -> Self {
+        Self {
+            resources: Vec::new(),
+            imported_views: HashMap::new(),
+            passes: Vec::new(),
+        }
+    }
+
+    /// texture owned outside graph like swap chain frame
+    pub fn import(&mut self, name: &'static str, view: &'r wgpu::TextureView) -> ResourceId {
+        let id = self.declare(name, ResourceKind::Imported);
+        self.imported_views.insert(id, view);
+        id
+    }
+
+    /// texture which lives only while passes of this frame use it
+    pub fn create_texture(&mut self, name: &'static str, desc: TextureDesc) -> ResourceId {
+        self.declare(name, ResourceKind::Transient(desc))
+    }
+
+    pub fn add_pass<'g>(&'g mut self, name: &'static str) -> PassBuilder<'g, 'r> {
+        PassBuilder {
+            graph: self,
+            name,
+            colors: Vec::new(),
+            depth: None,
+            reads: Vec::new(),
+            sampled: Vec::new(),
+        }
+    }
+
+    fn declare(&mut self, name: &'static str, kind: ResourceKind) -> ResourceId {
+        self.resources.push(ResourceNode { name, kind });
+        ResourceId(self.resources.len() - 1)
+    }
+
+    /// culls passes whose output is never used. other passes run in order they are added
+    /// transients are assigned to texture slots, aliased when lifetimes don't overlap
+    pub fn compile(&self) -> Result<CompiledGraph, GraphError> {
+        // passes whose output each pass uses: last pass added before it which writes texture it reads or draws into
+        // writer added after readers of texture runs after them, since passes keep their order
+        let mut deps: Vec<BTreeSet<usize>> = vec![BTreeSet::new(); self.passes.len()];
+        let mut last_writers: HashMap<ResourceId, usize> = HashMap::new();
+        for (idx, pass) in self.passes.iter().enumerate() {
+            for id in &pass.reads {
+                match last_writers.get(id) {
+                    Some(writer) => {
+                        deps[idx].insert(*writer);
+                    }
+                    None => {
+                        let resource = &self.resources[id.0];
+                        if matches!(resource.kind, ResourceKind::Transient(_)) {
+                            return Err(GraphError::UnwrittenTexture {
+                                pass: pass.name,
+                                texture: resource.name,
+                            });
+                        }
+                    }
+                }
+            }
+
+            for id in pass.writes() {
+                if let Some(writer) = last_writers.insert(id, idx) {
+                    if writer != idx {
+                        deps[idx].insert(writer);
+                    }
+                }
+            }
+        }
+
+        // passes writing imported textures and everything they depend on
+        let mut kept = vec![false; self.passes.len()];
+        let mut stack = self
+            .passes
+            .iter()
+            .enumerate()
+            .filter(|(_, pass)| {
+                pass.writes()
+                    .any(|id| matches!(self.resources[id.0].kind, ResourceKind::Imported))
+            })
+            .map(|(idx, _)| idx)
+            .collect::<Vec<_>>();
+        while let Some(idx) = stack.pop() {
+            if !kept[idx] {
+                kept[idx] = true;
+                stack.extend(deps[idx].iter().copied());
+            }
+        }
+
+        let order = (0..self.passes.len()).filter(|idx| kept[*idx]).collect::<Vec<_>>();
+
+        // first and last position in order of each transient
+        let mut lifetimes: HashMap<ResourceId, (usize, usize)> = HashMap::new();
+        for (pos, idx) in order.iter().enumerate() {
+            let pass = &self.passes[*idx];
+            for id in pass.writes().chain(pass.reads.iter().copied()) {
+                if let ResourceKind::Transient(_) = self.resources[id.0].kind {
+                    let lifetime = lifetimes.entry(id).or_insert((pos, pos));
+                    lifetime.1 = pos;
+                }
+            }
+        }
+
+        let mut transients = lifetimes.into_iter().collect::<Vec<_>>();
+        transients.sort_unstable_by_key(|(id, (first, _))| (*first, id.0));
+
+        let mut slots = HashMap::new();
+        let mut slot_descs = Vec::new();
+        let mut slot_ends: Vec<usize> = Vec::new();
+        for (id, (first, last)) in transients {
+            let desc = match self.resources[id.0].kind {
+                ResourceKind::Transient(desc) => desc,
+                ResourceKind::Imported => unreachable!(),
+            };
+            let free = (0..slot_descs.len()).find(|slot| slot_descs[*slot] == desc && slot_ends[*slot] < first);
+            let slot = match free {
+                Some(slot) => {
+                    slot_ends[slot] = last;
+                    slot
+                }
+                None => {
+                    slot_descs.push(desc);
+                    slot_ends.push(last);
+                    slot_descs.len() - 1
+                }
+            };
+            slots.insert(id, slot);
+        }
+
+        Ok(CompiledGraph {
+            order,
+            slots,
+            slot_descs,
+        })
+    }
+
+    /// records passes in compiled order. textures of pool should be prepared with compiled graph
+    #[profiling::function]
+    pub fn execute(
+        self,
+        compiled: &CompiledGraph,
+        pool: &'r TexturePool,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        let imported_views = self.imported_views;
+        let slots = &compiled.slots;
+        let view = |id: ResourceId| -> &'r wgpu::TextureView {
+            match imported_views.get(&id) {
+                Some(view) => view,
+                None => pool.view(slots[&id]),
+            }
+        };
+
+        // bind groups should outlive every pass. culled passes have none
+        let mut sampled_bind_groups = (0..self.passes.len()).map(|_| None).collect::<Vec<_>>();
+        for idx in &compiled.order {
+            let pass = &self.passes[*idx];
+            if pass.sampled.is_empty() {
+                continue;
+            }
+
+            let mut entries = pass
+                .sampled
+                .iter()
+                .enumerate()
+                .map(|(binding, id)| wgpu::BindGroupEntry {
+                    binding: binding as u32,
+                    resource: wgpu::BindingResource::TextureView(view(*id)),
+                })
+                .collect::<Vec<_>>();
+            entries.push(wgpu::BindGroupEntry {
+                binding: pass.sampled.len() as u32,
+                resource: wgpu::BindingResource::Sampler(pool.sampler()),
+            });
+            sampled_bind_groups[*idx] = Some(device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some(pass.name),
+                layout: pool.sampled_bind_group_layout(pass.sampled.len()),
+                entries: &entries,
+            }));
+        }
+
+        let mut passes = self.passes.into_iter().map(Some).collect::<Vec<_>>();
+        for idx in &compiled.order {
+            let pass = passes[*idx].take().unwrap();
+
+            let color_attachments = pass
+                .colors
+                .iter()
+                .map(|(id, load)| wgpu::RenderPassColorAttachment {
+                    view: view(*id),
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: *load,
+                        store: true,
+                    },
+                })
+                .collect::<Vec<_>>();
+            let depth_stencil_attachment = pass.depth.map(|(id, load)| wgpu::RenderPassDepthStencilAttachment {
+                view: view(id),
+                depth_ops: Some(wgpu::Operations { load, store: true }),
+                stencil_ops: None,
+            });
+
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some(pass.name),
+                color_attachments: &color_attachments,
+                depth_stencil_attachment,
+            });
+
+            let context = PassContext {
+                sampled: sampled_bind_groups[*idx].as_ref(),
+                _scope: PhantomData,
+            };
+            (pass.record)(&mut render_pass, &context);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn desc(width: u32) -> TextureDesc {
+        TextureDesc {
+            width,
+            height: 600,
+            format: wgpu::TextureFormat::Rgba16Float,
+            usage: wgpu::TextureUsage::RENDER_ATTACHMENT | wgpu::TextureUsage::SAMPLED,
+        }
+    }
+
+    fn pass_names(graph: &RenderGraph, compiled: &CompiledGraph) -> Vec<&'static str> {
+        compiled.order.iter().map(|idx| graph.passes[*idx].name).collect()
+    }
+
+    #[test]
+    fn test_order_by_addition() {
+        let mut graph = RenderGraph::new();
+        let frame = graph.declare("frame", ResourceKind::Imported);
+        let scene = graph.create_texture("scene", desc(800));
+        let shadow = graph.create_texture("shadow", desc(1024));
+
+        graph
+            .add_pass("shadow")
+            .color(shadow, wgpu::LoadOp::Clear(wgpu::Color::WHITE))
+            .record(|_, _| {});
+        graph
+            .add_pass("main")
+            .read(shadow)
+            .color(scene, wgpu::LoadOp::Clear(wgpu::Color::BLACK))
+            .record(|_, _| {});
+        graph
+            .add_pass("post")
+            .sample(scene)
+            .color(frame, wgpu::LoadOp::Load)
+            .record(|_, _| {});
+        graph.add_pass("ui").color(frame, wgpu::LoadOp::Load).record(|_, _| {});
+
+        let compiled = graph.compile().unwrap();
+        assert_eq!(pass_names(&graph, &compiled), vec!["shadow", "main", "post", "ui"]);
+    }
+
+    #[test]
+    fn test_read_between_writes() {
+        let mut graph = RenderGraph::new();
+        let frame = graph.declare("frame", ResourceKind::Imported);
+        let a = graph.create_texture("a", desc(800));
+        let b = graph.create_texture("b", desc(800));
+        let clear = wgpu::LoadOp::Clear(wgpu::Color::BLACK);
+
+        // W1 -> R -> W2. R reads what W1 wrote, and W2 overwrites it after R
+        graph.add_pass("w1").color(a, clear).record(|_, _| {});
+        graph.add_pass("r").read(a).color(b, clear).record(|_, _| {});
+        graph.add_pass("w2").color(a, wgpu::LoadOp::Load).record(|_, _| {});
+        graph
+            .add_pass("final")
+            .read(a)
+            .read(b)
+            .color(frame, wgpu::LoadOp::Load)
+            .record(|_, _| {});
+
+        let compiled = graph.compile().unwrap();
+        assert_eq!(pass_names(&graph, &compiled), vec!["w1", "r", "w2", "final"]);
+
+        // R is culled when its output is not used. W2 still loads what W1 wrote
+        let mut graph = RenderGraph::new();
+        let frame = graph.declare("frame", ResourceKind::Imported);
+        let a = graph.create_texture("a", desc(800));
+        let b = graph.create_texture("b", desc(800));
+        graph.add_pass("w1").color(a, clear).record(|_, _| {});
+        graph.add_pass("r").read(a).color(b, clear).record(|_, _| {});
+        graph.add_pass("w2").color(a, wgpu::LoadOp::Load).record(|_, _| {});
+        graph
+            .add_pass("final")
+            .read(a)
+            .color(frame, wgpu::LoadOp::Load)
+            .record(|_, _| {});
+
+        let compiled = graph.compile().unwrap();
+        assert_eq!(pass_names(&graph, &compiled), vec!["w1", "w2", "final"]);
+    }
+
+    #[test]
+    fn test_cull_unused_pass() {
+        let mut graph = RenderGraph::new();
+        let frame = graph.declare("frame", ResourceKind::Imported);
+        let debug = graph.create_texture("debug", desc(800));
+
+        graph
+            .add_pass("debug")
+            .color(debug, wgpu::LoadOp::Clear(wgpu::Color::BLACK))
+            .record(|_, _| {});
+        graph
+            .add_pass("main")
+            .color(frame, wgpu::LoadOp::Clear(wgpu::Color::BLACK))
+            .record(|_, _| {});
+
+        let compiled = graph.compile().unwrap();
+        assert_eq!(pass_names(&graph, &compiled), vec!["main"]);
+        assert!(compiled.texture_descs().is_empty());
+    }
+
+    #[test]
+    fn test_alias_transients() {
+        let mut graph = RenderGraph::new();
+        let frame = graph.declare("frame", ResourceKind::Imported);
+        let a = graph.create_texture("a", desc(800));
+        let b = graph.create_texture("b", desc(800));
+        let c = graph.create_texture("c", desc(800));
+        let small = graph.create_texture("small", desc(400));
+
+        let clear = wgpu::LoadOp::Clear(wgpu::Color::BLACK);
+        graph.add_pass("write a").color(a, clear).record(|_, _| {});
+        graph.add_pass("a to b").read(a).color(b, clear).record(|_, _| {});
+        graph.add_pass("b to c").read(b).color(c, clear).record(|_, _| {});
+        graph
+            .add_pass("c to small")
+            .read(c)
+            .color(small, clear)
+            .record(|_, _| {});
+        graph
+            .add_pass("small to frame")
+            .read(small)
+            .color(frame, wgpu::LoadOp::Load)
+            .record(|_, _| {});
+
+        let compiled = graph.compile().unwrap();
+
+        // a is dead when c is written. b is still read while c is written
+        assert_eq!(compiled.slots[&a], compiled.slots[&c]);
+        assert_ne!(compiled.slots[&a], compiled.slots[&b]);
+        assert_ne!(compiled.slots[&small], compiled.slots[&a]);
+        assert_ne!(compiled.slots[&small], compiled.slots[&b]);
+        assert_eq!(compiled.texture_descs(), &[desc(800), desc(800), desc(400)]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut graph = RenderGraph::new();
+        let frame = graph.declare("frame", ResourceKind::Imported);
+        let never_written = graph.create_texture("never written", desc(800));
+        graph
+            .add_pass("main")
+            .read(never_written)
+            .color(frame, wgpu::LoadOp::Load)
+            .record(|_, _| {});
+        assert_eq!(
+            graph.compile().unwrap_err(),
+            GraphError::UnwrittenTexture {
+                pass: "main",
+                texture: "never written"
+            }
+        );
+
+        // writer added after reader doesn't write what reader reads
+        let mut graph = RenderGraph::new();
+        let frame = graph.declare("frame", ResourceKind::Imported);
+        let a = graph.create_texture("a", desc(800));
+        graph
+            .add_pass("main")
+            .read(a)
+            .color(frame, wgpu::LoadOp::Load)
+            .record(|_, _| {});
+        graph
+            .add_pass("write a")
+            .color(a, wgpu::LoadOp::Clear(wgpu::Color::BLACK))
+            .record(|_, _| {});
+        assert_eq!(
+            graph.compile().unwrap_err(),
+            GraphError::UnwrittenTexture {
+                pass: "main",
+                texture: "a"
+            }
+        );
+    }
+}
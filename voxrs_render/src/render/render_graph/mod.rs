@@ -0,0 +1,5 @@
+mod graph;
+pub(crate) use graph::{FrameTargets, RenderGraph, ResourceId, TextureDesc};
+
+mod texture_pool;
+pub(crate) use texture_pool::TexturePool;
@@ -0,0 +1,112 @@
+use super::TextureDesc;
+
+/// max textures sampled by 1 pass
+const MAX_SAMPLED: usize = 4;
+
+/// gpu textures backing transients of render graph. textures are kept across frames while descs are same
+/// also layouts of bind groups through which passes sample textures
+pub(crate) struct TexturePool {
+    textures: Vec<(TextureDesc, wgpu::Texture, wgpu::TextureView)>,
+    sampled_layouts: Vec<wgpu::BindGroupLayout>, // for 1 to MAX_SAMPLED textures
+    sampler: wgpu::Sampler,
+}
+
+impl TexturePool {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let sampled_layouts = (1..=MAX_SAMPLED)
+            .map(|count| {
+                let mut entries = (0..count)
+                    .map(|binding| wgpu::BindGroupLayoutEntry {
+                        binding: binding as u32,
+                        visibility: wgpu::ShaderStage::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    })
+                    .collect::<Vec<_>>();
+                entries.push(wgpu::BindGroupLayoutEntry {
+                    binding: count as u32,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler {
+                        filtering: true,
+                        comparison: false,
+                    },
+                    count: None,
+                });
+                device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("render graph sampled bind group layout"),
+                    entries: &entries,
+                })
+            })
+            .collect();
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        Self {
+            textures: Vec::new(),
+            sampled_layouts,
+            sampler,
+        }
+    }
+
+    /// layout of pass which samples count textures
+    pub fn sampled_bind_group_layout(&self, count: usize) -> &wgpu::BindGroupLayout {
+        assert!(
+            (1..=MAX_SAMPLED).contains(&count),
+            "pass can sample 1 to {} textures",
+            MAX_SAMPLED
+        );
+        &self.sampled_layouts[count - 1]
+    }
+
+    pub fn sampler(&self) -> &wgpu::Sampler {
+        &self.sampler
+    }
+
+    /// 1 texture for each slot of compiled graph. textures no longer used are dropped
+    pub fn prepare(&mut self, descs: &[TextureDesc], device: &wgpu::Device) {
+        let mut old = std::mem::take(&mut self.textures);
+
+        for desc in descs {
+            let reused = old
+                .iter()
+                .position(|(old_desc, _, _)| old_desc == desc)
+                .map(|pos| old.swap_remove(pos));
+
+            let texture = reused.unwrap_or_else(|| {
+                let texture = device.create_texture(&wgpu::TextureDescriptor {
+                    label: Some("render graph texture"),
+                    size: wgpu::Extent3d {
+                        width: desc.width,
+                        height: desc.height,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: desc.format,
+                    usage: desc.usage,
+                });
+                let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+                (*desc, texture, view)
+            });
+
+            self.textures.push(texture);
+        }
+    }
+
+    pub fn view(&self, slot: usize) -> &wgpu::TextureView {
+        &self.textures[slot].2
+    }
+}
@@ -1,10 +1,15 @@
-use super::{commands::Command, ChunkRenderer, CommonUniforms, DynamicBlockRenderer, UiRenderer};
+use super::{
+    commands::Command,
+    render_graph::{FrameTargets, RenderGraph, TextureDesc, TexturePool},
+    shadow::build_cascades,
+    ChunkRenderer, CommonUniforms, DynamicBlockRenderer, PostProcessRenderer, ShadowMap, SkyRenderer, UiRenderer,
+};
 use crate::blueprint::{Blueprint, Camera, Text, TextSection, Ui};
 use crossbeam_channel::Receiver;
 use std::thread::{self, JoinHandle};
 use std::{iter, sync::Arc};
 use voxrs_asset::{AssetHandle, AssetManager, FontAsset};
-use voxrs_rhi::Texture;
+use voxrs_rhi::{DEPTH_FORMAT, HDR_FORMAT};
 use voxrs_types::{io::FileSystem, Fps};
 use winit::window::Window;
 
@@ -15,11 +20,14 @@ pub struct Renderer {
     swap_chain_desc: wgpu::SwapChainDescriptor,
     swap_chain: wgpu::SwapChain,
     size: winit::dpi::PhysicalSize<u32>,
-    depth_texture: Texture,
+    texture_pool: TexturePool, // transient textures of render graph
+    sky_renderer: SkyRenderer,
+    post_process_renderer: PostProcessRenderer,
     chunk_renderer: ChunkRenderer,
     dynamic_block_renderer: DynamicBlockRenderer,
     ui_renderer: UiRenderer,
     common_uniforms: CommonUniforms,
+    shadow_map: ShadowMap,
     font: AssetHandle<FontAsset>,
     fps: Fps,
     minimized: bool, // whether main window is minimized
@@ -42,7 +50,8 @@ impl Renderer {
             .request_device(
                 &wgpu::DeviceDescriptor {
                     label: Some("main device"),
-                    features: wgpu::Features::default(),
+                    // chunks are drawn with multi draw indirect if supported
+                    features: adapter.features() & wgpu::Features::MULTI_DRAW_INDIRECT,
                     limits: wgpu::Limits::default(),
                 },
                 None,
@@ -65,12 +74,14 @@ impl Renderer {
         };
         let swap_chain = device.create_swap_chain(&surface, &swap_chain_desc);
 
-        let depth_texture = Texture::create_depth_texture(&device, &swap_chain_desc, "depth_texture");
-
         let mut common_uniforms = CommonUniforms::new(&device);
         common_uniforms.set_screen_to_ndc_mat(size.width, size.height, &queue);
 
-        let chunk_renderer = ChunkRenderer::new(&device, &common_uniforms);
+        let shadow_map = ShadowMap::new(&device);
+        let texture_pool = TexturePool::new(&device);
+        let sky_renderer = SkyRenderer::new(&device, asset_manager);
+        let post_process_renderer = PostProcessRenderer::new(&device, &queue, &texture_pool, asset_manager);
+        let chunk_renderer = ChunkRenderer::new(&device, &common_uniforms, &shadow_map, asset_manager);
         let dynamic_block_renderer = DynamicBlockRenderer::new(&device, &common_uniforms);
         let ui_renderer = UiRenderer::new(&device, &common_uniforms, asset_manager);
         let font = asset_manager.get::<FontAsset>(&"assets/fonts/NanumBarunGothic.ttf".into());
@@ -83,11 +94,14 @@ impl Renderer {
             swap_chain_desc,
             swap_chain,
             size,
-            depth_texture,
+            texture_pool,
+            sky_renderer,
+            post_process_renderer,
             chunk_renderer,
             dynamic_block_renderer,
             ui_renderer,
             common_uniforms,
+            shadow_map,
             font,
             fps,
             minimized: false,
@@ -100,14 +114,38 @@ impl Renderer {
             return Ok(());
         }
 
-        let chunks = self.chunk_renderer.prepare(
+        self.chunk_renderer.set_cache_budget(bp.chunk_cache_budget);
+        self.chunk_renderer.prepare(
             &bp.chunks,
             &bp.world_block_mat_handle.unwrap(),
             bp.block_size,
+            bp.greedy_meshing,
+            bp.camera.eye,
             &self.device,
+            &self.queue,
         );
 
         self.update_camera(&bp.camera);
+        self.common_uniforms.set_light(&bp.environment, &self.queue);
+        self.common_uniforms.set_fog(bp.fog.as_ref(), &self.queue);
+
+        let cascades = if bp.shadow_settings.enabled {
+            build_cascades(&bp.camera, bp.environment.sun_dir, &bp.shadow_settings)
+        } else {
+            Vec::new()
+        };
+        self.shadow_map.update(&cascades, &self.queue);
+
+        self.sky_renderer
+            .prepare(&bp.camera, &bp.environment, &self.device, &self.queue);
+        self.post_process_renderer.prepare(
+            &bp.post_process,
+            self.swap_chain_desc.width,
+            self.swap_chain_desc.height,
+            &self.device,
+            &self.queue,
+        );
+
         let blocks = self
             .dynamic_block_renderer
             .prepare(&bp.dynamic_blocks, &self.device, &self.queue);
@@ -126,6 +164,26 @@ impl Renderer {
         });
         bp.uis.push(text);
 
+        // chunk cache stats of last frame (temp)
+        let stats = self.chunk_renderer.cache_stats();
+        let text = Ui::Text(Text {
+            pos: (20, 45).into(),
+            size: (600, 100).into(),
+            sections: vec![TextSection {
+                font: self.font.clone(),
+                font_size: 20,
+                text: format!(
+                    "chunks hit: {}, miss: {}, remesh: {}, resident: {} ({} KB)",
+                    stats.hits,
+                    stats.misses,
+                    stats.remeshes,
+                    stats.resident_count,
+                    stats.resident_bytes / 1024
+                ),
+            }],
+        });
+        bp.uis.push(text);
+
         let ui_render_infos = self.ui_renderer.prepare(&bp.uis, &self.device, &self.queue);
 
         let frame = self.swap_chain.get_current_frame()?.output;
@@ -135,41 +193,43 @@ impl Renderer {
         });
 
         {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("main render pass"),
-                color_attachments: &[wgpu::RenderPassColorAttachment {
-                    view: &frame.view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.1,
-                            g: 0.2,
-                            b: 0.3,
-                            a: 1.0,
-                        }),
-                        store: true,
-                    },
-                }],
-                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                    view: &self.depth_texture.view,
-                    depth_ops: Some(wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(1.0),
-                        store: true,
-                    }),
-                    stencil_ops: None,
-                }),
-            });
-
-            self.chunk_renderer.render(&chunks, &mut render_pass);
-
-            self.dynamic_block_renderer.render(&blocks, &mut render_pass);
-
-            self.ui_renderer.render(&ui_render_infos, &mut render_pass);
-
-            {
-                profiling::scope!("render_pass::drop");
-                drop(render_pass);
-            }
+            let mut graph = RenderGraph::new();
+            let (width, height) = (self.swap_chain_desc.width, self.swap_chain_desc.height);
+            let output = graph.import("frame", &frame.view);
+            let scene = graph.create_texture(
+                "scene",
+                TextureDesc {
+                    width,
+                    height,
+                    format: HDR_FORMAT,
+                    usage: wgpu::TextureUsage::RENDER_ATTACHMENT | wgpu::TextureUsage::SAMPLED,
+                },
+            );
+            let depth = graph.create_texture(
+                "depth",
+                TextureDesc {
+                    width,
+                    height,
+                    format: DEPTH_FORMAT,
+                    usage: wgpu::TextureUsage::RENDER_ATTACHMENT,
+                },
+            );
+            let scene_targets = FrameTargets { color: scene, depth };
+            let shadow_layers = self.shadow_map.import_layers(&mut graph);
+
+            self.sky_renderer.add_pass(scene_targets, &mut graph);
+            self.chunk_renderer
+                .add_passes(&self.shadow_map, &shadow_layers, scene_targets, &mut graph);
+            self.dynamic_block_renderer.add_pass(&blocks, scene_targets, &mut graph);
+            self.post_process_renderer
+                .add_passes(&bp.post_process, scene, output, &mut graph);
+            // ui is drawn after post process to stay crisp
+            self.ui_renderer
+                .add_pass(&ui_render_infos, FrameTargets { color: output, depth }, &mut graph);
+
+            let compiled = graph.compile().unwrap();
+            self.texture_pool.prepare(compiled.texture_descs(), &self.device);
+            graph.execute(&compiled, &self.texture_pool, &self.device, &mut encoder);
         }
 
         {
@@ -205,8 +265,6 @@ impl Renderer {
         self.swap_chain_desc.height = new_size.height;
         self.swap_chain = self.device.create_swap_chain(&self.surface, &self.swap_chain_desc);
 
-        self.depth_texture = Texture::create_depth_texture(&self.device, &self.swap_chain_desc, "depth_texture");
-
         self.common_uniforms
             .set_screen_to_ndc_mat(new_size.width, new_size.height, &self.queue);
     }
@@ -0,0 +1,408 @@
+use std::num::{NonZeroU32, NonZeroU64};
+
+use voxrs_math::*;
+use voxrs_rhi::DEPTH_FORMAT;
+
+use crate::blueprint::{Camera, ShadowSettings};
+
+use super::render_graph::{RenderGraph, ResourceId};
+
+/// cascades of shadow map. 1 layer of depth texture for each
+pub const MAX_CASCADES: usize = 4;
+
+/// width and height of each cascade in texels
+const SHADOW_MAP_SIZE: u32 = 2048;
+
+/// casters this far toward sun from cascade are still drawn into it
+const CASTER_MARGIN: f32 = 64.0;
+
+/// blend of logarithmic and uniform split distances. 1 : logarithmic only
+const SPLIT_LAMBDA: f32 = 0.75;
+
+/// light view projection of cascade covering camera frustum up to far
+pub(crate) struct Cascade {
+    pub view_proj: Mat4,
+    pub far: f32, // distance from camera
+}
+
+/// cascades fitted to camera frustum. empty if camera has no valid depth range
+pub(crate) fn build_cascades(camera: &Camera, sun_dir: Vec3, settings: &ShadowSettings) -> Vec<Cascade> {
+    let far = settings.distance.min(camera.zfar);
+    if far <= camera.znear {
+        return Vec::new();
+    }
+
+    let count = settings.cascade_count.clamp(1, MAX_CASCADES);
+    let mut near = camera.znear;
+    cascade_splits(camera.znear, far, count, SPLIT_LAMBDA)
+        .into_iter()
+        .map(|split| {
+            let corners = frustum_slice_corners(camera, near, split);
+            near = split;
+            Cascade {
+                view_proj: fit_cascade(&corners, sun_dir, SHADOW_MAP_SIZE),
+                far: split,
+            }
+        })
+        .collect()
+}
+
+/// far distances of cascades from camera. last one is far
+fn cascade_splits(near: f32, far: f32, count: usize, lambda: f32) -> Vec<f32> {
+    (1..=count)
+        .map(|i| {
+            let ratio = i as f32 / count as f32;
+            let log = near * (far / near).powf(ratio);
+            let uniform = near + (far - near) * ratio;
+            lambda * log + (1.0 - lambda) * uniform
+        })
+        .collect()
+}
+
+/// world positions of camera frustum corners between near and far distance
+/// 4 corners on near side, then 4 corners on far side
+fn frustum_slice_corners(camera: &Camera, near: f32, far: f32) -> [Vec3; 8] {
+    let inv_view_proj = camera.view_proj_mat.inverse();
+    let depth_range = camera.zfar - camera.znear;
+    let t_near = (near - camera.znear) / depth_range;
+    let t_far = (far - camera.znear) / depth_range;
+
+    let mut corners = [Vec3::ZERO; 8];
+    for (i, (x, y)) in [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)].iter().enumerate() {
+        // view depth is linear along edge of frustum
+        let near_corner = inv_view_proj.project_point3(Vec3::new(*x, *y, 0.0));
+        let far_corner = inv_view_proj.project_point3(Vec3::new(*x, *y, 1.0));
+        corners[i] = near_corner.lerp(far_corner, t_near);
+        corners[i + 4] = near_corner.lerp(far_corner, t_far);
+    }
+    corners
+}
+
+/// orthographic light view projection covering corners
+/// fitted to bounding sphere and snapped to texels so shadow edges don't shimmer as camera moves
+fn fit_cascade(corners: &[Vec3; 8], sun_dir: Vec3, map_size: u32) -> Mat4 {
+    let center = corners.iter().fold(Vec3::ZERO, |sum, corner| sum + *corner) / 8.0;
+    let radius = corners.iter().map(|corner| corner.distance(center)).fold(0.0, f32::max);
+    let radius = (radius * 16.0).ceil() / 16.0;
+
+    let up = if sun_dir.y.abs() > 0.99 { Vec3::Z } else { Vec3::Y };
+    let eye = center - sun_dir * (radius + CASTER_MARGIN);
+    let view = Mat4::look_at_lh(eye, center, up);
+    let proj = Mat4::orthographic_lh(-radius, radius, -radius, radius, 0.0, radius * 2.0 + CASTER_MARGIN);
+    let view_proj = proj * view;
+
+    // move world origin onto texel grid
+    let half_size = map_size as f32 / 2.0;
+    let origin = view_proj.project_point3(Vec3::ZERO) * half_size;
+    let offset = (origin.round() - origin) / half_size;
+    Mat4::from_translation(Vec3::new(offset.x, offset.y, 0.0)) * view_proj
+}
+
+/// cascades sampled by chunk shaders. vec4s for std140
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct ShadowUniform {
+    view_projs: [[f32; 16]; MAX_CASCADES],
+    splits: [f32; MAX_CASCADES],
+    params: [f32; 4], // cascade count, texel size
+}
+
+/// depth of scene seen from sun. 1 texture layer for each cascade
+pub(crate) struct ShadowMap {
+    layer_views: Vec<wgpu::TextureView>,
+    cascade_buffer: wgpu::Buffer,
+    cascade_bind_group_layout: wgpu::BindGroupLayout,
+    cascade_bind_groups: Vec<wgpu::BindGroup>,
+    sample_buffer: wgpu::Buffer,
+    sample_bind_group_layout: wgpu::BindGroupLayout,
+    sample_bind_group: wgpu::BindGroup,
+    cascade_count: usize,
+    _texture: wgpu::Texture,
+}
+
+impl ShadowMap {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("shadow map"),
+            size: wgpu::Extent3d {
+                width: SHADOW_MAP_SIZE,
+                height: SHADOW_MAP_SIZE,
+                depth_or_array_layers: MAX_CASCADES as u32,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: DEPTH_FORMAT,
+            usage: wgpu::TextureUsage::RENDER_ATTACHMENT | wgpu::TextureUsage::SAMPLED,
+        });
+
+        let layer_views = (0..MAX_CASCADES as u32)
+            .map(|layer| {
+                texture.create_view(&wgpu::TextureViewDescriptor {
+                    label: Some("shadow map layer view"),
+                    dimension: Some(wgpu::TextureViewDimension::D2),
+                    base_array_layer: layer,
+                    array_layer_count: NonZeroU32::new(1),
+                    ..Default::default()
+                })
+            })
+            .collect();
+
+        // view projection of each cascade for shadow pass
+        let cascade_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("cascade bind group layout for shadow"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStage::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let cascade_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("shadow cascade buffer"),
+            size: MAX_CASCADES as u64 * wgpu::BIND_BUFFER_ALIGNMENT,
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let cascade_bind_groups = (0..MAX_CASCADES as u64)
+            .map(|cascade| {
+                device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("shadow cascade bind group"),
+                    layout: &cascade_bind_group_layout,
+                    entries: &[wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                            buffer: &cascade_buffer,
+                            offset: cascade * wgpu::BIND_BUFFER_ALIGNMENT,
+                            size: NonZeroU64::new(std::mem::size_of::<Mat4>() as u64),
+                        }),
+                    }],
+                })
+            })
+            .collect();
+
+        // shadow map, comparison sampler and cascades for chunk shaders
+        let sample_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("shadow sample bind group layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Depth,
+                        view_dimension: wgpu::TextureViewDimension::D2Array,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler {
+                        filtering: true,
+                        comparison: true,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let sample_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("shadow sample buffer"),
+            size: std::mem::size_of::<ShadowUniform>() as u64,
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let array_view = texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("shadow map array view"),
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+
+        // linear filtering compares 2x2 texels in each tap
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("shadow sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            compare: Some(wgpu::CompareFunction::LessEqual),
+            ..Default::default()
+        });
+
+        let sample_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("shadow sample bind group"),
+            layout: &sample_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&array_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer: &sample_buffer,
+                        offset: 0,
+                        size: None,
+                    }),
+                },
+            ],
+        });
+
+        Self {
+            layer_views,
+            cascade_buffer,
+            cascade_bind_group_layout,
+            cascade_bind_groups,
+            sample_buffer,
+            sample_bind_group_layout,
+            sample_bind_group,
+            cascade_count: 0,
+            _texture: texture,
+        }
+    }
+
+    /// no cascade disables shadow in chunk shaders
+    pub fn update(&mut self, cascades: &[Cascade], queue: &wgpu::Queue) {
+        debug_assert!(cascades.len() <= MAX_CASCADES);
+        self.cascade_count = cascades.len();
+
+        let mut uniform = ShadowUniform {
+            view_projs: [Mat4::IDENTITY.to_cols_array(); MAX_CASCADES],
+            splits: [0.0; MAX_CASCADES],
+            params: [cascades.len() as f32, 1.0 / SHADOW_MAP_SIZE as f32, 0.0, 0.0],
+        };
+
+        for (i, cascade) in cascades.iter().enumerate() {
+            let view_proj = cascade.view_proj.to_cols_array();
+            uniform.view_projs[i] = view_proj;
+            uniform.splits[i] = cascade.far;
+
+            let offset = i as u64 * wgpu::BIND_BUFFER_ALIGNMENT;
+            queue.write_buffer(&self.cascade_buffer, offset, bytemuck::cast_slice(&view_proj));
+        }
+
+        queue.write_buffer(&self.sample_buffer, 0, bytemuck::bytes_of(&uniform));
+    }
+
+    pub fn cascade_count(&self) -> usize {
+        self.cascade_count
+    }
+
+    /// layers of cascades in use as depth attachments of shadow passes
+    pub fn import_layers<'r>(&'r self, graph: &mut RenderGraph<'r>) -> Vec<ResourceId> {
+        self.layer_views[..self.cascade_count]
+            .iter()
+            .map(|view| graph.import("shadow cascade", view))
+            .collect()
+    }
+
+    pub fn cascade_bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.cascade_bind_group_layout
+    }
+
+    pub fn cascade_bind_group(&self, cascade: usize) -> &wgpu::BindGroup {
+        &self.cascade_bind_groups[cascade]
+    }
+
+    pub fn sample_bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.sample_bind_group_layout
+    }
+
+    pub fn sample_bind_group(&self) -> &wgpu::BindGroup {
+        &self.sample_bind_group
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn camera() -> Camera {
+        let eye = Vec3::new(10.0, 20.0, -30.0);
+        let target = eye + Vec3::new(0.3, -0.2, 1.0).normalize();
+        let view = Mat4::look_at_lh(eye, target, Vec3::Y);
+        let proj = Mat4::perspective_lh(std::f32::consts::FRAC_PI_4, 16.0 / 9.0, 0.1, 100.0);
+        Camera {
+            eye,
+            target,
+            up: Vec3::Y,
+            aspect: 16.0 / 9.0,
+            fovy: std::f32::consts::FRAC_PI_4,
+            znear: 0.1,
+            zfar: 100.0,
+            view_proj_mat: proj * view,
+        }
+    }
+
+    #[test]
+    fn test_cascade_splits() {
+        let splits = cascade_splits(0.1, 100.0, 4, SPLIT_LAMBDA);
+        assert_eq!(splits.len(), 4);
+        assert!(splits.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(splits[0] > 0.1);
+        assert!((splits[3] - 100.0).abs() < 1e-3);
+
+        // near cascades are smaller than uniform split
+        assert!(splits[0] < 25.0);
+    }
+
+    #[test]
+    fn test_cascade_covers_frustum_slice() {
+        let camera = camera();
+        let sun_dir = Vec3::new(-0.3, -1.0, -0.5).normalize();
+        let cascades = build_cascades(&camera, sun_dir, &ShadowSettings::default());
+        assert_eq!(cascades.len(), 3);
+        assert!((cascades[2].far - 96.0).abs() < 1e-3);
+
+        let mut near = camera.znear;
+        for cascade in &cascades {
+            for corner in frustum_slice_corners(&camera, near, cascade.far).iter() {
+                let pos = cascade.view_proj.project_point3(*corner);
+                assert!(pos.x.abs() <= 1.0 && pos.y.abs() <= 1.0);
+                assert!((0.0..=1.0).contains(&pos.z));
+            }
+            near = cascade.far;
+        }
+    }
+
+    #[test]
+    fn test_cascade_snapped_to_texel() {
+        let sun_dir = Vec3::new(-0.3, -1.0, -0.5).normalize();
+        let mut corners = frustum_slice_corners(&camera(), 0.1, 20.0);
+        for offset in [0.0, 0.37, 1.91].iter() {
+            for corner in corners.iter_mut() {
+                *corner += Vec3::new(*offset, 0.0, *offset * 0.5);
+            }
+
+            // world origin stays on texel corner
+            let view_proj = fit_cascade(&corners, sun_dir, SHADOW_MAP_SIZE);
+            let origin = view_proj.project_point3(Vec3::ZERO) * (SHADOW_MAP_SIZE as f32 / 2.0);
+            assert!((origin.x - origin.x.round()).abs() < 1e-2);
+            assert!((origin.y - origin.y.round()).abs() < 1e-2);
+        }
+    }
+}
@@ -0,0 +1,320 @@
+use voxrs_asset::{AssetHash, AssetManager, ShaderAsset};
+use voxrs_math::*;
+use voxrs_rhi::{Texture, DEPTH_FORMAT, HDR_FORMAT};
+use voxrs_types::io::FileSystem;
+
+use crate::blueprint::{Camera, Environment};
+
+use super::render_graph::{FrameTargets, RenderGraph};
+
+/// procedural sky or skybox drawn behind everything with 1 full screen triangle
+pub struct SkyRenderer {
+    uniform_buffer: wgpu::Buffer,
+    uniform_bind_group: wgpu::BindGroup,
+    cubemap_bind_group_layout: wgpu::BindGroupLayout,
+    procedural_pipeline: wgpu::RenderPipeline,
+    cubemap_pipeline: wgpu::RenderPipeline,
+    cubemap_hash: Option<AssetHash>,  // skybox of cubemap
+    cubemap_error: Option<AssetHash>, // skybox whose failed load or build is logged
+    cubemap: Option<Cubemap>,
+}
+
+struct Cubemap {
+    bind_group: wgpu::BindGroup,
+    _texture: Texture,
+}
+
+/// camera to rebuild view rays and sky colors. vec3s are padded to vec4 for std140
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct SkyUniform {
+    inv_view_proj: [f32; 16],
+    eye: [f32; 4],
+    zenith_color: [f32; 4],
+    horizon_color: [f32; 4],
+    to_sun: [f32; 4],
+    sun_color: [f32; 4], // w : cos of sun disc radius
+}
+
+impl SkyUniform {
+    fn new(camera: &Camera, environment: &Environment) -> Self {
+        Self {
+            inv_view_proj: camera.view_proj_mat.inverse().to_cols_array(),
+            eye: camera.eye.extend(1.0).to_array(),
+            zenith_color: environment.sky_color.extend(1.0).to_array(),
+            horizon_color: environment.horizon_color.extend(1.0).to_array(),
+            to_sun: (-environment.sun_dir).extend(0.0).to_array(),
+            sun_color: environment.sun_color.extend(environment.sun_size.cos()).to_array(),
+        }
+    }
+}
+
+impl SkyRenderer {
+    pub fn new<F: FileSystem>(device: &wgpu::Device, asset_manager: &mut AssetManager<F>) -> Self {
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("sky uniform buffer"),
+            size: std::mem::size_of::<SkyUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let uniform_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("sky uniform bind group layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStage::VERTEX | wgpu::ShaderStage::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("sky uniform bind group"),
+            layout: &uniform_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        let cubemap_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("sky cubemap bind group layout"),
+            entries: &[
+                // cube texture
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::Cube,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                // sampler
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler {
+                        filtering: true,
+                        comparison: false,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let procedural_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("procedural sky pipeline layout"),
+            bind_group_layouts: &[&uniform_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let cubemap_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("cubemap sky pipeline layout"),
+            bind_group_layouts: &[&uniform_bind_group_layout, &cubemap_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let procedural_pipeline = create_sky_pipeline(
+            device,
+            &procedural_layout,
+            "assets/shaders/sky_shader.frag.spv",
+            asset_manager,
+        );
+        let cubemap_pipeline = create_sky_pipeline(
+            device,
+            &cubemap_layout,
+            "assets/shaders/sky_cubemap_shader.frag.spv",
+            asset_manager,
+        );
+
+        Self {
+            uniform_buffer,
+            uniform_bind_group,
+            cubemap_bind_group_layout,
+            procedural_pipeline,
+            cubemap_pipeline,
+            cubemap_hash: None,
+            cubemap_error: None,
+            cubemap: None,
+        }
+    }
+
+    /// procedural sky is drawn until changed skybox and its faces are loaded and built
+    /// never blocks on loading skybox
+    #[profiling::function]
+    pub fn prepare(&mut self, camera: &Camera, environment: &Environment, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let uniform = SkyUniform::new(camera, environment);
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+
+        let hash = environment.skybox.as_ref().map(|handle| handle.asset_hash());
+        if hash == self.cubemap_hash {
+            return;
+        }
+        self.cubemap = None;
+
+        let handle = match &environment.skybox {
+            Some(handle) => handle,
+            None => {
+                self.cubemap_hash = None;
+                self.cubemap_error = None;
+                return;
+            }
+        };
+
+        // failed build is not retried until skybox is changed, because loaded faces don't change
+        if hash == self.cubemap_error || !handle.is_loaded() {
+            return;
+        }
+        if handle.is_failed() {
+            self.fail_cubemap(hash, "skybox is not loaded");
+            return;
+        }
+
+        let asset = handle.get_asset();
+        if !asset.is_loaded() {
+            return;
+        }
+
+        match asset.build_texture(device, queue) {
+            Ok(texture) => {
+                let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("sky cubemap bind group"),
+                    layout: &self.cubemap_bind_group_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: wgpu::BindingResource::TextureView(&texture.view),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: wgpu::BindingResource::Sampler(&texture.sampler),
+                        },
+                    ],
+                });
+                self.cubemap = Some(Cubemap {
+                    bind_group,
+                    _texture: texture,
+                });
+                self.cubemap_hash = hash;
+            }
+            Err(err) => self.fail_cubemap(hash, err),
+        }
+    }
+
+    /// skybox is failed to load or build. draw procedural sky, and log once per skybox
+    fn fail_cubemap(&mut self, hash: Option<AssetHash>, err: impl std::fmt::Display) {
+        if self.cubemap_error != hash {
+            log::error!("skybox build error. draw procedural sky. err: {}", err);
+            self.cubemap_error = hash;
+        }
+    }
+
+    /// first pass of frame. clears depth which later passes test against
+    pub(crate) fn add_pass<'r>(&'r self, targets: FrameTargets, graph: &mut RenderGraph<'r>) {
+        graph
+            .add_pass("sky pass")
+            .color(targets.color, wgpu::LoadOp::Clear(wgpu::Color::BLACK))
+            .depth(targets.depth, wgpu::LoadOp::Clear(1.0))
+            .record(move |pass, _| self.render(pass));
+    }
+
+    #[profiling::function]
+    pub fn render<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        match &self.cubemap {
+            Some(cubemap) => {
+                render_pass.set_pipeline(&self.cubemap_pipeline);
+                render_pass.set_bind_group(1, &cubemap.bind_group, &[]);
+            }
+            None => render_pass.set_pipeline(&self.procedural_pipeline),
+        }
+        render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+/// sky is drawn first without depth write. everything drawn later covers it
+fn create_sky_pipeline<F: FileSystem>(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    frag_shader: &str,
+    asset_manager: &mut AssetManager<F>,
+) -> wgpu::RenderPipeline {
+    let vs_handle = asset_manager.get::<ShaderAsset>(&"assets/shaders/sky_shader.vert.spv".into());
+    let fs_handle = asset_manager.get::<ShaderAsset>(&frag_shader.into());
+    let vs_asset = vs_handle.get_asset();
+    let fs_asset = fs_handle.get_asset();
+    let vs_module = vs_asset.module.as_ref().unwrap();
+    let fs_module = fs_asset.module.as_ref().unwrap();
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("sky pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: &vs_module,
+            entry_point: "main",
+            buffers: &[],
+        },
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Cw,
+            cull_mode: None,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            clamp_depth: false,
+            conservative: false,
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: false,
+            depth_compare: wgpu::CompareFunction::Always,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &fs_module,
+            entry_point: "main",
+            targets: &[wgpu::ColorTargetState {
+                format: HDR_FORMAT,
+                blend: None,
+                write_mask: wgpu::ColorWrite::ALL,
+            }],
+        }),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_view_ray_from_far_plane() {
+        let eye = Vec3::new(10.0, 20.0, 30.0);
+        let target = Vec3::new(15.0, 18.0, 40.0);
+        let camera = Camera {
+            eye,
+            target,
+            view_proj_mat: Mat4::perspective_lh(1.0, 1.5, 0.1, 100.0) * Mat4::look_at_lh(eye, target, Vec3::Y),
+            ..Default::default()
+        };
+        let uniform = SkyUniform::new(&camera, &Environment::default());
+
+        // center of screen looks at target like sky vertex shader rebuilds it
+        let inv_view_proj = Mat4::from_cols_array(&uniform.inv_view_proj);
+        let far = inv_view_proj * Vec4::new(0.0, 0.0, 1.0, 1.0);
+        let dir = (far.truncate() / far.w - eye).normalize();
+        assert!(dir.dot((target - eye).normalize()) > 0.9999);
+
+        let to_sun = Vec3::new(uniform.to_sun[0], uniform.to_sun[1], uniform.to_sun[2]);
+        assert!(to_sun.dot(Environment::default().sun_dir) < -0.9999);
+    }
+}
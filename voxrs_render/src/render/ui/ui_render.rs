@@ -1,7 +1,13 @@
 use voxrs_asset::AssetManager;
 use voxrs_types::io::FileSystem;
 
-use crate::{blueprint::Ui, render::CommonUniforms};
+use crate::{
+    blueprint::Ui,
+    render::{
+        render_graph::{FrameTargets, RenderGraph},
+        CommonUniforms,
+    },
+};
 
 use super::{panel_render::PanelRenderInfo, text_render::TextRenderInfo, PanelRenderer, TextRenderer};
 
@@ -59,6 +65,20 @@ impl UiRenderer {
         }
     }
 
+    /// ui is drawn over everything. depth is attached for pipelines but never tested
+    pub(crate) fn add_pass<'r>(
+        &'r self,
+        render_infos: &'r [UiRenderInfo],
+        targets: FrameTargets,
+        graph: &mut RenderGraph<'r>,
+    ) {
+        graph
+            .add_pass("ui pass")
+            .color(targets.color, wgpu::LoadOp::Load)
+            .depth(targets.depth, wgpu::LoadOp::Load)
+            .record(move |pass, _| self.render(render_infos, pass));
+    }
+
     pub fn clear(&mut self) {
         self.panel_renderer.clear();
         self.text_renderer.clear();
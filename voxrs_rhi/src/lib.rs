@@ -1,6 +1,7 @@
 mod texture;
 pub use texture::Texture;
 pub use texture::DEPTH_FORMAT;
+pub use texture::HDR_FORMAT;
 
 mod dynamic_texture;
 pub use dynamic_texture::DynamicTexture;
@@ -5,6 +5,8 @@ use image::GenericImageView;
 
 //pub const COLOR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Bgra8UnormSrgb;
 pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+/// scene is drawn in linear hdr, then tone mapped to swap chain
+pub const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
 pub struct Texture {
     pub texture: wgpu::Texture,
     pub view: wgpu::TextureView,
@@ -59,6 +61,85 @@ impl Texture {
         );
 
         let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        // repeat for tiled uv of merged chunk faces
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            address_mode_w: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Ok(Self { texture, view, sampler })
+    }
+
+    /// 2d array texture with 1 layer for each image
+    pub fn array_from_bytes(device: &wgpu::Device, queue: &wgpu::Queue, bytes: &[&[u8]], label: &str) -> Result<Self> {
+        let imgs = bytes
+            .iter()
+            .map(|bytes| image::load_from_memory(bytes))
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::array_from_images(device, queue, &imgs, Some(label))
+    }
+
+    /// images are resized to largest width and height among them
+    pub fn array_from_images(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        imgs: &[image::DynamicImage],
+        label: Option<&str>,
+    ) -> Result<Self> {
+        ensure!(!imgs.is_empty(), "texture array without image");
+
+        let width = imgs.iter().map(|img| img.width()).max().unwrap();
+        let height = imgs.iter().map(|img| img.height()).max().unwrap();
+        let texture = Self::create_layers(device, queue, imgs, width, height, label);
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+        // repeat for tiled uv of merged chunk faces
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            address_mode_w: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Ok(Self { texture, view, sampler })
+    }
+
+    /// cube texture from 6 faces in +x, -x, +y, -y, +z, -z order
+    pub fn cube_from_bytes(device: &wgpu::Device, queue: &wgpu::Queue, bytes: &[&[u8]], label: &str) -> Result<Self> {
+        let imgs = bytes
+            .iter()
+            .map(|bytes| image::load_from_memory(bytes))
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::cube_from_images(device, queue, &imgs, Some(label))
+    }
+
+    /// faces are resized to square of largest dimension among them
+    pub fn cube_from_images(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        imgs: &[image::DynamicImage],
+        label: Option<&str>,
+    ) -> Result<Self> {
+        ensure!(imgs.len() == 6, "cube texture needs 6 faces, got {}", imgs.len());
+
+        let size = imgs.iter().map(|img| img.width().max(img.height())).max().unwrap();
+        let texture = Self::create_layers(device, queue, imgs, size, size, label);
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..Default::default()
+        });
         let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
             address_mode_u: wgpu::AddressMode::ClampToEdge,
             address_mode_v: wgpu::AddressMode::ClampToEdge,
@@ -72,6 +153,65 @@ impl Texture {
         Ok(Self { texture, view, sampler })
     }
 
+    /// 2d texture with 1 layer for each image, resized to width and height
+    fn create_layers(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        imgs: &[image::DynamicImage],
+        width: u32,
+        height: u32,
+        label: Option<&str>,
+    ) -> wgpu::Texture {
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: imgs.len() as u32,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label,
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
+        });
+
+        for (layer, img) in imgs.iter().enumerate() {
+            let rgba = if img.dimensions() == (width, height) {
+                img.to_rgba8()
+            } else {
+                img.resize_exact(width, height, image::imageops::FilterType::Triangle)
+                    .to_rgba8()
+            };
+
+            queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    texture: &texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d {
+                        x: 0,
+                        y: 0,
+                        z: layer as u32,
+                    },
+                },
+                &rgba,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(4 * width),
+                    rows_per_image: NonZeroU32::new(height),
+                },
+                wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+
+        texture
+    }
+
     pub fn create_depth_texture(
         device: &wgpu::Device,
         swap_chain_desc: &wgpu::SwapChainDescriptor,
@@ -115,6 +115,10 @@ pub mod tests {
                     let s = include_str!("../../test_assets/world_block.wb");
                     Ok(s.to_string())
                 }
+                Some("skybox.cube") => {
+                    let s = include_str!("../../test_assets/skybox.cube");
+                    Ok(s.to_string())
+                }
                 _ => panic!("not found"),
             }
         }